@@ -1,37 +1,189 @@
 pub use cqrs_core::Aggregate;
-use cqrs_core::AggregateEvent;
+use cqrs_core::{AggregateEvent, AggregateId, Event};
 
+use std::fmt;
+
+use crate::events::requests::RequestsEvent;
+use crate::events::shape::ShapeEvent;
 use crate::events::OpticEvent;
+use crate::projections::subscriptions::Projection;
+use crate::state::provenance::EntityId;
+use crate::state::requests::{PathComponentId, RequestId, RequestParameterId, ResponseId};
+use crate::state::shape::{FieldId, ParameterShapeDescriptor, ShapeId};
 
+pub mod provenance;
 pub mod requests;
 pub mod rfc;
 pub mod shape;
+pub mod undo;
 
 // rename this to RfcState.. but then what is RfcState?
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct OpticState<'a> {
   pub requests: &'a requests::RequestsState,
   pub rfc: &'a rfc::RfcState,
   pub shape: &'a shape::ShapeState,
+  // not part of the spec itself, so left out of its JSON
+  #[serde(skip)]
+  pub provenance: &'a provenance::ProvenanceState,
 }
 
-#[derive(Default)]
+#[derive(Default, Serialize, Deserialize)]
 pub struct OpticAggregate {
   requests: requests::RequestsAggregate,
   rfc: rfc::RfcAggregate,
   shape: shape::ShapeAggregate,
+  provenance: provenance::ProvenanceState,
+  // read models updated as events are applied, which aren't part of the aggregate's snapshots
+  #[serde(skip)]
+  projections: Vec<Box<dyn Projection>>,
 }
 
 impl OpticAggregate {
-  pub fn get_state(&self) -> OpticState {
+  // The aggregate a stream of events folds into
+  pub fn fold(events: impl IntoIterator<Item = OpticEvent>) -> Self {
+    let mut aggregate = OpticAggregate::default();
+    for event in events {
+      aggregate.apply(event);
+    }
+    aggregate
+  }
+
+  pub fn get_state(&self) -> OpticState<'_> {
     OpticState {
       requests: self.requests.get_state(),
       rfc: self.rfc.get_state(),
       shape: self.shape.get_state(),
+      provenance: &self.provenance,
+    }
+  }
+
+  pub fn subscribe(&mut self, projection: Box<dyn Projection>) {
+    self.projections.push(projection);
+  }
+
+  // Applies an event, returning the events that reverse what it overwrote. An event that can't be
+  // reversed isn't applied.
+  pub fn apply_reversibly(
+    &mut self,
+    event: OpticEvent,
+  ) -> Result<Vec<OpticEvent>, undo::Irreversible> {
+    // a rejected event changes nothing, so there's nothing to reverse
+    if self.validate(&event).is_err() {
+      self.apply(event);
+      return Ok(vec![]);
+    }
+    let reverse_events = undo::compensating_events(&self.get_state(), &event)?;
+    self.apply(event);
+    Ok(reverse_events)
+  }
+
+  // Events that can't be applied to the state, like a duplicate request parameter or changes to an
+  // entity that doesn't exist, are rejected before they're applied or recorded anywhere
+  pub fn validate(&self, event: &OpticEvent) -> Result<(), String> {
+    let (requests, shapes) = (self.requests.get_state(), self.shape.get_state());
+    let exists = |exists: bool, entity: &dyn fmt::Display| {
+      if exists {
+        Ok(())
+      } else {
+        Err(format!("{} doesn't exist", entity))
+      }
+    };
+    let path_exists = |path_id: PathComponentId| {
+      exists(
+        requests.path_component(path_id).is_some(),
+        &EntityId::PathComponent(path_id),
+      )
+    };
+    let parameter_exists = |parameter_id: RequestParameterId| {
+      exists(
+        requests.request_parameter(parameter_id).is_some(),
+        &EntityId::RequestParameter(parameter_id),
+      )
+    };
+    let request_exists = |request_id: RequestId| {
+      exists(
+        requests.request(request_id).is_some(),
+        &EntityId::Request(request_id),
+      )
+    };
+    let response_exists = |response_id: ResponseId| {
+      exists(
+        requests.response(response_id).is_some(),
+        &EntityId::Response(response_id),
+      )
+    };
+    let shape_exists =
+      |shape_id: ShapeId| exists(shapes.shape(shape_id).is_some(), &EntityId::Shape(shape_id));
+    let field_exists =
+      |field_id: FieldId| exists(shapes.field(field_id).is_some(), &EntityId::Field(field_id));
+
+    match event {
+      OpticEvent::RequestsEvent(event) => match event {
+        RequestsEvent::PathComponentRenamed(e) => path_exists(e.path_id),
+        RequestsEvent::PathComponentRemoved(e) => path_exists(e.path_id),
+        RequestsEvent::PathParameterShapeSet(e) => match requests.path_component(e.path_id) {
+          Some(component) if component.descriptor.is_parameter() => Ok(()),
+          Some(_) => Err(format!("path {} isn't a path parameter", e.path_id)),
+          None => path_exists(e.path_id),
+        },
+        RequestsEvent::PathParameterRenamed(e) => path_exists(e.path_id),
+        RequestsEvent::PathParameterRemoved(e) => path_exists(e.path_id),
+        RequestsEvent::RequestParameterAddedByPathAndMethod(e) => requests
+          .validate_request_parameter(e.path_id, &e.http_method, e.parameter_location, &e.name)
+          .map_err(|err| err.to_string()),
+        RequestsEvent::RequestParameterShapeSet(e) => parameter_exists(e.parameter_id),
+        RequestsEvent::RequestParameterShapeUnset(e) => parameter_exists(e.parameter_id),
+        RequestsEvent::RequestParameterRenamed(e) => parameter_exists(e.parameter_id),
+        RequestsEvent::RequestParameterRemoved(e) => parameter_exists(e.parameter_id),
+        RequestsEvent::RequestContentTypeSet(e) => request_exists(e.request_id),
+        RequestsEvent::RequestBodySet(e) => request_exists(e.request_id),
+        RequestsEvent::RequestBodyUnset(e) => request_exists(e.request_id),
+        RequestsEvent::RequestRemoved(e) => request_exists(e.request_id),
+        RequestsEvent::ResponseStatusCodeSet(e) => response_exists(e.response_id),
+        RequestsEvent::ResponseContentTypeSet(e) => response_exists(e.response_id),
+        RequestsEvent::ResponseBodySet(e) => response_exists(e.response_id),
+        RequestsEvent::ResponseBodyUnset(e) => response_exists(e.response_id),
+        RequestsEvent::ResponseRemoved(e) => response_exists(e.response_id),
+        _ => Ok(()),
+      },
+      OpticEvent::ShapeEvent(event) => match event {
+        ShapeEvent::BaseShapeSet(e) => shape_exists(e.shape_id),
+        ShapeEvent::ShapeRenamed(e) => shape_exists(e.shape_id),
+        ShapeEvent::ShapeRemoved(e) => shape_exists(e.shape_id),
+        ShapeEvent::FieldAdded(e) => shape_exists(e.shape_id),
+        ShapeEvent::FieldShapeSet(e) => field_exists(e.shape_descriptor.field_id()),
+        ShapeEvent::FieldRenamed(e) => field_exists(e.field_id),
+        ShapeEvent::FieldRemoved(e) => field_exists(e.field_id),
+        ShapeEvent::ShapeParameterShapeSet(e) => match &e.shape_descriptor {
+          ParameterShapeDescriptor::ProviderInShape(binding) => shape_exists(binding.shape_id),
+          ParameterShapeDescriptor::ProviderInField(binding) => field_exists(binding.field_id),
+        },
+        _ => Ok(()),
+      },
+      OpticEvent::RfcEvent(_) => Ok(()),
+    }
+  }
+
+  fn apply_unobserved(&mut self, event: OpticEvent) {
+    self.provenance.apply(&event);
+    match event {
+      OpticEvent::RequestsEvent(evt) => self.requests.apply(evt),
+      OpticEvent::RfcEvent(evt) => self.rfc.apply(evt),
+      OpticEvent::ShapeEvent(evt) => self.shape.apply(evt),
     }
   }
 }
 
+// identifies the spec an aggregate was folded from, so snapshots can't be restored for another spec
+pub struct OpticAggregateId(pub String);
+
+impl AggregateId<OpticAggregate> for OpticAggregateId {
+  fn as_str(&self) -> &str {
+    &self.0
+  }
+}
+
 impl Aggregate for OpticAggregate {
   #[inline(always)]
   fn aggregate_type() -> &'static str
@@ -44,10 +196,259 @@ impl Aggregate for OpticAggregate {
 
 impl AggregateEvent<OpticAggregate> for OpticEvent {
   fn apply_to(self, aggregate: &mut OpticAggregate) {
-    match self {
-      OpticEvent::RequestsEvent(evt) => aggregate.requests.apply(evt),
-      OpticEvent::RfcEvent(evt) => aggregate.rfc.apply(evt),
-      OpticEvent::ShapeEvent(evt) => aggregate.shape.apply(evt),
+    if let Err(err) = aggregate.validate(&self) {
+      return eprintln!("Rejected '{}' event: {}", self.event_type(), err);
+    }
+    if aggregate.projections.is_empty() {
+      return aggregate.apply_unobserved(self);
     }
+
+    // the event is applied to a clone, so the original can still be shown to the projections
+    let mut projections = std::mem::take(&mut aggregate.projections);
+    for projection in &mut projections {
+      projection.before_apply(&self, &aggregate.get_state());
+    }
+    aggregate.apply_unobserved(self.clone());
+    for projection in &mut projections {
+      projection.after_apply(&self, &aggregate.get_state());
+    }
+    aggregate.projections = projections;
   }
 }
+
+#[test]
+fn folding_is_deterministic_across_runs() {
+  let fold = || {
+    OpticAggregate::fold(crate::events_from_file("test-fixtures/uncompacted-spec.json").unwrap())
+  };
+  let (first, second) = (fold(), fold());
+
+  assert_eq!(
+    format!("{:?}", first.get_state()),
+    format!("{:?}", second.get_state())
+  );
+  assert_eq!(
+    serde_json::to_string(&first).unwrap(),
+    serde_json::to_string(&second).unwrap()
+  );
+}
+
+#[test]
+fn requests_are_listed_in_event_order() {
+  use crate::events::requests::RequestsEvent;
+
+  let events = crate::events_from_file("test-fixtures/uncompacted-spec.json").unwrap();
+  let added_request_ids: Vec<String> = events
+    .iter()
+    .filter_map(|event| match event {
+      OpticEvent::RequestsEvent(RequestsEvent::RequestAdded(e)) => Some(e.request_id.to_string()),
+      _ => None,
+    })
+    .collect();
+
+  let aggregate = OpticAggregate::fold(events);
+  let listed_request_ids: Vec<String> = aggregate
+    .get_state()
+    .requests
+    .all_requests()
+    .map(|request| request.request_id.to_string())
+    .collect();
+
+  assert_eq!(listed_request_ids, added_request_ids);
+}
+
+#[test]
+fn path_parameters_are_shaped() {
+  use crate::state::requests::{PathComponentDescriptor, PathComponentId};
+
+  let aggregate =
+    OpticAggregate::fold(crate::events_from_file("test-fixtures/uncompacted-spec.json").unwrap());
+  let season = aggregate
+    .get_state()
+    .requests
+    .path_component(PathComponentId::from("path_UslYN0iwbI"))
+    .unwrap();
+  match &season.descriptor {
+    PathComponentDescriptor::Parameterized(descriptor) => {
+      let shaped = descriptor.shape_descriptor.shaped().unwrap();
+      assert_eq!(shaped.shape_id.as_str(), "shape_Ba53AWXhVW");
+    }
+    PathComponentDescriptor::Basic(_) => panic!("season must be a path parameter"),
+  }
+}
+
+#[test]
+fn duplicate_request_parameters_are_rejected() {
+  use crate::state::http::HttpMethod;
+  use crate::state::provenance::EntityId;
+  use crate::state::requests::{ParameterLocation, PathComponentId};
+
+  let mut aggregate =
+    OpticAggregate::fold(crate::events_from_file("test-fixtures/uncompacted-spec.json").unwrap());
+  let parameter_added = |parameter_id: &str, location: &str, name: &str| -> OpticEvent {
+    serde_json::from_value(serde_json::json!({
+      "RequestParameterAddedByPathAndMethod": {
+        "parameterId": parameter_id,
+        "pathId": "path_UslYN0iwbI",
+        "httpMethod": "GET",
+        "parameterLocation": location,
+        "name": name,
+        "eventContext": null,
+      }
+    }))
+    .unwrap()
+  };
+  for event in [
+    parameter_added("duplicate_query", "query", "queryString"),
+    parameter_added("header", "header", "X-Season"),
+    parameter_added("duplicate_header", "header", "x-season"),
+    parameter_added("cookie", "cookie", "queryString"),
+  ] {
+    aggregate.apply(event);
+  }
+  // changes to a rejected parameter are rejected with it
+  let followups: Vec<OpticEvent> = serde_json::from_value(serde_json::json!([
+    {"RequestParameterShapeSet": {"parameterId": "duplicate_query", "parameterDescriptor": {"shapeId": "$string", "isRemoved": false}, "eventContext": null}},
+    {"RequestParameterRenamed": {"parameterId": "duplicate_query", "name": "season", "eventContext": null}},
+    {"RequestParameterRemoved": {"parameterId": "duplicate_query", "eventContext": null}},
+  ]))
+  .unwrap();
+  for event in followups {
+    assert_eq!(
+      aggregate.validate(&event),
+      Err(String::from(
+        "request parameter duplicate_query doesn't exist"
+      ))
+    );
+    aggregate.apply(event);
+  }
+
+  let state = aggregate.get_state();
+  let rejected = EntityId::RequestParameter(RequestParameterId::from("duplicate_query"));
+  assert!(state.provenance.blame(rejected).is_none());
+  let requests = state.requests;
+  let names_at = |location| -> Vec<&str> {
+    requests
+      .request_parameters_at(
+        PathComponentId::from("path_UslYN0iwbI"),
+        &HttpMethod::Get,
+        location,
+      )
+      .map(|parameter| parameter.request_parameter_descriptor.name.as_str())
+      .collect()
+  };
+  assert_eq!(names_at(ParameterLocation::Query), vec!["queryString"]);
+  assert_eq!(names_at(ParameterLocation::Header), vec!["X-Season"]);
+  assert_eq!(names_at(ParameterLocation::Cookie), vec!["queryString"]);
+
+  let invalid_location = OpticEvent::from_value(&serde_json::json!({
+    "RequestParameterAddedByPathAndMethod": {
+      "parameterId": "body",
+      "pathId": "path_UslYN0iwbI",
+      "httpMethod": "GET",
+      "parameterLocation": "body",
+      "name": "name",
+      "eventContext": null,
+    }
+  }));
+  match invalid_location {
+    Err(err) => assert!(err.starts_with("unknown variant `body`"), "{}", err),
+    Ok(_) => panic!("a parameter in the body must be rejected"),
+  }
+}
+
+#[test]
+fn changes_to_entities_that_dont_exist_are_rejected() {
+  let mut aggregate =
+    OpticAggregate::fold(crate::events_from_file("test-fixtures/uncompacted-spec.json").unwrap());
+  let before = serde_json::to_string(&aggregate).unwrap();
+  let events: Vec<OpticEvent> = serde_json::from_value(serde_json::json!([
+    {"PathComponentRenamed": {"pathId": "missing_path", "name": "drivers", "eventContext": null}},
+    {"PathParameterShapeSet": {"pathId": "path_7K06UY59re", "shapeDescriptor": {"shapeId": "$string", "isRemoved": false}, "eventContext": null}},
+    {"RequestBodyUnset": {"requestId": "missing_request", "eventContext": null}},
+    {"ResponseStatusCodeSet": {"responseId": "missing_response", "httpStatusCode": 404, "eventContext": null}},
+    {"ShapeRemoved": {"shapeId": "missing_shape", "eventContext": null}},
+    {"FieldRenamed": {"fieldId": "missing_field", "name": "name", "eventContext": null}},
+  ]))
+  .unwrap();
+  let errors: Vec<String> = events
+    .iter()
+    .map(|event| aggregate.validate(event).unwrap_err())
+    .collect();
+  assert_eq!(
+    errors,
+    vec![
+      "path missing_path doesn't exist",
+      "path path_7K06UY59re isn't a path parameter",
+      "request missing_request doesn't exist",
+      "response missing_response doesn't exist",
+      "shape missing_shape doesn't exist",
+      "field missing_field doesn't exist",
+    ]
+  );
+
+  // applied anyway, they're left out instead of panicking
+  for event in events {
+    aggregate.apply(event);
+  }
+  assert_eq!(serde_json::to_string(&aggregate).unwrap(), before);
+}
+
+#[test]
+fn entities_are_blamed_on_the_events_that_created_and_changed_them() {
+  use crate::state::provenance::EntityId;
+  use crate::state::shape::FieldId;
+
+  let mut aggregate =
+    OpticAggregate::fold(crate::events_from_file("test-fixtures/uncompacted-spec.json").unwrap());
+  let field_id = EntityId::Field(FieldId::from("EQSZqM_12"));
+  let blame = aggregate
+    .get_state()
+    .provenance
+    .blame(field_id)
+    .unwrap()
+    .clone();
+  let created = blame.created.unwrap();
+  let context = created.event_context.as_ref().unwrap();
+  assert_eq!(created.event_type, "FieldAdded");
+  assert_eq!(
+    context.client_command_batch_id(),
+    "ee02a716-7a3a-43d2-b1b2-b0a8259f071f"
+  );
+  assert_eq!(context.created_at(), "2020-04-08T09:23:41.638Z");
+  // attributed to the batch commit it's part of, like blame does
+  let batch = created.batch.as_ref().unwrap();
+  assert_eq!(batch.batch_id, "3960ebac-7dba-4118-97df-3af91005dc61");
+  assert!(batch.commit_message.contains("Added 200 Response"));
+  assert!(created
+    .to_string()
+    .ends_with("(batch 3960ebac-7dba-4118-97df-3af91005dc61)"));
+
+  aggregate.apply(
+    serde_json::from_value::<OpticEvent>(serde_json::json!({
+      "FieldRenamed": {
+        "fieldId": "EQSZqM_12",
+        "name": "countryCode",
+        "eventContext": {
+          "clientId": "someone-else",
+          "clientSessionId": "session",
+          "clientCommandBatchId": "batch",
+          "createdAt": "2020-08-01T12:00:00Z"
+        }
+      }
+    }))
+    .unwrap(),
+  );
+  let state = aggregate.get_state();
+  let blame = state.provenance.blame(field_id).unwrap();
+  let last_modified = blame.last_modified.event_context.as_ref().unwrap();
+  assert_eq!(blame.created.as_ref().unwrap().event_type, "FieldAdded");
+  assert_eq!(blame.last_modified.event_type, "FieldRenamed");
+  assert_eq!(last_modified.client_id(), "someone-else");
+
+  // the root path was never added
+  let root = state.provenance.blame(EntityId::PathComponent(
+    crate::state::requests::PathComponentId::from("root"),
+  ));
+  assert!(root.is_none());
+}
@@ -0,0 +1,161 @@
+use cqrs_core::Event;
+
+use crate::events::requests::RequestsEvent;
+use crate::events::rfc::RfcEvent;
+use crate::events::shape::ShapeEvent;
+use crate::events::OpticEvent;
+pub use crate::state::provenance::ProvenanceState;
+use crate::state::provenance::{Change, EntityId};
+use crate::state::shape::{FieldShapeDescriptor, ParameterShapeDescriptor, ShapeId};
+
+impl ProvenanceState {
+  pub fn apply(&mut self, event: &OpticEvent) {
+    match event {
+      OpticEvent::RfcEvent(RfcEvent::BatchCommitStarted(e)) => {
+        self.with_batch_started(&e.batch_id, &e.commit_message)
+      }
+      OpticEvent::RfcEvent(RfcEvent::BatchCommitEnded(_)) => self.with_batch_ended(),
+      _ => {}
+    }
+
+    for (entity_id, is_creation) in changed_entities(event) {
+      let change = Change {
+        event_type: String::from(event.event_type()),
+        event_context: event.event_context().cloned(),
+        batch: self.current_batch().cloned(),
+      };
+      self.with_change(entity_id, change, is_creation);
+    }
+  }
+}
+
+// The entities an event changes, and whether it creates them. Adding something to an entity (like
+// a request to a path) doesn't change the entity itself.
+pub fn changed_entities(event: &OpticEvent) -> Vec<(EntityId, bool)> {
+  let created = |entity_id| vec![(entity_id, true)];
+  let changed = |entity_id| vec![(entity_id, false)];
+
+  match event {
+    OpticEvent::RequestsEvent(event) => match event {
+      RequestsEvent::PathComponentAdded(e) => created(EntityId::PathComponent(e.path_id)),
+      RequestsEvent::PathComponentRenamed(e) => changed(EntityId::PathComponent(e.path_id)),
+      RequestsEvent::PathComponentRemoved(e) => changed(EntityId::PathComponent(e.path_id)),
+      RequestsEvent::PathParameterAdded(e) => created(EntityId::PathComponent(e.path_id)),
+      RequestsEvent::PathParameterShapeSet(e) => changed(EntityId::PathComponent(e.path_id)),
+      RequestsEvent::PathParameterRenamed(e) => changed(EntityId::PathComponent(e.path_id)),
+      RequestsEvent::PathParameterRemoved(e) => changed(EntityId::PathComponent(e.path_id)),
+      RequestsEvent::RequestParameterAddedByPathAndMethod(e) => {
+        created(EntityId::RequestParameter(e.parameter_id))
+      }
+      RequestsEvent::RequestParameterRenamed(e) => {
+        changed(EntityId::RequestParameter(e.parameter_id))
+      }
+      RequestsEvent::RequestParameterShapeSet(e) => {
+        changed(EntityId::RequestParameter(e.parameter_id))
+      }
+      RequestsEvent::RequestParameterShapeUnset(e) => {
+        changed(EntityId::RequestParameter(e.parameter_id))
+      }
+      RequestsEvent::RequestParameterRemoved(e) => {
+        changed(EntityId::RequestParameter(e.parameter_id))
+      }
+      RequestsEvent::RequestAdded(e) => created(EntityId::Request(e.request_id)),
+      RequestsEvent::RequestContentTypeSet(e) => changed(EntityId::Request(e.request_id)),
+      RequestsEvent::RequestBodySet(e) => changed(EntityId::Request(e.request_id)),
+      RequestsEvent::RequestBodyUnset(e) => changed(EntityId::Request(e.request_id)),
+      RequestsEvent::RequestRemoved(e) => changed(EntityId::Request(e.request_id)),
+      RequestsEvent::ResponseAddedByPathAndMethod(e) => created(EntityId::Response(e.response_id)),
+      RequestsEvent::ResponseStatusCodeSet(e) => changed(EntityId::Response(e.response_id)),
+      RequestsEvent::ResponseContentTypeSet(e) => changed(EntityId::Response(e.response_id)),
+      RequestsEvent::ResponseBodySet(e) => changed(EntityId::Response(e.response_id)),
+      RequestsEvent::ResponseBodyUnset(e) => changed(EntityId::Response(e.response_id)),
+      RequestsEvent::ResponseRemoved(e) => changed(EntityId::Response(e.response_id)),
+    },
+    OpticEvent::ShapeEvent(event) => match event {
+      ShapeEvent::ShapeAdded(e) => created(EntityId::Shape(e.shape_id)),
+      ShapeEvent::BaseShapeSet(e) => changed(EntityId::Shape(e.shape_id)),
+      ShapeEvent::ShapeRenamed(e) => changed(EntityId::Shape(e.shape_id)),
+      ShapeEvent::ShapeRemoved(e) => changed(EntityId::Shape(e.shape_id)),
+      ShapeEvent::ShapeParameterAdded(e) => created(EntityId::ShapeParameter(e.shape_parameter_id)),
+      ShapeEvent::ShapeParameterShapeSet(e) => match &e.shape_descriptor {
+        ParameterShapeDescriptor::ProviderInShape(binding) => {
+          changed(EntityId::Shape(binding.shape_id))
+        }
+        ParameterShapeDescriptor::ProviderInField(binding) => {
+          changed(EntityId::Field(binding.field_id))
+        }
+      },
+      ShapeEvent::ShapeParameterRenamed(e) => {
+        changed(EntityId::ShapeParameter(e.shape_parameter_id))
+      }
+      ShapeEvent::ShapeParameterRemoved(e) => {
+        changed(EntityId::ShapeParameter(e.shape_parameter_id))
+      }
+      ShapeEvent::FieldAdded(e) => created(EntityId::Field(e.field_id)),
+      ShapeEvent::FieldShapeSet(e) => match &e.shape_descriptor {
+        FieldShapeDescriptor::FieldShapeFromShape(descriptor) => {
+          changed(EntityId::Field(descriptor.field_id))
+        }
+        FieldShapeDescriptor::FieldShapeFromParameter(descriptor) => {
+          changed(EntityId::Field(descriptor.field_id))
+        }
+      },
+      ShapeEvent::FieldRenamed(e) => changed(EntityId::Field(e.field_id)),
+      ShapeEvent::FieldRemoved(e) => changed(EntityId::Field(e.field_id)),
+    },
+    OpticEvent::RfcEvent(event) => match event {
+      RfcEvent::ContributionAdded(_)
+      | RfcEvent::APINamed(_)
+      | RfcEvent::GitStateSet(_)
+      | RfcEvent::BatchCommitStarted(_)
+      | RfcEvent::BatchCommitEnded(_) => vec![],
+    },
+  }
+}
+
+// The entities an event refers to without changing them, like the path a request is added to or
+// the shapes a field or body is shaped by
+pub fn referenced_entities(event: &OpticEvent) -> Vec<EntityId> {
+  let path = |path_id| vec![EntityId::PathComponent(path_id)];
+  let shape = |shape_id| vec![EntityId::Shape(shape_id)];
+  let shapes = |shape_ids: &[Option<ShapeId>]| -> Vec<EntityId> {
+    shape_ids
+      .iter()
+      .flatten()
+      .copied()
+      .map(EntityId::Shape)
+      .collect()
+  };
+
+  match event {
+    OpticEvent::RequestsEvent(event) => match event {
+      RequestsEvent::PathComponentAdded(e) => path(e.parent_path_id),
+      RequestsEvent::PathParameterAdded(e) => path(e.parent_path_id),
+      RequestsEvent::PathParameterShapeSet(e) => shape(e.shape_descriptor.shape_id),
+      RequestsEvent::RequestParameterAddedByPathAndMethod(e) => path(e.path_id),
+      RequestsEvent::RequestParameterShapeSet(e) => shape(e.parameter_descriptor.shape_id),
+      RequestsEvent::RequestAdded(e) => path(e.path_id),
+      RequestsEvent::RequestBodySet(e) => shape(e.body_descriptor.shape_id),
+      RequestsEvent::ResponseAddedByPathAndMethod(e) => path(e.path_id),
+      RequestsEvent::ResponseBodySet(e) => shape(e.body_descriptor.shape_id),
+      _ => vec![],
+    },
+    OpticEvent::ShapeEvent(event) => match event {
+      ShapeEvent::ShapeAdded(e) => shape(e.base_shape_id),
+      ShapeEvent::BaseShapeSet(e) => shape(e.base_shape_id),
+      ShapeEvent::ShapeParameterAdded(e) => shape(e.shape_id),
+      ShapeEvent::ShapeParameterShapeSet(e) => match &e.shape_descriptor {
+        ParameterShapeDescriptor::ProviderInShape(binding) => {
+          shapes(&[binding.provider_descriptor.shape_id()])
+        }
+        ParameterShapeDescriptor::ProviderInField(binding) => {
+          shapes(&[binding.provider_descriptor.shape_id()])
+        }
+      },
+      ShapeEvent::FieldAdded(e) => shapes(&[Some(e.shape_id), e.shape_descriptor.shape_id()]),
+      ShapeEvent::FieldShapeSet(e) => shapes(&[e.shape_descriptor.shape_id()]),
+      _ => vec![],
+    },
+    OpticEvent::RfcEvent(_) => vec![],
+  }
+}
@@ -1,9 +1,10 @@
-use cqrs_core::{Aggregate, AggregateEvent, Event};
+use cqrs_core::{Aggregate, AggregateEvent};
 
 use crate::events::requests::RequestsEvent;
-pub use crate::state::requests::{BodyDescriptor, RequestsState};
+pub use crate::state::requests::RequestsState;
 
-#[derive(Default)]
+#[derive(Default, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct RequestsAggregate {
   pub state: RequestsState,
 }
@@ -34,9 +35,38 @@ impl AggregateEvent<RequestsAggregate> for RequestsEvent {
       RequestsEvent::PathComponentAdded(e) => {
         state.with_path_component(e.path_id, e.parent_path_id, e.name)
       }
+      RequestsEvent::PathComponentRenamed(e) => state.with_path_component_name(e.path_id, e.name),
+      RequestsEvent::PathComponentRemoved(e) if e.reverts_addition => {
+        state.forget_path_component(e.path_id)
+      }
+      RequestsEvent::PathComponentRemoved(e) => state.without_path_component(e.path_id),
+
+      // Path parameters
+      // ---------------
+      RequestsEvent::PathParameterAdded(e) => {
+        state.with_path_parameter(e.path_id, e.parent_path_id, e.name)
+      }
+      RequestsEvent::PathParameterShapeSet(e) => {
+        state.with_path_parameter_shape(e.path_id, e.shape_descriptor)
+      }
+      RequestsEvent::PathParameterRenamed(e) => state.with_path_component_name(e.path_id, e.name),
+      RequestsEvent::PathParameterRemoved(e) if e.reverts_addition => {
+        state.forget_path_component(e.path_id)
+      }
+      RequestsEvent::PathParameterRemoved(e) => state.without_path_component(e.path_id),
+
       // Requests
       // --------
       RequestsEvent::RequestAdded(e) => state.with_request(e.request_id, e.path_id, e.http_method),
+      RequestsEvent::RequestContentTypeSet(e) => {
+        state.with_request_content_type(e.request_id, e.http_content_type)
+      }
+      RequestsEvent::RequestBodySet(e) => state.with_request_body(e.request_id, e.body_descriptor),
+      RequestsEvent::RequestBodyUnset(e) => {
+        state.without_request_body(e.request_id, e.http_content_type)
+      }
+      RequestsEvent::RequestRemoved(e) if e.reverts_addition => state.forget_request(e.request_id),
+      RequestsEvent::RequestRemoved(e) => state.without_request(e.request_id),
 
       // RequestParameters
       // -----------------
@@ -51,6 +81,16 @@ impl AggregateEvent<RequestsAggregate> for RequestsEvent {
       RequestsEvent::RequestParameterShapeSet(e) => {
         state.with_request_parameter_shape(e.parameter_id, e.parameter_descriptor)
       }
+      RequestsEvent::RequestParameterRenamed(e) => {
+        state.with_request_parameter_name(e.parameter_id, e.name)
+      }
+      RequestsEvent::RequestParameterShapeUnset(e) => {
+        state.without_request_parameter_shape(e.parameter_id)
+      }
+      RequestsEvent::RequestParameterRemoved(e) if e.reverts_addition => {
+        state.forget_request_parameter(e.parameter_id)
+      }
+      RequestsEvent::RequestParameterRemoved(e) => state.without_request_parameter(e.parameter_id),
       // Responses
       // ---------
       RequestsEvent::ResponseAddedByPathAndMethod(e) => state.with_response_by_path_and_method(
@@ -59,14 +99,22 @@ impl AggregateEvent<RequestsAggregate> for RequestsEvent {
         e.http_method,
         e.http_status_code,
       ),
+      RequestsEvent::ResponseStatusCodeSet(e) => {
+        state.with_response_status_code(e.response_id, e.http_status_code)
+      }
+      RequestsEvent::ResponseContentTypeSet(e) => {
+        state.with_response_content_type(e.response_id, e.http_content_type)
+      }
       RequestsEvent::ResponseBodySet(e) => {
-        state.with_response_body(e.response_id, BodyDescriptor::Shaped(e.body_descriptor))
+        state.with_response_body(e.response_id, e.body_descriptor)
       }
-      _ => println!(
-        "Missing application logic of '{}' event for '{}' aggregate",
-        self.event_type(),
-        RequestsAggregate::aggregate_type()
-      ),
+      RequestsEvent::ResponseBodyUnset(e) => {
+        state.without_response_body(e.response_id, e.http_content_type)
+      }
+      RequestsEvent::ResponseRemoved(e) if e.reverts_addition => {
+        state.forget_response(e.response_id)
+      }
+      RequestsEvent::ResponseRemoved(e) => state.without_response(e.response_id),
     }
   }
 }
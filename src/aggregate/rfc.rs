@@ -1,11 +1,29 @@
 use cqrs_core::{Aggregate, AggregateEvent, Event};
+use indexmap::IndexMap;
 
 use crate::events::rfc::RfcEvent;
 
-#[derive(Default, Debug)]
-pub struct RfcState {}
+#[derive(Default, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RfcState {
+  pub api_name: Option<String>,
+  // contributions (like an endpoint's purpose) by the id they were contributed to, and their key
+  pub contributions: IndexMap<String, IndexMap<String, String>>,
+  pub last_batch_id: Option<String>,
+}
+
+impl RfcState {
+  pub fn contribution(&self, id: &str, key: &str) -> Option<&str> {
+    self
+      .contributions
+      .get(id)
+      .and_then(|contributions| contributions.get(key))
+      .map(String::as_str)
+  }
+}
 
-#[derive(Default)]
+#[derive(Default, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct RfcAggregate {
   pub state: RfcState,
 }
@@ -28,10 +46,21 @@ impl Aggregate for RfcAggregate {
 
 impl AggregateEvent<RfcAggregate> for RfcEvent {
   fn apply_to(self, aggregate: &mut RfcAggregate) {
-    // let state = &mut aggregate.state;
+    let state = &mut aggregate.state;
 
     match self {
-      _ => println!(
+      RfcEvent::ContributionAdded(e) => {
+        state
+          .contributions
+          .entry(e.id)
+          .or_default()
+          .insert(e.key, e.value);
+      }
+      RfcEvent::APINamed(e) => state.api_name = Some(e.name),
+      // batches only group other events, a batch is only done once it has ended
+      RfcEvent::BatchCommitStarted(_) => {}
+      RfcEvent::BatchCommitEnded(e) => state.last_batch_id = Some(e.batch_id),
+      _ => eprintln!(
         "Missing application logic of '{}' event for '{}' aggregate",
         self.event_type(),
         RfcAggregate::aggregate_type()
@@ -3,7 +3,8 @@ use cqrs_core::{Aggregate, AggregateEvent, Event};
 use crate::events::shape::ShapeEvent;
 pub use crate::state::shape::ShapeState;
 
-#[derive(Default)]
+#[derive(Default, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct ShapeAggregate {
   pub state: ShapeState,
 }
@@ -32,10 +33,19 @@ impl AggregateEvent<ShapeAggregate> for ShapeEvent {
       ShapeEvent::ShapeAdded(e) => {
         state.with_shape(e.shape_id, e.base_shape_id, e.parameters, e.name)
       }
+      ShapeEvent::BaseShapeSet(e) => state.with_base_shape(e.shape_id, e.base_shape_id),
+      ShapeEvent::ShapeRenamed(e) => state.with_shape_name(e.shape_id, e.name),
+      ShapeEvent::ShapeRemoved(e) if e.reverts_addition => state.forget_shape(e.shape_id),
+      ShapeEvent::ShapeRemoved(e) => state.without_shape(e.shape_id),
       ShapeEvent::FieldAdded(e) => {
         state.with_field(e.field_id, e.shape_id, e.name, e.shape_descriptor)
       }
-      _ => println!(
+      ShapeEvent::FieldShapeSet(e) => state.with_field_shape(e.shape_descriptor),
+      ShapeEvent::FieldRenamed(e) => state.with_field_name(e.field_id, e.name),
+      ShapeEvent::FieldRemoved(e) if e.reverts_addition => state.forget_field(e.field_id),
+      ShapeEvent::FieldRemoved(e) => state.without_field(e.field_id),
+      ShapeEvent::ShapeParameterShapeSet(e) => state.with_parameter_shape(e.shape_descriptor),
+      _ => eprintln!(
         "Missing application of '{}' event for '{}' aggregate",
         self.event_type(),
         ShapeAggregate::aggregate_type()
@@ -0,0 +1,1110 @@
+use cqrs_core::Event;
+
+use std::collections::HashSet;
+use std::fmt;
+
+use super::provenance::{changed_entities, referenced_entities};
+use super::rfc::RfcState;
+use super::{OpticAggregate, OpticState};
+use crate::events::batches::batches;
+use crate::events::requests::*;
+use crate::events::rfc::{
+  APINamed, BatchCommitEnded, BatchCommitStarted, ContributionAdded, RfcEvent,
+};
+use crate::events::shape::*;
+use crate::events::OpticEvent;
+use crate::state::requests::{
+  PathComponentDescriptor, PathComponentId, RequestId, RequestParameterId, RequestsState,
+  ResponseId,
+};
+use crate::state::shape::{
+  FieldId, FieldShapeDescriptor, ParameterShapeDescriptor, ProviderInField, ProviderInShape,
+  ShapeId, ShapeState,
+};
+
+// Undoing a batch doesn't truncate the history of the spec: it appends a batch of compensating
+// events that revert the batch's changes, forgetting the entities it added and restoring the names,
+// shapes and bodies it set. Redoing is undoing that batch in turn.
+#[derive(Debug)]
+pub enum UndoError {
+  UnknownBatch(String),
+  // a later event changed an entity the batch changed, or refers to one it added, so reverting
+  // would lose that change
+  ChangedSince {
+    batch_id: String,
+    entity: String,
+  },
+  Irreversible {
+    batch_id: String,
+    cause: Irreversible,
+  },
+}
+
+// An event that can't be reversed, as the state doesn't keep what it overwrote or there's no event
+// that restores it
+#[derive(Debug)]
+pub struct Irreversible {
+  pub event_type: &'static str,
+  pub reason: &'static str,
+}
+
+impl fmt::Display for Irreversible {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(
+      f,
+      "{} can't be reversed, as {}",
+      self.event_type, self.reason
+    )
+  }
+}
+
+impl fmt::Display for UndoError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      UndoError::UnknownBatch(batch_id) => write!(f, "there is no batch {}", batch_id),
+      UndoError::ChangedSince { batch_id, entity } => write!(
+        f,
+        "{} was changed after batch {}, so it can't be reverted",
+        entity, batch_id
+      ),
+      UndoError::Irreversible { batch_id, cause } => {
+        write!(f, "batch {} can't be reverted: {}", batch_id, cause)
+      }
+    }
+  }
+}
+
+impl OpticAggregate {
+  // The batch of events that reverts the batch with the given id
+  pub fn undo_batch(events: &[OpticEvent], batch_id: &str) -> Result<Vec<OpticEvent>, UndoError> {
+    revert_batch(events, batch_id, "undo")
+  }
+
+  // The batch of events that reverts a batch returned by `undo_batch`, once it was appended
+  pub fn redo_batch(
+    events: &[OpticEvent],
+    undo_batch_id: &str,
+  ) -> Result<Vec<OpticEvent>, UndoError> {
+    revert_batch(events, undo_batch_id, "redo")
+  }
+}
+
+fn revert_batch(
+  events: &[OpticEvent],
+  batch_id: &str,
+  verb: &str,
+) -> Result<Vec<OpticEvent>, UndoError> {
+  let batch = batches(events)
+    .find(|batch| batch.batch_id() == Some(batch_id))
+    .ok_or_else(|| UndoError::UnknownBatch(String::from(batch_id)))?;
+  let start = batch.index;
+  let later = &events[start + 1 + batch.events.len()..];
+
+  // reverting removes what the batch added, so nothing added since may refer to it either
+  let changed_by_batch: Vec<_> = batch.events.iter().flat_map(changed_entities).collect();
+  let changed: HashSet<_> = changed_by_batch
+    .iter()
+    .map(|(entity_id, _)| *entity_id)
+    .collect();
+  let added: HashSet<_> = changed_by_batch
+    .into_iter()
+    .filter(|(_, is_creation)| *is_creation)
+    .map(|(entity_id, _)| entity_id)
+    .collect();
+  let changed_since = later.iter().find_map(|event| {
+    changed_entities(event)
+      .into_iter()
+      .map(|(entity_id, _)| entity_id)
+      .find(|entity_id| changed.contains(entity_id))
+      .or_else(|| {
+        referenced_entities(event)
+          .into_iter()
+          .find(|entity_id| added.contains(entity_id))
+      })
+  });
+  if let Some(entity_id) = changed_since {
+    return Err(UndoError::ChangedSince {
+      batch_id: String::from(batch_id),
+      entity: entity_id.to_string(),
+    });
+  }
+
+  // every event is compensated against the state it was applied to, last event first
+  let mut aggregate = OpticAggregate::fold(events[..start].iter().cloned());
+  let compensations = batch
+    .events
+    .iter()
+    .map(|event| aggregate.apply_reversibly(event.clone()))
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|cause| UndoError::Irreversible {
+      batch_id: String::from(batch_id),
+      cause,
+    })?;
+
+  let reverting_batch_id = format!("{}-{}-{}", verb, events.len(), batch_id);
+  let mut reverting = vec![OpticEvent::RfcEvent(RfcEvent::BatchCommitStarted(
+    BatchCommitStarted {
+      batch_id: reverting_batch_id.clone(),
+      commit_message: format!("{} batch {}", capitalized(verb), batch_id),
+      event_context: None,
+    },
+  ))];
+  reverting.extend(compensations.into_iter().rev().flatten());
+  reverting.push(OpticEvent::RfcEvent(RfcEvent::BatchCommitEnded(
+    BatchCommitEnded {
+      batch_id: reverting_batch_id,
+      event_context: None,
+    },
+  )));
+  Ok(reverting)
+}
+
+fn capitalized(word: &str) -> String {
+  let mut chars = word.chars();
+  chars
+    .next()
+    .map(|first| first.to_uppercase().chain(chars).collect())
+    .unwrap_or_default()
+}
+
+// The events that revert an event, when applied after it to the state it was applied to
+pub fn compensating_events(
+  state: &OpticState,
+  event: &OpticEvent,
+) -> Result<Vec<OpticEvent>, Irreversible> {
+  let irreversible = |reason| Irreversible {
+    event_type: event.event_type(),
+    reason,
+  };
+  let events = match event {
+    OpticEvent::RequestsEvent(event) => compensating_requests_events(state.requests, event)
+      .into_iter()
+      .map(OpticEvent::RequestsEvent)
+      .collect(),
+    OpticEvent::RfcEvent(event) => compensating_rfc_events(state.rfc, event)
+      .map_err(irreversible)?
+      .into_iter()
+      .map(OpticEvent::RfcEvent)
+      .collect(),
+    OpticEvent::ShapeEvent(event) => compensating_shape_events(state.shape, event)
+      .map_err(irreversible)?
+      .into_iter()
+      .map(OpticEvent::ShapeEvent)
+      .collect(),
+  };
+  Ok(events)
+}
+
+// Requests
+// --------
+
+fn compensating_requests_events(
+  state: &RequestsState,
+  event: &RequestsEvent,
+) -> Vec<RequestsEvent> {
+  match event {
+    RequestsEvent::PathComponentAdded(e) => match state.path_component(e.path_id) {
+      Some(_) => restore_path_component(state, e.path_id),
+      None => vec![RequestsEvent::PathComponentRemoved(PathComponentRemoved {
+        path_id: e.path_id,
+        reverts_addition: true,
+        event_context: None,
+      })],
+    },
+    RequestsEvent::PathParameterAdded(e) => match state.path_component(e.path_id) {
+      Some(_) => restore_path_component(state, e.path_id),
+      None => vec![RequestsEvent::PathParameterRemoved(PathParameterRemoved {
+        path_id: e.path_id,
+        name: e.name.clone(),
+        reverts_addition: true,
+        event_context: None,
+      })],
+    },
+    RequestsEvent::PathComponentRenamed(e) => state
+      .path_component(e.path_id)
+      .map(|component| {
+        RequestsEvent::PathComponentRenamed(PathComponentRenamed {
+          path_id: e.path_id,
+          name: String::from(component.descriptor.name()),
+          event_context: None,
+        })
+      })
+      .into_iter()
+      .collect(),
+    RequestsEvent::PathParameterRenamed(e) => state
+      .path_component(e.path_id)
+      .map(|component| {
+        RequestsEvent::PathParameterRenamed(PathParameterRenamed {
+          path_id: e.path_id,
+          name: String::from(component.descriptor.name()),
+          event_context: None,
+        })
+      })
+      .into_iter()
+      .collect(),
+    RequestsEvent::PathComponentRemoved(e) => restore_path_component(state, e.path_id),
+    RequestsEvent::PathParameterShapeSet(e) => restore_path_component(state, e.path_id),
+    RequestsEvent::PathParameterRemoved(e) => restore_path_component(state, e.path_id),
+
+    RequestsEvent::RequestParameterAddedByPathAndMethod(e) => {
+      match state.request_parameter(e.parameter_id) {
+        Some(_) => restore_request_parameter(state, e.parameter_id),
+        None => {
+          // rejected parameters are never added, so there's nothing to remove
+          let validation = state.validate_request_parameter(
+            e.path_id,
+            &e.http_method,
+            e.parameter_location,
+            &e.name,
+          );
+          match validation {
+            Ok(()) => vec![RequestsEvent::RequestParameterRemoved(
+              RequestParameterRemoved {
+                parameter_id: e.parameter_id,
+                reverts_addition: true,
+                event_context: None,
+              },
+            )],
+            Err(_) => vec![],
+          }
+        }
+      }
+    }
+    RequestsEvent::RequestParameterRenamed(e) => state
+      .request_parameter(e.parameter_id)
+      .map(|parameter| {
+        RequestsEvent::RequestParameterRenamed(RequestParameterRenamed {
+          parameter_id: e.parameter_id,
+          name: parameter.request_parameter_descriptor.name.clone(),
+          event_context: None,
+        })
+      })
+      .into_iter()
+      .collect(),
+    RequestsEvent::RequestParameterShapeSet(e) => {
+      restore_request_parameter_shape(state, e.parameter_id)
+    }
+    RequestsEvent::RequestParameterShapeUnset(e) => {
+      restore_request_parameter_shape(state, e.parameter_id)
+    }
+    RequestsEvent::RequestParameterRemoved(e) => restore_request_parameter(state, e.parameter_id),
+
+    RequestsEvent::RequestAdded(e) => match state.request(e.request_id) {
+      Some(_) => restore_request(state, e.request_id),
+      None => vec![RequestsEvent::RequestRemoved(RequestRemoved {
+        request_id: e.request_id,
+        reverts_addition: true,
+        event_context: None,
+      })],
+    },
+    RequestsEvent::RequestContentTypeSet(e) => restore_request_bodies(state, e.request_id),
+    RequestsEvent::RequestBodySet(e) => restore_request_bodies(state, e.request_id),
+    RequestsEvent::RequestBodyUnset(e) => restore_request_bodies(state, e.request_id),
+    RequestsEvent::RequestRemoved(e) => restore_request(state, e.request_id),
+
+    RequestsEvent::ResponseAddedByPathAndMethod(e) => match state.response(e.response_id) {
+      Some(_) => restore_response(state, e.response_id),
+      None => vec![RequestsEvent::ResponseRemoved(ResponseRemoved {
+        response_id: e.response_id,
+        reverts_addition: true,
+        event_context: None,
+      })],
+    },
+    RequestsEvent::ResponseStatusCodeSet(e) => state
+      .response(e.response_id)
+      .map(|response| {
+        RequestsEvent::ResponseStatusCodeSet(ResponseStatusCodeSet {
+          response_id: e.response_id,
+          http_status_code: response.response_descriptor.http_status_code,
+          event_context: None,
+        })
+      })
+      .into_iter()
+      .collect(),
+    RequestsEvent::ResponseContentTypeSet(e) => restore_response_bodies(state, e.response_id),
+    RequestsEvent::ResponseBodySet(e) => restore_response_bodies(state, e.response_id),
+    RequestsEvent::ResponseBodyUnset(e) => restore_response_bodies(state, e.response_id),
+    RequestsEvent::ResponseRemoved(e) => restore_response(state, e.response_id),
+  }
+}
+
+// Restoring an entity adds it again as it was, which replaces it in place
+fn restore_path_component(state: &RequestsState, path_id: PathComponentId) -> Vec<RequestsEvent> {
+  let component = match state.path_component(path_id) {
+    Some(component) => component,
+    None => return vec![],
+  };
+
+  let mut events = vec![];
+  match &component.descriptor {
+    PathComponentDescriptor::Basic(descriptor) => {
+      events.push(RequestsEvent::PathComponentAdded(PathComponentAdded {
+        path_id,
+        parent_path_id: descriptor.parent_path_id,
+        name: descriptor.name.clone(),
+        event_context: None,
+      }));
+      if component.is_removed {
+        events.push(RequestsEvent::PathComponentRemoved(PathComponentRemoved {
+          path_id,
+          reverts_addition: false,
+          event_context: None,
+        }));
+      }
+    }
+    PathComponentDescriptor::Parameterized(descriptor) => {
+      events.push(RequestsEvent::PathParameterAdded(PathParameterAdded {
+        path_id,
+        parent_path_id: descriptor.parent_path_id,
+        name: descriptor.name.clone(),
+        event_context: None,
+      }));
+      if let Some(shaped) = descriptor.shape_descriptor.shaped() {
+        events.push(RequestsEvent::PathParameterShapeSet(
+          PathParameterShapeSet {
+            path_id,
+            shape_descriptor: shaped.clone(),
+            event_context: None,
+          },
+        ));
+      }
+      if component.is_removed {
+        events.push(RequestsEvent::PathParameterRemoved(PathParameterRemoved {
+          path_id,
+          name: descriptor.name.clone(),
+          reverts_addition: false,
+          event_context: None,
+        }));
+      }
+    }
+  }
+  events
+}
+
+fn restore_request_parameter(
+  state: &RequestsState,
+  parameter_id: RequestParameterId,
+) -> Vec<RequestsEvent> {
+  let parameter = match state.request_parameter(parameter_id) {
+    Some(parameter) => parameter,
+    None => return vec![],
+  };
+  let descriptor = &parameter.request_parameter_descriptor;
+
+  let mut events = vec![RequestsEvent::RequestParameterAddedByPathAndMethod(
+    RequestParameterAddedByPathAndMethod {
+      parameter_id,
+      path_id: descriptor.path_id,
+      http_method: descriptor.http_method.clone(),
+      parameter_location: descriptor.location,
+      name: descriptor.name.clone(),
+      event_context: None,
+    },
+  )];
+  events.extend(restore_request_parameter_shape(state, parameter_id));
+  if parameter.is_removed {
+    events.push(RequestsEvent::RequestParameterRemoved(
+      RequestParameterRemoved {
+        parameter_id,
+        reverts_addition: false,
+        event_context: None,
+      },
+    ));
+  }
+  events
+}
+
+fn restore_request_parameter_shape(
+  state: &RequestsState,
+  parameter_id: RequestParameterId,
+) -> Vec<RequestsEvent> {
+  let parameter = match state.request_parameter(parameter_id) {
+    Some(parameter) => parameter,
+    None => return vec![],
+  };
+  let shape_descriptor = &parameter.request_parameter_descriptor.shape_descriptor;
+  let event = match shape_descriptor.shaped() {
+    Some(shaped) => RequestsEvent::RequestParameterShapeSet(RequestParameterShapeSet {
+      parameter_id,
+      parameter_descriptor: shaped.clone(),
+      event_context: None,
+    }),
+    None => RequestsEvent::RequestParameterShapeUnset(RequestParameterShapeUnset {
+      parameter_id,
+      event_context: None,
+    }),
+  };
+  vec![event]
+}
+
+fn restore_request(state: &RequestsState, request_id: RequestId) -> Vec<RequestsEvent> {
+  let request = match state.request(request_id) {
+    Some(request) => request,
+    None => return vec![],
+  };
+  let descriptor = &request.request_descriptor;
+
+  let mut events = vec![RequestsEvent::RequestAdded(RequestAdded {
+    request_id,
+    path_id: descriptor.path_component_id,
+    http_method: descriptor.http_method.clone(),
+    event_context: None,
+  })];
+  events.extend(
+    restore_request_bodies(state, request_id)
+      .into_iter()
+      .skip(1),
+  );
+  if request.is_removed {
+    events.push(RequestsEvent::RequestRemoved(RequestRemoved {
+      request_id,
+      reverts_addition: false,
+      event_context: None,
+    }));
+  }
+  events
+}
+
+// bodies are restored all at once, so they're listed in the order they were before
+fn restore_request_bodies(state: &RequestsState, request_id: RequestId) -> Vec<RequestsEvent> {
+  let request = match state.request(request_id) {
+    Some(request) => request,
+    None => return vec![],
+  };
+
+  let mut events = vec![RequestsEvent::RequestBodyUnset(RequestBodyUnset {
+    request_id,
+    http_content_type: None,
+    event_context: None,
+  })];
+  events.extend(request.request_descriptor.bodies.values().map(|body| {
+    RequestsEvent::RequestBodySet(RequestBodySet {
+      request_id,
+      body_descriptor: body.clone(),
+      event_context: None,
+    })
+  }));
+  events
+}
+
+fn restore_response(state: &RequestsState, response_id: ResponseId) -> Vec<RequestsEvent> {
+  let response = match state.response(response_id) {
+    Some(response) => response,
+    None => return vec![],
+  };
+  let descriptor = &response.response_descriptor;
+
+  let mut events = vec![RequestsEvent::ResponseAddedByPathAndMethod(
+    ResponseAddedByPathAndMethod {
+      response_id,
+      path_id: descriptor.path_id,
+      http_method: descriptor.http_method.clone(),
+      http_status_code: descriptor.http_status_code,
+      event_context: None,
+    },
+  )];
+  events.extend(
+    restore_response_bodies(state, response_id)
+      .into_iter()
+      .skip(1),
+  );
+  if response.is_removed {
+    events.push(RequestsEvent::ResponseRemoved(ResponseRemoved {
+      response_id,
+      reverts_addition: false,
+      event_context: None,
+    }));
+  }
+  events
+}
+
+fn restore_response_bodies(state: &RequestsState, response_id: ResponseId) -> Vec<RequestsEvent> {
+  let response = match state.response(response_id) {
+    Some(response) => response,
+    None => return vec![],
+  };
+
+  let mut events = vec![RequestsEvent::ResponseBodyUnset(ResponseBodyUnset {
+    response_id,
+    http_content_type: None,
+    event_context: None,
+  })];
+  events.extend(response.response_descriptor.bodies.values().map(|body| {
+    RequestsEvent::ResponseBodySet(ResponseBodySet {
+      response_id,
+      body_descriptor: body.clone(),
+      event_context: None,
+    })
+  }));
+  events
+}
+
+// Rfc
+// ---
+
+// There are no RFC events that unset a value, so a contribution or an API name that's set for the
+// first time can't be reversed, and neither can a batch that ended
+fn compensating_rfc_events(
+  state: &RfcState,
+  event: &RfcEvent,
+) -> Result<Vec<RfcEvent>, &'static str> {
+  match event {
+    RfcEvent::ContributionAdded(e) => state
+      .contribution(&e.id, &e.key)
+      .map(|value| {
+        vec![RfcEvent::ContributionAdded(ContributionAdded {
+          id: e.id.clone(),
+          key: e.key.clone(),
+          value: String::from(value),
+          event_context: None,
+        })]
+      })
+      .ok_or("there's no event that removes a contribution"),
+    RfcEvent::APINamed(_) => state
+      .api_name
+      .as_ref()
+      .map(|name| {
+        vec![RfcEvent::APINamed(APINamed {
+          name: name.clone(),
+          event_context: None,
+        })]
+      })
+      .ok_or("there's no event that removes the name of the API"),
+    RfcEvent::BatchCommitEnded(_) => Err("the batch that ended before it can't end again"),
+    RfcEvent::GitStateSet(_) => Err("the git state isn't kept in the state"),
+    RfcEvent::BatchCommitStarted(_) => Ok(vec![]),
+  }
+}
+
+// Shapes
+// ------
+
+fn compensating_shape_events(
+  state: &ShapeState,
+  event: &ShapeEvent,
+) -> Result<Vec<ShapeEvent>, &'static str> {
+  let events = match event {
+    ShapeEvent::ShapeAdded(e) => match state.shape(e.shape_id) {
+      Some(_) => restore_shape(state, e.shape_id),
+      None => vec![ShapeEvent::ShapeRemoved(ShapeRemoved {
+        shape_id: e.shape_id,
+        reverts_addition: true,
+        event_context: None,
+      })],
+    },
+    ShapeEvent::BaseShapeSet(e) => state
+      .shape(e.shape_id)
+      .map(|shape| {
+        ShapeEvent::BaseShapeSet(BaseShapeSet {
+          shape_id: e.shape_id,
+          base_shape_id: shape.descriptor.base_shape_id,
+          event_context: None,
+        })
+      })
+      .into_iter()
+      .collect(),
+    ShapeEvent::ShapeRenamed(e) => state
+      .shape(e.shape_id)
+      .map(|shape| {
+        ShapeEvent::ShapeRenamed(ShapeRenamed {
+          shape_id: e.shape_id,
+          name: shape.descriptor.name.clone(),
+          event_context: None,
+        })
+      })
+      .into_iter()
+      .collect(),
+    ShapeEvent::ShapeRemoved(e) => restore_shape(state, e.shape_id),
+    ShapeEvent::ShapeParameterAdded(_)
+    | ShapeEvent::ShapeParameterRenamed(_)
+    | ShapeEvent::ShapeParameterRemoved(_) => {
+      return Err("shape parameters aren't kept in the state, only the shapes that have them")
+    }
+    // a parameter bound for the first time can only be unbound by adding its shape or field again
+    ShapeEvent::ShapeParameterShapeSet(e) => match &e.shape_descriptor {
+      ParameterShapeDescriptor::ProviderInShape(binding) => {
+        let shape = match state.shape(binding.shape_id) {
+          Some(shape) => shape,
+          None => return Ok(vec![]),
+        };
+        match shape
+          .descriptor
+          .bindings
+          .get(&binding.consuming_parameter_id)
+        {
+          Some(provider_descriptor) => {
+            vec![ShapeEvent::ShapeParameterShapeSet(ShapeParameterShapeSet {
+              shape_descriptor: ParameterShapeDescriptor::ProviderInShape(ProviderInShape {
+                provider_descriptor: provider_descriptor.clone(),
+                ..binding.clone()
+              }),
+              event_context: None,
+            })]
+          }
+          None => restore_shape(state, binding.shape_id),
+        }
+      }
+      ParameterShapeDescriptor::ProviderInField(binding) => {
+        let field = match state.field(binding.field_id) {
+          Some(field) => field,
+          None => return Ok(vec![]),
+        };
+        match field
+          .descriptor
+          .bindings
+          .get(&binding.consuming_parameter_id)
+        {
+          Some(provider_descriptor) => {
+            vec![ShapeEvent::ShapeParameterShapeSet(ShapeParameterShapeSet {
+              shape_descriptor: ParameterShapeDescriptor::ProviderInField(ProviderInField {
+                provider_descriptor: provider_descriptor.clone(),
+                ..binding.clone()
+              }),
+              event_context: None,
+            })]
+          }
+          None => restore_field(state, binding.field_id),
+        }
+      }
+    },
+
+    ShapeEvent::FieldAdded(e) => match state.field(e.field_id) {
+      Some(_) => restore_field(state, e.field_id),
+      None => vec![ShapeEvent::FieldRemoved(FieldRemoved {
+        field_id: e.field_id,
+        reverts_addition: true,
+        event_context: None,
+      })],
+    },
+    ShapeEvent::FieldShapeSet(e) => {
+      let field_id = match &e.shape_descriptor {
+        FieldShapeDescriptor::FieldShapeFromShape(descriptor) => descriptor.field_id,
+        FieldShapeDescriptor::FieldShapeFromParameter(descriptor) => descriptor.field_id,
+      };
+      state
+        .field(field_id)
+        .map(|field| {
+          ShapeEvent::FieldShapeSet(FieldShapeSet {
+            shape_descriptor: field.descriptor.shape_descriptor.clone(),
+            event_context: None,
+          })
+        })
+        .into_iter()
+        .collect()
+    }
+    ShapeEvent::FieldRenamed(e) => state
+      .field(e.field_id)
+      .map(|field| {
+        ShapeEvent::FieldRenamed(FieldRenamed {
+          field_id: e.field_id,
+          name: field.descriptor.name.clone(),
+          event_context: None,
+        })
+      })
+      .into_iter()
+      .collect(),
+    ShapeEvent::FieldRemoved(e) => restore_field(state, e.field_id),
+  };
+  Ok(events)
+}
+
+// A shape added again has no fields or bindings, so they're added and bound again after it
+fn restore_shape(state: &ShapeState, shape_id: ShapeId) -> Vec<ShapeEvent> {
+  let shape = match state.shape(shape_id) {
+    Some(shape) => shape,
+    None => return vec![],
+  };
+  let descriptor = &shape.descriptor;
+
+  let mut events = vec![ShapeEvent::ShapeAdded(ShapeAdded {
+    shape_id,
+    base_shape_id: descriptor.base_shape_id,
+    parameters: descriptor.parameters.clone(),
+    name: descriptor.name.clone(),
+    event_context: None,
+  })];
+  events.extend(
+    descriptor
+      .bindings
+      .iter()
+      .map(|(consuming_parameter_id, provider_descriptor)| {
+        ShapeEvent::ShapeParameterShapeSet(ShapeParameterShapeSet {
+          shape_descriptor: ParameterShapeDescriptor::ProviderInShape(ProviderInShape {
+            shape_id,
+            provider_descriptor: provider_descriptor.clone(),
+            consuming_parameter_id: *consuming_parameter_id,
+          }),
+          event_context: None,
+        })
+      }),
+  );
+  for field_id in &descriptor.field_ordering {
+    events.extend(restore_field(state, *field_id));
+  }
+  if shape.is_removed {
+    events.push(ShapeEvent::ShapeRemoved(ShapeRemoved {
+      shape_id,
+      reverts_addition: false,
+      event_context: None,
+    }));
+  }
+  events
+}
+
+fn restore_field(state: &ShapeState, field_id: FieldId) -> Vec<ShapeEvent> {
+  let field = match state.field(field_id) {
+    Some(field) => field,
+    None => return vec![],
+  };
+  let descriptor = &field.descriptor;
+
+  let mut events = vec![ShapeEvent::FieldAdded(FieldAdded {
+    field_id,
+    shape_id: descriptor.shape_id,
+    name: descriptor.name.clone(),
+    shape_descriptor: descriptor.shape_descriptor.clone(),
+    event_context: None,
+  })];
+  events.extend(
+    descriptor
+      .bindings
+      .iter()
+      .map(|(consuming_parameter_id, provider_descriptor)| {
+        ShapeEvent::ShapeParameterShapeSet(ShapeParameterShapeSet {
+          shape_descriptor: ParameterShapeDescriptor::ProviderInField(ProviderInField {
+            field_id,
+            provider_descriptor: provider_descriptor.clone(),
+            consuming_parameter_id: *consuming_parameter_id,
+          }),
+          event_context: None,
+        })
+      }),
+  );
+  if field.is_removed {
+    events.push(ShapeEvent::FieldRemoved(FieldRemoved {
+      field_id,
+      reverts_addition: false,
+      event_context: None,
+    }));
+  }
+  events
+}
+
+#[test]
+fn batches_are_undone_and_redone_with_compensating_events() {
+  use crate::state::json::state_to_json;
+
+  let spec = |events: &[OpticEvent]| {
+    let aggregate = OpticAggregate::fold(events.iter().cloned());
+    let json = state_to_json(&aggregate.get_state(), true).unwrap();
+    (json["requests"].clone(), json["shape"].clone())
+  };
+  let events = crate::events_from_file("test-fixtures/uncompacted-spec.json").unwrap();
+  let batch_id = "84008f7c-c965-469d-a71e-e4f61fe8939a";
+
+  // undoing the last batch removes the response and shapes it added
+  let undo = OpticAggregate::undo_batch(&events, batch_id).unwrap();
+  let without_batch: Vec<OpticEvent> = events
+    .iter()
+    .take_while(|event| match event {
+      OpticEvent::RfcEvent(RfcEvent::BatchCommitStarted(e)) => e.batch_id != batch_id,
+      _ => true,
+    })
+    .cloned()
+    .collect();
+  let undone = [&events[..], &undo[..]].concat();
+  assert_eq!(spec(&undone), spec(&without_batch));
+  // removals that revert additions are marked, so removals read from a spec don't forget anything
+  let json = |events: &[OpticEvent]| serde_json::to_string(events).unwrap();
+  assert!(json(&undo).contains(r#""revertsAddition":true"#));
+  assert!(!json(&events).contains("revertsAddition"));
+
+  let undo_batch_id = match &undo[0] {
+    OpticEvent::RfcEvent(RfcEvent::BatchCommitStarted(e)) => e.batch_id.clone(),
+    _ => unreachable!(),
+  };
+  let redo = OpticAggregate::redo_batch(&undone, &undo_batch_id).unwrap();
+  let redone = [&undone[..], &redo[..]].concat();
+  assert_eq!(spec(&redone), spec(&events));
+
+  // renames are reverted to the previous name
+  let rename = |batch_id: &str, name: &str| -> Vec<OpticEvent> {
+    serde_json::from_value(serde_json::json!([
+      {"BatchCommitStarted": {"batchId": batch_id, "commitMessage": "", "eventContext": null}},
+      {"FieldRenamed": {"fieldId": "EQSZqM_12", "name": name, "eventContext": null}},
+      {"BatchCommitEnded": {"batchId": batch_id, "eventContext": null}},
+    ]))
+    .unwrap()
+  };
+  let renamed = [
+    &events[..],
+    &rename("first", "nation"),
+    &rename("second", "land"),
+  ]
+  .concat();
+  let undo = OpticAggregate::undo_batch(&renamed, "second").unwrap();
+  let (_, shapes) = spec(&[&renamed[..], &undo[..]].concat());
+  assert_eq!(
+    shapes,
+    spec(&[&events[..], &rename("first", "nation")].concat()).1
+  );
+
+  // shapes added again lose their fields and bindings, which undoing adds and binds again
+  let readded: Vec<OpticEvent> = serde_json::from_value(serde_json::json!([
+    {"BatchCommitStarted": {"batchId": "readded", "commitMessage": "", "eventContext": null}},
+    {"ShapeAdded": {"shapeId": "EQSZqM_11", "baseShapeId": "$object", "parameters": {"DynamicParameterList": {"shapeParameterIds": []}}, "name": "", "eventContext": null}},
+    {"ShapeAdded": {"shapeId": "EQSZqM_6", "baseShapeId": "$list", "parameters": {"DynamicParameterList": {"shapeParameterIds": []}}, "name": "", "eventContext": null}},
+    {"BatchCommitEnded": {"batchId": "readded", "eventContext": null}},
+  ]))
+  .unwrap();
+  let readded = [&events[..], &readded[..]].concat();
+  let state = OpticAggregate::fold(readded.iter().cloned());
+  assert_eq!(
+    state
+      .get_state()
+      .shape
+      .fields_of(ShapeId::from("EQSZqM_11"))
+      .count(),
+    0
+  );
+  let undo = OpticAggregate::undo_batch(&readded, "readded").unwrap();
+  assert_eq!(spec(&[&readded[..], &undo[..]].concat()), spec(&events));
+
+  // a batch can't be undone once later batches changed what it changed
+  match OpticAggregate::undo_batch(&renamed, "first") {
+    Err(UndoError::ChangedSince { entity, .. }) => assert_eq!(entity, "field EQSZqM_12"),
+    Err(error) => panic!("unexpected error: {}", error),
+    Ok(_) => panic!("the batch was undone"),
+  }
+  // nor once later batches added something to what it added
+  let added_under: Vec<OpticEvent> = serde_json::from_value(serde_json::json!([
+    {"BatchCommitStarted": {"batchId": "path", "commitMessage": "", "eventContext": null}},
+    {"PathComponentAdded": {"pathId": "path_teams", "parentPathId": "root", "name": "teams", "eventContext": null}},
+    // a batch started before the previous one ended ends it
+    {"BatchCommitStarted": {"batchId": "request", "commitMessage": "", "eventContext": null}},
+    {"RequestAdded": {"requestId": "request_teams", "pathId": "path_teams", "httpMethod": "GET", "eventContext": null}},
+    {"BatchCommitEnded": {"batchId": "request", "eventContext": null}},
+  ]))
+  .unwrap();
+  let added_under = [&events[..], &added_under[..]].concat();
+  match OpticAggregate::undo_batch(&added_under, "path") {
+    Err(UndoError::ChangedSince { entity, .. }) => assert_eq!(entity, "path path_teams"),
+    Err(error) => panic!("unexpected error: {}", error),
+    Ok(_) => panic!("the batch was undone"),
+  }
+  let undo = OpticAggregate::undo_batch(&added_under, "request").unwrap();
+  let without_request = &added_under[..added_under.len() - 3];
+  assert_eq!(
+    spec(&[&added_under[..], &undo[..]].concat()).0,
+    spec(without_request).0
+  );
+  assert!(OpticAggregate::undo_batch(&events, "no-such-batch").is_err());
+}
+
+#[test]
+fn applying_the_reverse_of_an_event_restores_the_state_before_it() {
+  use super::Aggregate;
+  use crate::state::http::StatusCode;
+  use crate::state::json::state_to_json;
+  use crate::state::shape::ShapeParameterId;
+
+  // the whole state, removed entities included
+  let spec = |aggregate: &OpticAggregate| state_to_json(&aggregate.get_state(), true).unwrap();
+  let assert_reversible = |aggregate: &mut OpticAggregate, event: OpticEvent| {
+    let expected = spec(aggregate);
+    let event_type = cqrs_core::Event::event_type(&event);
+    match aggregate.apply_reversibly(event) {
+      Ok(reverse_events) => {
+        for reverse_event in reverse_events {
+          aggregate.apply(reverse_event);
+        }
+      }
+      // values set for the first time can't be unset, see `compensating_rfc_events`, and shape
+      // parameters aren't kept in the state
+      Err(Irreversible { event_type, .. }) => assert!(
+        matches!(
+          event_type,
+          "ContributionAdded" | "APINamed" | "BatchCommitEnded" | "ShapeParameterAdded"
+        ),
+        "{} is irreversible",
+        event_type
+      ),
+    }
+    assert!(
+      spec(aggregate) == expected,
+      "reversing {} changed the state",
+      event_type
+    );
+  };
+
+  // a seeded xorshift, so failures can be reproduced
+  let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+  let mut random = move |bound: usize| {
+    seed ^= seed << 13;
+    seed ^= seed >> 7;
+    seed ^= seed << 17;
+    (seed % bound.max(1) as u64) as usize
+  };
+
+  // events of the fixture, and random changes to entities of the states they're applied to, are
+  // reversed before being applied for good (a sample of them, as comparing states is slow)
+  let mut aggregate = OpticAggregate::default();
+  for (index, event) in crate::events_from_file("test-fixtures/uncompacted-spec.json")
+    .unwrap()
+    .into_iter()
+    .enumerate()
+  {
+    if random(8) == 0 {
+      assert_reversible(&mut aggregate, event.clone());
+    }
+    if random(8) != 0 {
+      aggregate.apply(event);
+      continue;
+    }
+
+    let change = {
+      let state = aggregate.get_state();
+      let shapes: Vec<_> = state.shape.all_shapes().collect();
+      let fields: Vec<_> = state.shape.all_fields().collect();
+      let responses: Vec<_> = state.requests.all_responses().collect();
+      let requests: Vec<_> = state.requests.all_requests().collect();
+      let paths: Vec<_> = state.requests.all_path_components().collect();
+      let shape_id = shapes.get(random(shapes.len())).map(|shape| shape.shape_id);
+      let field_id = fields.get(random(fields.len())).map(|field| field.field_id);
+      let response_id = responses
+        .get(random(responses.len()))
+        .map(|response| response.response_id);
+      let request_id = requests
+        .get(random(requests.len()))
+        .map(|request| request.request_id);
+      let path_id = paths.get(random(paths.len())).map(|path| path.path_id);
+      let new_field_id = FieldId::from(format!("generated_field_{}", index).as_str());
+      let name = format!("generated_{}", index);
+
+      let change = match random(15) {
+        0 => field_id.map(|field_id| {
+          OpticEvent::ShapeEvent(ShapeEvent::FieldRenamed(FieldRenamed {
+            field_id,
+            name,
+            event_context: None,
+          }))
+        }),
+        1 => field_id.map(|field_id| {
+          OpticEvent::ShapeEvent(ShapeEvent::FieldRemoved(FieldRemoved {
+            field_id,
+            reverts_addition: false,
+            event_context: None,
+          }))
+        }),
+        2 => shape_id.map(|shape_id| {
+          OpticEvent::ShapeEvent(ShapeEvent::FieldAdded(FieldAdded {
+            field_id: new_field_id,
+            shape_id,
+            name,
+            shape_descriptor: FieldShapeDescriptor::FieldShapeFromShape(
+              crate::state::shape::FieldShapeFromShape {
+                field_id: new_field_id,
+                shape_id: ShapeId::from("$string"),
+              },
+            ),
+            event_context: None,
+          }))
+        }),
+        3 => shape_id.map(|shape_id| {
+          OpticEvent::ShapeEvent(ShapeEvent::ShapeRenamed(ShapeRenamed {
+            shape_id,
+            name,
+            event_context: None,
+          }))
+        }),
+        4 => shape_id.map(|shape_id| {
+          OpticEvent::ShapeEvent(ShapeEvent::BaseShapeSet(BaseShapeSet {
+            shape_id,
+            base_shape_id: ShapeId::from("$number"),
+            event_context: None,
+          }))
+        }),
+        5 => shape_id.map(|shape_id| {
+          OpticEvent::ShapeEvent(ShapeEvent::ShapeRemoved(ShapeRemoved {
+            shape_id,
+            reverts_addition: false,
+            event_context: None,
+          }))
+        }),
+        6 => shape_id.map(|shape_id| {
+          let shape = state.shape.shape(shape_id).unwrap();
+          OpticEvent::ShapeEvent(ShapeEvent::ShapeAdded(ShapeAdded {
+            shape_id,
+            base_shape_id: ShapeId::from("$object"),
+            parameters: shape.descriptor.parameters.clone(),
+            name,
+            event_context: None,
+          }))
+        }),
+        7 => response_id.map(|response_id| {
+          OpticEvent::RequestsEvent(RequestsEvent::ResponseBodyUnset(ResponseBodyUnset {
+            response_id,
+            http_content_type: None,
+            event_context: None,
+          }))
+        }),
+        8 => response_id.map(|response_id| {
+          OpticEvent::RequestsEvent(RequestsEvent::ResponseStatusCodeSet(
+            ResponseStatusCodeSet {
+              response_id,
+              http_status_code: StatusCode::new(404).unwrap(),
+              event_context: None,
+            },
+          ))
+        }),
+        9 => response_id.map(|response_id| {
+          OpticEvent::RequestsEvent(RequestsEvent::ResponseRemoved(ResponseRemoved {
+            response_id,
+            reverts_addition: false,
+            event_context: None,
+          }))
+        }),
+        10 => request_id.map(|request_id| {
+          OpticEvent::RequestsEvent(RequestsEvent::RequestRemoved(RequestRemoved {
+            request_id,
+            reverts_addition: false,
+            event_context: None,
+          }))
+        }),
+        11 => path_id.map(|path_id| {
+          OpticEvent::RfcEvent(RfcEvent::ContributionAdded(ContributionAdded {
+            id: format!("{}.GET", path_id),
+            key: String::from("purpose"),
+            value: name,
+            event_context: None,
+          }))
+        }),
+        12 => Some(OpticEvent::RfcEvent(RfcEvent::APINamed(APINamed {
+          name,
+          event_context: None,
+        }))),
+        13 => shape_id.map(|shape_id| {
+          OpticEvent::ShapeEvent(ShapeEvent::ShapeParameterAdded(ShapeParameterAdded {
+            shape_parameter_id: ShapeParameterId::from(format!("parameter_{}", index).as_str()),
+            shape_id,
+            name,
+            event_context: None,
+          }))
+        }),
+        _ => path_id.map(|path_id| {
+          OpticEvent::RequestsEvent(RequestsEvent::PathComponentRenamed(PathComponentRenamed {
+            path_id,
+            name,
+            event_context: None,
+          }))
+        }),
+      };
+      change
+    };
+    if let Some(change) = change {
+      assert_reversible(&mut aggregate, change);
+    }
+
+    aggregate.apply(event);
+  }
+}
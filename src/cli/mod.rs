@@ -0,0 +1,593 @@
+use cqrs_core::SnapshotSink;
+use serde::Serialize;
+use std::fmt;
+use std::fs;
+use std::net::TcpListener;
+
+use crate::aggregate::{Aggregate, OpticAggregate, OpticAggregateId};
+use crate::compaction::dedup::compact_deduplicated;
+use crate::compaction::gc::collect_garbage;
+use crate::compaction::{compact, compact_without_garbage};
+use crate::events::OpticEvent;
+use crate::export::{
+  examples::examples, json_schema::json_schema, openapi::openapi, rust::rust,
+  typescript::typescript,
+};
+use crate::merge::merge;
+use crate::mock::MockServer;
+use crate::reports::blame::BlameReport;
+use crate::reports::changelog::ChangelogReport;
+use crate::reports::check::CheckReport;
+use crate::reports::diff::DiffReport;
+use crate::reports::endpoints::EndpointsReport;
+use crate::reports::shapes::ShapesReport;
+use crate::reports::stats::StatsReport;
+use crate::snapshot::{self, SnapshotEncoding, SnapshotFile};
+use crate::state::json::state_to_json;
+
+pub const USAGE: &str = "\
+USAGE:
+    compacted-spec-experiment <COMMAND> --input <FILE> [OPTIONS]
+
+COMMANDS:
+    fold                  fold the events and print the resulting state as JSON
+    compact               print the minimal event stream that folds into the same state
+    gc                    print the events that remove the shapes no endpoint refers to
+    check                 check that entities refer to entities that exist, and batches are well formed
+    export openapi        export the endpoints as an OpenAPI document
+    export json-schema    export the object shapes as JSON Schema definitions
+    export typescript     export the bodies of the endpoints as TypeScript declarations
+    export rust           export the bodies of the endpoints as Rust types
+    export examples       export an example body for every response of the endpoints
+    endpoints             list the endpoints and their responses
+    shapes                list the shapes and their fields
+    diff                  list the changes from the spec given by --against to the input
+    changelog             list the batches of changes committed to the spec
+    stats                 summarize the events and entities in the spec
+    merge                 merge the events of --against into the input, printing the merged events
+    blame <TARGET>        list the events behind an endpoint (like \"GET /users\") or a shape id
+    mock                  serve example responses of the endpoints on localhost
+
+OPTIONS:
+    -i, --input <FILE>               the spec file of events to read
+    -f, --format <FORMAT>            (check and reports) text or json [default: text]
+    -o, --output <FILE>              write to FILE instead of stdout
+        --against <FILE>             (diff, merge) the spec file to compare against or merge
+        --base <FILE>                (merge) the common ancestor [default: the common events]
+        --snapshot <FILE>            (fold) restore from and update a snapshot of the state
+        --snapshot-encoding <ENC>    (fold) json or binary [default: json]
+        --include-removed            (fold) keep removed entities in the json output
+        --gc                         (compact) leave out the shapes no endpoint refers to
+        --dedup                      (compact) keep one of every set of structurally equal shapes
+        --seed <N>                   (export examples, mock) generate other examples [default: 0]
+        --port <PORT>                (mock) the port to listen on [default: 8080]
+        --verbose                    (mock) print every request and the status it was answered with
+
+EXIT CODES:
+    0    success
+    1    check found problems, or merge found conflicts
+    2    invalid usage
+    3    input could not be read
+    4    output could not be written
+    5    mock server could not listen
+";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+  Fold,
+  Compact,
+  Gc,
+  Check,
+  ExportOpenApi,
+  ExportJsonSchema,
+  ExportTypeScript,
+  ExportRust,
+  ExportExamples,
+  Endpoints,
+  Shapes,
+  Diff,
+  Changelog,
+  Stats,
+  Merge,
+  Blame,
+  Mock,
+}
+
+impl Command {
+  // Whether an option applies to the command, where --input and --output apply to every command
+  fn accepts(self, option: &str) -> bool {
+    match option {
+      "--format" => self.has_formats(),
+      "--against" => matches!(self, Command::Diff | Command::Merge),
+      "--base" => self == Command::Merge,
+      "--snapshot" | "--snapshot-encoding" | "--include-removed" => self == Command::Fold,
+      "--gc" | "--dedup" => self == Command::Compact,
+      "--seed" => matches!(self, Command::ExportExamples | Command::Mock),
+      "--port" | "--verbose" => self == Command::Mock,
+      _ => true,
+    }
+  }
+
+  // commands that print their output as text or JSON, where the others always print events, the
+  // state or a generated document
+  fn has_formats(self) -> bool {
+    matches!(
+      self,
+      Command::Check
+        | Command::Endpoints
+        | Command::Shapes
+        | Command::Diff
+        | Command::Changelog
+        | Command::Stats
+        | Command::Blame
+    )
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+  Text,
+  Json,
+}
+
+#[derive(Debug)]
+pub struct Invocation {
+  pub command: Command,
+  pub input: String,
+  pub format: Format,
+  pub output: Option<String>,
+  pub against: Option<String>,
+  pub base: Option<String>,
+  pub target: Option<String>,
+  pub snapshot: Option<String>,
+  pub snapshot_encoding: SnapshotEncoding,
+  pub include_removed: bool,
+  pub gc: bool,
+  pub dedup: bool,
+  pub seed: u64,
+  pub port: u16,
+  pub verbose: bool,
+}
+
+#[derive(Debug)]
+pub enum CliError {
+  Usage(String),
+  Input(String),
+  Output(String),
+  ChecksFailed(usize),
+  MergeConflicts(usize),
+  Serve(String),
+}
+
+impl CliError {
+  pub fn exit_code(&self) -> i32 {
+    match self {
+      CliError::ChecksFailed(_) | CliError::MergeConflicts(_) => 1,
+      CliError::Usage(_) => 2,
+      CliError::Input(_) => 3,
+      CliError::Output(_) => 4,
+      CliError::Serve(_) => 5,
+    }
+  }
+}
+
+impl fmt::Display for CliError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      CliError::Usage(message) => write!(f, "error: {}\n\n{}", message, USAGE),
+      CliError::Input(message) => write!(f, "error: {}", message),
+      CliError::Output(message) => write!(f, "error: {}", message),
+      CliError::Serve(message) => write!(f, "error: {}", message),
+      CliError::ChecksFailed(count) => write!(f, "check failed with {} problems", count),
+      CliError::MergeConflicts(count) => write!(f, "merge left out {} conflicts", count),
+    }
+  }
+}
+
+// Parsing
+// -------
+
+pub fn parse(args: &[String]) -> Result<Invocation, CliError> {
+  let mut args = args.iter().map(String::as_str);
+
+  let mut command_name = String::from(args.clone().next().unwrap_or_default());
+  let command = match (args.next(), args.clone().next()) {
+    (Some("fold"), _) => Command::Fold,
+    (Some("compact"), _) => Command::Compact,
+    (Some("gc"), _) => Command::Gc,
+    (Some("check"), _) => Command::Check,
+    (Some("export"), Some("openapi")) => Command::ExportOpenApi,
+    (Some("export"), Some("json-schema")) => Command::ExportJsonSchema,
+    (Some("export"), Some("typescript")) => Command::ExportTypeScript,
+    (Some("export"), Some("rust")) => Command::ExportRust,
+    (Some("export"), Some("examples")) => Command::ExportExamples,
+    (Some("export"), _) => {
+      return Err(CliError::Usage(String::from(
+        "export needs a target: openapi, json-schema, typescript, rust or examples",
+      )))
+    }
+    (Some("endpoints"), _) => Command::Endpoints,
+    (Some("shapes"), _) => Command::Shapes,
+    (Some("diff"), _) => Command::Diff,
+    (Some("changelog"), _) => Command::Changelog,
+    (Some("stats"), _) => Command::Stats,
+    (Some("merge"), _) => Command::Merge,
+    (Some("blame"), _) => Command::Blame,
+    (Some("mock"), _) => Command::Mock,
+    (Some(command), _) => return Err(CliError::Usage(format!("unknown command '{}'", command))),
+    (None, _) => return Err(CliError::Usage(String::from("no command given"))),
+  };
+  if let Command::ExportOpenApi
+  | Command::ExportJsonSchema
+  | Command::ExportTypeScript
+  | Command::ExportRust
+  | Command::ExportExamples = command
+  {
+    command_name = format!("{} {}", command_name, args.next().unwrap_or_default());
+  }
+  let target = match command {
+    Command::Blame => match args.clone().next() {
+      Some(target) if !target.starts_with('-') => args.next().map(String::from),
+      _ => {
+        return Err(CliError::Usage(String::from(
+          "blame needs an endpoint (like \"GET /users\") or a shape id",
+        )))
+      }
+    },
+    _ => None,
+  };
+
+  let mut input = None;
+  let mut format = None;
+  let mut output = None;
+  let mut against = None;
+  let mut base = None;
+  let mut snapshot = None;
+  let mut snapshot_encoding = SnapshotEncoding::Json;
+  let mut include_removed = false;
+  let mut gc = false;
+  let mut dedup = false;
+  let mut seed = 0;
+  let mut port = 8080;
+  let mut verbose = false;
+
+  while let Some(arg) = args.next() {
+    let option = match arg {
+      "-f" => "--format",
+      option => option,
+    };
+    if !command.accepts(option) {
+      return Err(CliError::Usage(format!(
+        "{} doesn't apply to {}",
+        option, command_name
+      )));
+    }
+    let mut value = || {
+      args
+        .next()
+        .map(String::from)
+        .ok_or_else(|| CliError::Usage(format!("{} needs a value", arg)))
+    };
+    match arg {
+      "-i" | "--input" => input = Some(value()?),
+      "-o" | "--output" => output = Some(value()?),
+      "-f" | "--format" => {
+        format = match value()?.as_str() {
+          "text" => Some(Format::Text),
+          "json" => Some(Format::Json),
+          other => return Err(CliError::Usage(format!("unknown format '{}'", other))),
+        }
+      }
+      "--against" => against = Some(value()?),
+      "--base" => base = Some(value()?),
+      "--snapshot" => snapshot = Some(value()?),
+      "--snapshot-encoding" => snapshot_encoding = value()?.parse().map_err(CliError::Usage)?,
+      "--include-removed" => include_removed = true,
+      "--gc" => gc = true,
+      "--dedup" => dedup = true,
+      "--seed" => {
+        seed = value()?
+          .parse()
+          .map_err(|_| CliError::Usage(String::from("--seed needs a number")))?
+      }
+      "--port" => {
+        port = value()?
+          .parse()
+          .map_err(|_| CliError::Usage(String::from("--port needs a port number")))?
+      }
+      "--verbose" => verbose = true,
+      other => return Err(CliError::Usage(format!("unexpected argument '{}'", other))),
+    }
+  }
+
+  let input = input.ok_or_else(|| CliError::Usage(String::from("--input is required")))?;
+  let format = format.unwrap_or(Format::Text);
+  if let (Command::Diff | Command::Merge, None) = (command, &against) {
+    return Err(CliError::Usage(String::from(
+      "diff and merge need --against",
+    )));
+  }
+
+  Ok(Invocation {
+    command,
+    input,
+    format,
+    output,
+    against,
+    base,
+    target,
+    snapshot,
+    snapshot_encoding,
+    include_removed,
+    gc,
+    dedup,
+    seed,
+    port,
+    verbose,
+  })
+}
+
+// Running
+// -------
+
+pub fn run(invocation: Invocation) -> Result<(), CliError> {
+  let events = read_events(&invocation.input)?;
+  let format = invocation.format;
+
+  match invocation.command {
+    Command::Fold => {
+      let aggregate = fold_with_snapshot(&invocation, events)?;
+      let state = aggregate.get_state();
+      let json = state_to_json(&state, invocation.include_removed)
+        .map_err(|err| CliError::Output(format!("output could not be serialized: {}", err)))?;
+      write_output(&invocation, to_json(&json)?)
+    }
+    Command::Compact => {
+      let aggregate = OpticAggregate::fold(events);
+      let compacted = match (invocation.gc, invocation.dedup) {
+        (false, false) => compact(&aggregate.get_state()),
+        (true, false) => compact_without_garbage(&aggregate.get_state()),
+        (gc, true) => {
+          // deduplicating after collecting garbage, so garbage never becomes a canonical shape
+          let mut aggregate = aggregate;
+          if gc {
+            for event in collect_garbage(&aggregate.get_state()) {
+              aggregate.apply(event);
+            }
+          }
+          compact_deduplicated(&aggregate.get_state())
+        }
+      };
+      write_output(&invocation, to_json(&compacted)?)
+    }
+    Command::Gc => {
+      let aggregate = OpticAggregate::fold(events);
+      write_output(
+        &invocation,
+        to_json(&collect_garbage(&aggregate.get_state()))?,
+      )
+    }
+    Command::Check => {
+      let aggregate = OpticAggregate::fold(events.clone());
+      let report = CheckReport::from_state(&aggregate.get_state()).with_batches(&events);
+      write_output(&invocation, render(&report, format)?)?;
+      if report.is_ok() {
+        Ok(())
+      } else {
+        Err(CliError::ChecksFailed(report.problems.len()))
+      }
+    }
+    Command::ExportOpenApi => {
+      let aggregate = OpticAggregate::fold(events);
+      write_output(&invocation, to_json(&openapi(&aggregate.get_state()))?)
+    }
+    Command::ExportJsonSchema => {
+      let aggregate = OpticAggregate::fold(events);
+      write_output(
+        &invocation,
+        to_json(&json_schema(aggregate.get_state().shape))?,
+      )
+    }
+    Command::ExportTypeScript => {
+      let aggregate = OpticAggregate::fold(events);
+      write_output(&invocation, typescript(&aggregate.get_state()))
+    }
+    Command::ExportRust => {
+      let aggregate = OpticAggregate::fold(events);
+      write_output(&invocation, rust(&aggregate.get_state()))
+    }
+    Command::ExportExamples => {
+      let aggregate = OpticAggregate::fold(events);
+      let document = examples(&aggregate.get_state(), invocation.seed);
+      write_output(&invocation, to_json(&document)?)
+    }
+    Command::Endpoints => {
+      let aggregate = OpticAggregate::fold(events);
+      let report = EndpointsReport::from_state(&aggregate.get_state());
+      write_output(&invocation, render(&report, format)?)
+    }
+    Command::Shapes => {
+      let aggregate = OpticAggregate::fold(events);
+      let report = ShapesReport::from_state(aggregate.get_state().shape);
+      write_output(&invocation, render(&report, format)?)
+    }
+    Command::Diff => {
+      let against = invocation.against.as_deref().unwrap_or_default();
+      let base = OpticAggregate::fold(read_events(against)?);
+      let head = OpticAggregate::fold(events);
+      let report = DiffReport::between(&base.get_state(), &head.get_state());
+      write_output(&invocation, render(&report, format)?)
+    }
+    Command::Changelog => {
+      let report = ChangelogReport::from_events(&events);
+      write_output(&invocation, render(&report, format)?)
+    }
+    Command::Stats => {
+      let aggregate = OpticAggregate::fold(events.clone());
+      let report = StatsReport::new(&events, &aggregate.get_state());
+      write_output(&invocation, render(&report, format)?)
+    }
+    Command::Blame => {
+      let target = invocation
+        .target
+        .as_deref()
+        .unwrap_or_default()
+        .parse()
+        .map_err(CliError::Usage)?;
+      let aggregate = OpticAggregate::fold(events.clone());
+      let report = BlameReport::for_target(&events, &aggregate.get_state(), &target)
+        .map_err(CliError::Usage)?;
+      write_output(&invocation, render(&report, format)?)
+    }
+    Command::Mock => {
+      let aggregate = OpticAggregate::fold(events);
+      let server = MockServer::from_state(&aggregate.get_state(), invocation.seed);
+      let address = format!("127.0.0.1:{}", invocation.port);
+      let listener = TcpListener::bind(&address)
+        .map_err(|err| CliError::Serve(format!("could not listen on {}: {}", address, err)))?;
+      println!("serving mock responses on http://{}", address);
+      server
+        .serve(listener, invocation.verbose)
+        .map_err(|err| CliError::Serve(format!("stopped serving on {}: {}", address, err)))
+    }
+    Command::Merge => {
+      let theirs = read_events(invocation.against.as_deref().unwrap_or_default())?;
+      let base = match &invocation.base {
+        Some(base) => Some(read_events(base)?),
+        None => None,
+      };
+      let merged =
+        merge(base.as_deref(), &events, &theirs).map_err(|err| CliError::Input(err.to_string()))?;
+      write_output(&invocation, to_json(&merged.events)?)?;
+      for conflict in &merged.conflicts {
+        eprint!("{}", conflict);
+      }
+      if merged.conflicts.is_empty() {
+        Ok(())
+      } else {
+        Err(CliError::MergeConflicts(merged.conflicts.len()))
+      }
+    }
+  }
+}
+
+fn read_events(filename: &str) -> Result<Vec<OpticEvent>, CliError> {
+  crate::events_from_file(filename).map_err(CliError::Input)
+}
+
+fn fold_with_snapshot(
+  invocation: &Invocation,
+  events: Vec<OpticEvent>,
+) -> Result<OpticAggregate, CliError> {
+  let snapshot_filename = match &invocation.snapshot {
+    Some(snapshot_filename) => snapshot_filename,
+    None => return Ok(OpticAggregate::fold(events)),
+  };
+
+  let snapshots = SnapshotFile::with_encoding(snapshot_filename, invocation.snapshot_encoding);
+  let id = OpticAggregateId(invocation.input.clone());
+  let restored = snapshot::restore_aggregate(&snapshots, &id, events)
+    .map_err(|err| CliError::Input(err.to_string()))?;
+  snapshots
+    .persist_snapshot(&id, &restored.payload, restored.version, None)
+    .map_err(|err| CliError::Output(err.to_string()))?;
+
+  Ok(restored.payload)
+}
+
+fn render<R: Serialize + fmt::Display>(report: &R, format: Format) -> Result<String, CliError> {
+  match format {
+    Format::Text => Ok(report.to_string()),
+    Format::Json => to_json(report),
+  }
+}
+
+fn to_json<T: Serialize + ?Sized>(value: &T) -> Result<String, CliError> {
+  serde_json::to_string_pretty(value)
+    .map(|json| json + "\n")
+    .map_err(|err| CliError::Output(format!("output could not be serialized: {}", err)))
+}
+
+fn write_output(invocation: &Invocation, rendered: String) -> Result<(), CliError> {
+  match &invocation.output {
+    Some(filename) => fs::write(filename, rendered)
+      .map_err(|err| CliError::Output(format!("{} could not be written: {}", filename, err))),
+    None => {
+      print!("{}", rendered);
+      Ok(())
+    }
+  }
+}
+
+#[test]
+fn parses_export_command_with_options() {
+  let args: Vec<String> = vec![
+    "export",
+    "openapi",
+    "-i",
+    "spec.json",
+    "--output",
+    "out.json",
+  ]
+  .into_iter()
+  .map(String::from)
+  .collect();
+
+  let invocation = parse(&args).unwrap();
+  assert_eq!(invocation.command, Command::ExportOpenApi);
+  assert_eq!(invocation.input, "spec.json");
+  assert_eq!(invocation.output.as_deref(), Some("out.json"));
+  assert_eq!(invocation.format, Format::Text);
+}
+
+#[test]
+fn rejects_invalid_usage() {
+  let parse_args = |args: &[&str]| {
+    let args: Vec<String> = args.iter().map(|arg| String::from(*arg)).collect();
+    parse(&args).unwrap_err().exit_code()
+  };
+
+  assert_eq!(parse_args(&[]), 2);
+  assert_eq!(parse_args(&["fold"]), 2);
+  assert_eq!(parse_args(&["unfold", "-i", "spec.json"]), 2);
+  assert_eq!(parse_args(&["diff", "-i", "spec.json"]), 2);
+  assert_eq!(parse_args(&["blame", "-i", "spec.json"]), 2);
+  assert_eq!(parse_args(&["stats", "-i", "spec.json", "-f", "yaml"]), 2);
+  assert_eq!(
+    parse_args(&["export", "openapi", "-i", "spec.json", "-f", "text"]),
+    2
+  );
+  assert_eq!(
+    parse_args(&["export", "examples", "-i", "spec.json", "--seed", "x"]),
+    2
+  );
+  assert_eq!(
+    parse_args(&["mock", "-i", "spec.json", "--port", "80000"]),
+    2
+  );
+  // options of other commands
+  assert_eq!(parse_args(&["fold", "-i", "spec.json", "-f", "json"]), 2);
+  assert_eq!(parse_args(&["check", "-i", "spec.json", "--port", "1"]), 2);
+  assert_eq!(parse_args(&["fold", "-i", "spec.json", "--gc"]), 2);
+  assert_eq!(parse_args(&["stats", "-i", "spec.json", "--verbose"]), 2);
+  assert_eq!(
+    parse_args(&[
+      "diff",
+      "-i",
+      "spec.json",
+      "--against",
+      "a.json",
+      "--base",
+      "b.json"
+    ]),
+    2
+  );
+  assert_eq!(
+    parse_args(&["export", "rust", "-i", "spec.json", "--seed", "1"]),
+    2
+  );
+  assert_eq!(
+    parse_args(&["compact", "-i", "spec.json", "--snapshot", "s.json"]),
+    2
+  );
+}
@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+
+use crate::aggregate::OpticState;
+use crate::events::requests::RequestsEvent;
+use crate::events::shape::ShapeEvent;
+use crate::events::OpticEvent;
+use crate::projections::equivalence::canonical_shape_ids;
+use crate::state::shape::{
+  FieldShapeDescriptor, ParameterShapeDescriptor, ProviderDescriptor, ShapeId,
+};
+
+// Compaction that keeps one shape of every set of structurally equivalent shapes, pointing every
+// reference to the others (base shapes, field shapes, bindings, bodies and parameters) at it
+pub fn compact_deduplicated(state: &OpticState) -> Vec<OpticEvent> {
+  let canonical_shape_ids = canonical_shape_ids(state.shape);
+  let mut events = super::compact_shapes_where(state, |shape_id| {
+    !canonical_shape_ids.contains_key(&shape_id)
+  });
+  for event in &mut events {
+    rewrite_shape_ids(event, &canonical_shape_ids);
+  }
+  events
+}
+
+fn rewrite_shape_ids(event: &mut OpticEvent, canonical_shape_ids: &HashMap<ShapeId, ShapeId>) {
+  let canonical = |shape_id: &mut ShapeId| {
+    if let Some(canonical_shape_id) = canonical_shape_ids.get(shape_id) {
+      *shape_id = *canonical_shape_id;
+    }
+  };
+  let canonical_provider = |provider: &mut ProviderDescriptor| {
+    if let ProviderDescriptor::ShapeProvider(provider) = provider {
+      canonical(&mut provider.shape_id);
+    }
+  };
+
+  match event {
+    OpticEvent::ShapeEvent(ShapeEvent::ShapeAdded(e)) => canonical(&mut e.base_shape_id),
+    OpticEvent::ShapeEvent(ShapeEvent::FieldAdded(e)) => {
+      if let FieldShapeDescriptor::FieldShapeFromShape(descriptor) = &mut e.shape_descriptor {
+        canonical(&mut descriptor.shape_id);
+      }
+    }
+    OpticEvent::ShapeEvent(ShapeEvent::ShapeParameterShapeSet(e)) => {
+      match &mut e.shape_descriptor {
+        ParameterShapeDescriptor::ProviderInShape(binding) => {
+          canonical_provider(&mut binding.provider_descriptor)
+        }
+        ParameterShapeDescriptor::ProviderInField(binding) => {
+          canonical_provider(&mut binding.provider_descriptor)
+        }
+      }
+    }
+    OpticEvent::RequestsEvent(RequestsEvent::PathParameterShapeSet(e)) => {
+      canonical(&mut e.shape_descriptor.shape_id)
+    }
+    OpticEvent::RequestsEvent(RequestsEvent::RequestParameterShapeSet(e)) => {
+      canonical(&mut e.parameter_descriptor.shape_id)
+    }
+    OpticEvent::RequestsEvent(RequestsEvent::RequestBodySet(e)) => {
+      canonical(&mut e.body_descriptor.shape_id)
+    }
+    OpticEvent::RequestsEvent(RequestsEvent::ResponseBodySet(e)) => {
+      canonical(&mut e.body_descriptor.shape_id)
+    }
+    _ => {}
+  }
+}
+
+#[test]
+fn deduplicated_specs_are_smaller_and_equivalent() {
+  use crate::aggregate::OpticAggregate;
+  use crate::projections::equivalence::equivalence_classes;
+
+  let original =
+    OpticAggregate::fold(crate::events_from_file("test-fixtures/uncompacted-spec.json").unwrap());
+  let compacted = super::compact(&original.get_state());
+  let deduplicated = compact_deduplicated(&original.get_state());
+  assert!(deduplicated.len() < compacted.len());
+
+  // every remaining shape is structurally unique, and no reference is left dangling
+  let deduplicated = OpticAggregate::fold(deduplicated);
+  let state = deduplicated.get_state();
+  assert!(equivalence_classes(state.shape)
+    .iter()
+    .all(|class| class.len() == 1));
+  assert!(crate::reports::check::CheckReport::from_state(&state).is_ok());
+
+  // the endpoints still describe the same requests and responses
+  let diff = crate::reports::diff::DiffReport::between(&original.get_state(), &state);
+  assert!(diff.changes.is_empty());
+}
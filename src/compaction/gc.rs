@@ -0,0 +1,74 @@
+use crate::aggregate::OpticState;
+use crate::events::shape::{FieldRemoved, ShapeEvent, ShapeRemoved};
+use crate::events::OpticEvent;
+use crate::projections::reachability::unreachable_shapes;
+
+// The events that remove every shape no endpoint refers to, directly or through fields, base
+// shapes and parameter bindings. Each shape's fields are removed before the shape itself.
+pub fn collect_garbage(state: &OpticState) -> Vec<OpticEvent> {
+  let mut events = vec![];
+
+  for shape_id in unreachable_shapes(state) {
+    for field in state.shape.fields_of(shape_id) {
+      events.push(OpticEvent::ShapeEvent(ShapeEvent::FieldRemoved(
+        FieldRemoved {
+          field_id: field.field_id,
+          reverts_addition: false,
+          event_context: None,
+        },
+      )));
+    }
+    events.push(OpticEvent::ShapeEvent(ShapeEvent::ShapeRemoved(
+      ShapeRemoved {
+        shape_id,
+        reverts_addition: false,
+        event_context: None,
+      },
+    )));
+  }
+
+  events
+}
+
+#[test]
+fn collected_garbage_leaves_only_reachable_shapes() {
+  use crate::aggregate::{Aggregate, OpticAggregate};
+  use crate::projections::reachability::reachable_shapes;
+
+  let mut aggregate =
+    OpticAggregate::fold(crate::events_from_file("test-fixtures/uncompacted-spec.json").unwrap());
+  // scaffolding left behind by shape learning, which no endpoint refers to
+  let scaffolding: Vec<OpticEvent> = serde_json::from_str(
+    r#"[
+      {"ShapeAdded":{"shapeId":"scaffold_0","baseShapeId":"$object","parameters":{"DynamicParameterList":{"shapeParameterIds":[]}},"name":"","eventContext":null}},
+      {"ShapeAdded":{"shapeId":"scaffold_1","baseShapeId":"$string","parameters":{"DynamicParameterList":{"shapeParameterIds":[]}},"name":"","eventContext":null}},
+      {"FieldAdded":{"fieldId":"scaffold_2","shapeId":"scaffold_0","name":"id","shapeDescriptor":{"FieldShapeFromShape":{"fieldId":"scaffold_2","shapeId":"scaffold_1"}},"eventContext":null}}
+    ]"#,
+  )
+  .unwrap();
+  for event in scaffolding {
+    aggregate.apply(event);
+  }
+  let garbage = collect_garbage(&aggregate.get_state());
+  assert_eq!(garbage.len(), 3);
+
+  let reachable = reachable_shapes(&aggregate.get_state());
+  let compacted_without_garbage = super::compact_without_garbage(&aggregate.get_state());
+  for event in garbage {
+    aggregate.apply(event);
+  }
+  let state = aggregate.get_state();
+  assert!(unreachable_shapes(&state).is_empty());
+  for shape in state.shape.all_shapes().filter(|s| !s.is_removed) {
+    assert!(reachable.contains(&shape.shape_id));
+  }
+  for field in state.shape.all_fields().filter(|f| !f.is_removed) {
+    assert!(reachable.contains(&field.descriptor.shape_id));
+  }
+
+  // compacting without garbage is the same as compacting after the garbage was removed
+  assert_eq!(
+    serde_json::to_string(&compacted_without_garbage).unwrap(),
+    serde_json::to_string(&super::compact(&state)).unwrap()
+  );
+}
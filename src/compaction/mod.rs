@@ -0,0 +1,269 @@
+use crate::aggregate::OpticState;
+use crate::events::requests::{
+  PathComponentAdded, PathParameterAdded, PathParameterShapeSet, RequestAdded, RequestBodySet,
+  RequestParameterAddedByPathAndMethod, RequestParameterShapeSet, RequestsEvent,
+  ResponseAddedByPathAndMethod, ResponseBodySet,
+};
+use crate::events::rfc::{APINamed, ContributionAdded, RfcEvent};
+use crate::events::shape::{FieldAdded, ShapeAdded, ShapeEvent, ShapeParameterShapeSet};
+use crate::events::OpticEvent;
+use crate::projections::reachability::unreachable_shapes;
+use crate::state::requests::PathComponentDescriptor;
+use crate::state::shape::{ParameterShapeDescriptor, ProviderInField, ProviderInShape, ShapeId};
+
+pub mod dedup;
+pub mod gc;
+
+// Compaction replaces the history of a spec with the minimal stream of events that folds into the
+// same state: one event to add every live entity and one to set each of its descriptors. Removed
+// entities, renames, batches and event contexts are left behind.
+pub fn compact(state: &OpticState) -> Vec<OpticEvent> {
+  compact_shapes_where(state, |_| true)
+}
+
+// Compaction that also leaves behind the shapes no endpoint refers to, and their fields
+pub fn compact_without_garbage(state: &OpticState) -> Vec<OpticEvent> {
+  let garbage = unreachable_shapes(state);
+  compact_shapes_where(state, |shape_id| !garbage.contains(&shape_id))
+}
+
+fn compact_shapes_where<F: Fn(ShapeId) -> bool>(
+  state: &OpticState,
+  keep_shape: F,
+) -> Vec<OpticEvent> {
+  let mut events = vec![];
+
+  if let Some(name) = &state.rfc.api_name {
+    events.push(OpticEvent::RfcEvent(RfcEvent::APINamed(APINamed {
+      name: name.clone(),
+      event_context: None,
+    })));
+  }
+
+  compact_shapes(state, keep_shape, &mut events);
+  compact_requests(state, &mut events);
+
+  for (id, contributions) in &state.rfc.contributions {
+    for (key, value) in contributions {
+      events.push(OpticEvent::RfcEvent(RfcEvent::ContributionAdded(
+        ContributionAdded {
+          id: id.clone(),
+          key: key.clone(),
+          value: value.clone(),
+          event_context: None,
+        },
+      )));
+    }
+  }
+
+  events
+}
+
+fn compact_shapes<F: Fn(ShapeId) -> bool>(
+  state: &OpticState,
+  keep_shape: F,
+  events: &mut Vec<OpticEvent>,
+) {
+  let shapes: Vec<_> = state
+    .shape
+    .all_shapes()
+    .filter(|s| !s.is_removed && keep_shape(s.shape_id))
+    .collect();
+  let fields: Vec<_> = state
+    .shape
+    .all_fields()
+    .filter(|f| !f.is_removed && keep_shape(f.descriptor.shape_id))
+    .collect();
+
+  // all shapes first, so fields and bindings never refer to shapes that don't exist yet
+  for shape in &shapes {
+    events.push(OpticEvent::ShapeEvent(ShapeEvent::ShapeAdded(ShapeAdded {
+      shape_id: shape.shape_id,
+      base_shape_id: shape.descriptor.base_shape_id,
+      parameters: shape.descriptor.parameters.clone(),
+      name: shape.descriptor.name.clone(),
+      event_context: None,
+    })));
+  }
+
+  for shape in &shapes {
+    for field in state.shape.fields_of(shape.shape_id) {
+      events.push(OpticEvent::ShapeEvent(ShapeEvent::FieldAdded(FieldAdded {
+        field_id: field.field_id,
+        shape_id: field.descriptor.shape_id,
+        name: field.descriptor.name.clone(),
+        shape_descriptor: field.descriptor.shape_descriptor.clone(),
+        event_context: None,
+      })));
+    }
+  }
+
+  for shape in &shapes {
+    for (parameter_id, provider) in &shape.descriptor.bindings {
+      let binding = ParameterShapeDescriptor::ProviderInShape(ProviderInShape {
+        shape_id: shape.shape_id,
+        provider_descriptor: provider.clone(),
+        consuming_parameter_id: *parameter_id,
+      });
+      events.push(parameter_shape_set(binding));
+    }
+  }
+
+  for field in &fields {
+    for (parameter_id, provider) in &field.descriptor.bindings {
+      let binding = ParameterShapeDescriptor::ProviderInField(ProviderInField {
+        field_id: field.field_id,
+        provider_descriptor: provider.clone(),
+        consuming_parameter_id: *parameter_id,
+      });
+      events.push(parameter_shape_set(binding));
+    }
+  }
+}
+
+fn parameter_shape_set(shape_descriptor: ParameterShapeDescriptor) -> OpticEvent {
+  OpticEvent::ShapeEvent(ShapeEvent::ShapeParameterShapeSet(ShapeParameterShapeSet {
+    shape_descriptor,
+    event_context: None,
+  }))
+}
+
+fn compact_requests(state: &OpticState, events: &mut Vec<OpticEvent>) {
+  let requests_state = state.requests;
+
+  for component in requests_state
+    .all_path_components()
+    .filter(|c| !c.is_removed)
+  {
+    match &component.descriptor {
+      PathComponentDescriptor::Basic(descriptor) => {
+        events.push(OpticEvent::RequestsEvent(
+          RequestsEvent::PathComponentAdded(PathComponentAdded {
+            path_id: component.path_id,
+            parent_path_id: descriptor.parent_path_id,
+            name: descriptor.name.clone(),
+            event_context: None,
+          }),
+        ));
+      }
+      PathComponentDescriptor::Parameterized(descriptor) => {
+        events.push(OpticEvent::RequestsEvent(
+          RequestsEvent::PathParameterAdded(PathParameterAdded {
+            path_id: component.path_id,
+            parent_path_id: descriptor.parent_path_id,
+            name: descriptor.name.clone(),
+            event_context: None,
+          }),
+        ));
+        if let Some(shaped) = descriptor.shape_descriptor.shaped() {
+          events.push(OpticEvent::RequestsEvent(
+            RequestsEvent::PathParameterShapeSet(PathParameterShapeSet {
+              path_id: component.path_id,
+              shape_descriptor: shaped.clone(),
+              event_context: None,
+            }),
+          ));
+        }
+      }
+    }
+  }
+
+  for parameter in requests_state
+    .all_request_parameters()
+    .filter(|p| !p.is_removed)
+  {
+    let descriptor = &parameter.request_parameter_descriptor;
+    events.push(OpticEvent::RequestsEvent(
+      RequestsEvent::RequestParameterAddedByPathAndMethod(RequestParameterAddedByPathAndMethod {
+        parameter_id: parameter.parameter_id,
+        path_id: descriptor.path_id,
+        http_method: descriptor.http_method.clone(),
+        parameter_location: descriptor.location,
+        name: descriptor.name.clone(),
+        event_context: None,
+      }),
+    ));
+    if let Some(shaped) = descriptor.shape_descriptor.shaped() {
+      events.push(OpticEvent::RequestsEvent(
+        RequestsEvent::RequestParameterShapeSet(RequestParameterShapeSet {
+          parameter_id: parameter.parameter_id,
+          parameter_descriptor: shaped.clone(),
+          event_context: None,
+        }),
+      ));
+    }
+  }
+
+  for request in requests_state.all_requests().filter(|r| !r.is_removed) {
+    let descriptor = &request.request_descriptor;
+    events.push(OpticEvent::RequestsEvent(RequestsEvent::RequestAdded(
+      RequestAdded {
+        request_id: request.request_id,
+        path_id: descriptor.path_component_id,
+        http_method: descriptor.http_method.clone(),
+        event_context: None,
+      },
+    )));
+    for body in descriptor.bodies.values() {
+      events.push(OpticEvent::RequestsEvent(RequestsEvent::RequestBodySet(
+        RequestBodySet {
+          request_id: request.request_id,
+          body_descriptor: body.clone(),
+          event_context: None,
+        },
+      )));
+    }
+  }
+
+  for response in requests_state.all_responses().filter(|r| !r.is_removed) {
+    let descriptor = &response.response_descriptor;
+    events.push(OpticEvent::RequestsEvent(
+      RequestsEvent::ResponseAddedByPathAndMethod(ResponseAddedByPathAndMethod {
+        response_id: response.response_id,
+        path_id: descriptor.path_id,
+        http_method: descriptor.http_method.clone(),
+        http_status_code: descriptor.http_status_code,
+        event_context: None,
+      }),
+    ));
+    for body in descriptor.bodies.values() {
+      events.push(OpticEvent::RequestsEvent(RequestsEvent::ResponseBodySet(
+        ResponseBodySet {
+          response_id: response.response_id,
+          body_descriptor: body.clone(),
+          event_context: None,
+        },
+      )));
+    }
+  }
+}
+
+#[test]
+fn compacted_events_fold_into_the_same_state() {
+  use crate::aggregate::OpticAggregate;
+
+  let events = crate::events_from_file("test-fixtures/uncompacted-spec.json").unwrap();
+  let event_count = events.len();
+  let original = OpticAggregate::fold(events);
+  let compacted_events = compact(&original.get_state());
+  assert!(compacted_events.len() < event_count);
+
+  // round trip through JSON, as a compacted spec is written to and read from a file
+  let compacted_json = serde_json::to_string(&compacted_events).unwrap();
+  let compacted =
+    OpticAggregate::fold(serde_json::from_str::<Vec<OpticEvent>>(&compacted_json).unwrap());
+
+  let (original, compacted) = (original.get_state(), compacted.get_state());
+  assert_eq!(
+    serde_json::to_value(original.requests).unwrap(),
+    serde_json::to_value(compacted.requests).unwrap()
+  );
+  assert_eq!(
+    serde_json::to_value(original.shape).unwrap(),
+    serde_json::to_value(compacted.shape).unwrap()
+  );
+  assert_eq!(
+    serde_json::to_value(&original.rfc.contributions).unwrap(),
+    serde_json::to_value(&compacted.rfc.contributions).unwrap()
+  );
+}
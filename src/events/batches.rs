@@ -0,0 +1,353 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use super::rfc::{BatchCommitEnded, BatchCommitStarted, RfcEvent};
+use super::OpticEvent;
+
+// The events committed together between a `BatchCommitStarted` and its `BatchCommitEnded`, or a
+// run of consecutive events committed outside of any batch
+pub struct Batch<'a> {
+  // the index of the batch's first event in the stream, including the start of the batch
+  pub index: usize,
+  pub started: Option<&'a BatchCommitStarted>,
+  pub events: &'a [OpticEvent],
+  // missing when the stream, another batch or the end of another batch ends it first
+  pub ended: Option<&'a BatchCommitEnded>,
+}
+
+impl<'a> Batch<'a> {
+  pub fn batch_id(&self) -> Option<&'a str> {
+    self.started.map(|started| started.batch_id.as_str())
+  }
+
+  pub fn commit_message(&self) -> Option<&'a str> {
+    self.started.map(|started| started.commit_message.trim())
+  }
+
+  pub fn is_outside_batches(&self) -> bool {
+    self.started.is_none()
+  }
+}
+
+// Iterates a stream of events batch by batch. Batches don't nest: a batch that's started before
+// the previous one ended ends it, as does an end of another batch, without either being its end.
+// An end without a start is skipped.
+pub struct Batches<'a> {
+  events: &'a [OpticEvent],
+  index: usize,
+}
+
+pub fn batches(events: &[OpticEvent]) -> Batches<'_> {
+  Batches { events, index: 0 }
+}
+
+impl<'a> Iterator for Batches<'a> {
+  type Item = Batch<'a>;
+
+  fn next(&mut self) -> Option<Batch<'a>> {
+    while let Some(OpticEvent::RfcEvent(RfcEvent::BatchCommitEnded(_))) =
+      self.events.get(self.index)
+    {
+      self.index += 1;
+    }
+    let index = self.index;
+    let started = match self.events.get(index)? {
+      OpticEvent::RfcEvent(RfcEvent::BatchCommitStarted(e)) => Some(e),
+      _ => None,
+    };
+
+    let first = if started.is_some() { index + 1 } else { index };
+    let length = self.events[first..]
+      .iter()
+      .position(|event| match event {
+        OpticEvent::RfcEvent(RfcEvent::BatchCommitStarted(_)) => true,
+        OpticEvent::RfcEvent(RfcEvent::BatchCommitEnded(_)) => started.is_some(),
+        _ => false,
+      })
+      .unwrap_or(self.events.len() - first);
+    let ended = match (started, self.events.get(first + length)) {
+      (Some(started), Some(OpticEvent::RfcEvent(RfcEvent::BatchCommitEnded(e)))) => {
+        self.index = first + length + 1;
+        Some(e).filter(|e| e.batch_id == started.batch_id)
+      }
+      _ => {
+        self.index = first + length;
+        None
+      }
+    };
+
+    Some(Batch {
+      index,
+      started,
+      events: &self.events[first..first + length],
+      ended,
+    })
+  }
+}
+
+// Validation
+// ----------
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "problem")]
+pub enum BatchProblem {
+  // a batch started before the batch it's in ended
+  NestedBatch {
+    index: usize,
+    batch_id: String,
+    open_batch_id: String,
+  },
+  // a batch that's still open when the stream ends
+  UnterminatedBatch {
+    index: usize,
+    batch_id: String,
+  },
+  // a batch id that's started again after being used
+  RepeatedBatchId {
+    index: usize,
+    batch_id: String,
+  },
+  // an end without a start, like a batch ended twice
+  UnmatchedEnd {
+    index: usize,
+    batch_id: String,
+  },
+  // an end of another batch than the one that's open, which ends the open one
+  MismatchedEnd {
+    index: usize,
+    batch_id: String,
+    open_batch_id: String,
+  },
+  // consecutive events committed outside of any batch
+  EventsOutsideBatches {
+    index: usize,
+    count: usize,
+  },
+}
+
+impl BatchProblem {
+  // the index in the stream of the event with the problem
+  pub fn index(&self) -> usize {
+    match self {
+      BatchProblem::NestedBatch { index, .. }
+      | BatchProblem::UnterminatedBatch { index, .. }
+      | BatchProblem::RepeatedBatchId { index, .. }
+      | BatchProblem::UnmatchedEnd { index, .. }
+      | BatchProblem::MismatchedEnd { index, .. }
+      | BatchProblem::EventsOutsideBatches { index, .. } => *index,
+    }
+  }
+
+  // events outside of batches are allowed, just not committed like the rest
+  pub fn is_warning(&self) -> bool {
+    matches!(self, BatchProblem::EventsOutsideBatches { .. })
+  }
+}
+
+// Checks every batch is started once and ended once, without other batches started in between.
+// Like `batches`, a batch that's started or ended while another is open ends the open one, so each
+// fault is reported once: the open batch isn't reported as unterminated as well.
+pub fn validate_batches(events: &[OpticEvent]) -> Vec<BatchProblem> {
+  let mut problems = vec![];
+  let mut started_at: HashMap<&str, usize> = HashMap::new();
+  let mut open: Option<(usize, &str)> = None;
+  let mut outside: Option<(usize, usize)> = None;
+
+  for (index, event) in events.iter().enumerate() {
+    match event {
+      OpticEvent::RfcEvent(RfcEvent::BatchCommitStarted(e)) => {
+        if let Some((_, open_batch_id)) = open {
+          problems.push(BatchProblem::NestedBatch {
+            index,
+            batch_id: e.batch_id.clone(),
+            open_batch_id: String::from(open_batch_id),
+          });
+        }
+        if started_at.insert(&e.batch_id, index).is_some() {
+          problems.push(BatchProblem::RepeatedBatchId {
+            index,
+            batch_id: e.batch_id.clone(),
+          });
+        }
+        open = Some((index, &e.batch_id));
+      }
+      OpticEvent::RfcEvent(RfcEvent::BatchCommitEnded(e)) => match open.take() {
+        Some((_, open_batch_id)) if open_batch_id == e.batch_id => {}
+        Some((_, open_batch_id)) => problems.push(BatchProblem::MismatchedEnd {
+          index,
+          batch_id: e.batch_id.clone(),
+          open_batch_id: String::from(open_batch_id),
+        }),
+        None => problems.push(BatchProblem::UnmatchedEnd {
+          index,
+          batch_id: e.batch_id.clone(),
+        }),
+      },
+      _ if open.is_none() => {
+        let (_, count) = outside.get_or_insert((index, 0));
+        *count += 1;
+        continue;
+      }
+      _ => {}
+    }
+    if let Some((index, count)) = outside.take() {
+      problems.push(BatchProblem::EventsOutsideBatches { index, count });
+    }
+  }
+
+  problems.extend(
+    outside
+      .into_iter()
+      .map(|(index, count)| BatchProblem::EventsOutsideBatches { index, count }),
+  );
+  if let Some((index, batch_id)) = open {
+    problems.push(BatchProblem::UnterminatedBatch {
+      index,
+      batch_id: String::from(batch_id),
+    });
+  }
+  problems.sort_by_key(BatchProblem::index);
+  problems
+}
+
+impl fmt::Display for BatchProblem {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      BatchProblem::NestedBatch {
+        batch_id,
+        open_batch_id,
+        ..
+      } => write!(
+        f,
+        "batch {} is started before batch {} ended",
+        batch_id, open_batch_id
+      ),
+      BatchProblem::UnterminatedBatch { batch_id, .. } => {
+        write!(f, "batch {} is never ended", batch_id)
+      }
+      BatchProblem::RepeatedBatchId { batch_id, .. } => {
+        write!(f, "batch {} is started more than once", batch_id)
+      }
+      BatchProblem::UnmatchedEnd { batch_id, .. } => {
+        write!(f, "batch {} is ended without being started", batch_id)
+      }
+      BatchProblem::MismatchedEnd {
+        batch_id,
+        open_batch_id,
+        ..
+      } => write!(
+        f,
+        "batch {} is ended while batch {} is open",
+        batch_id, open_batch_id
+      ),
+      BatchProblem::EventsOutsideBatches { count: 1, .. } => {
+        write!(f, "1 event is committed outside of batches")
+      }
+      BatchProblem::EventsOutsideBatches { count, .. } => {
+        write!(f, "{} events are committed outside of batches", count)
+      }
+    }
+  }
+}
+
+#[test]
+fn streams_are_grouped_and_validated_by_batch() {
+  use cqrs_core::Event;
+
+  let events = crate::events_from_file("test-fixtures/uncompacted-spec.json").unwrap();
+
+  // the fixture's batches are well formed, but some of its events aren't in any
+  let problems = validate_batches(&events);
+  assert!(problems.iter().all(BatchProblem::is_warning));
+  let outside: usize = problems
+    .iter()
+    .map(|problem| match problem {
+      BatchProblem::EventsOutsideBatches { count, .. } => *count,
+      _ => 0,
+    })
+    .sum();
+  assert_eq!(outside, 24);
+
+  let grouped: Vec<Batch> = batches(&events).collect();
+  let markers = events
+    .iter()
+    .filter(|event| {
+      matches!(
+        event,
+        OpticEvent::RfcEvent(RfcEvent::BatchCommitStarted(_))
+          | OpticEvent::RfcEvent(RfcEvent::BatchCommitEnded(_))
+      )
+    })
+    .count();
+  let grouped_events: usize = grouped.iter().map(|batch| batch.events.len()).sum();
+  assert_eq!(grouped_events + markers, events.len());
+  assert!(grouped
+    .iter()
+    .filter(|batch| !batch.is_outside_batches())
+    .all(|batch| batch.ended.is_some()));
+  let last = grouped.last().unwrap();
+  assert_eq!(
+    last.batch_id(),
+    Some("84008f7c-c965-469d-a71e-e4f61fe8939a")
+  );
+  assert_eq!(last.events.len(), 125);
+  assert_eq!(last.events[0].event_type(), "ResponseAddedByPathAndMethod");
+
+  // a batch started in another, ended twice, ended by another's end and never ended, each reported
+  // once
+  let malformed: Vec<OpticEvent> = serde_json::from_value(serde_json::json!([
+    {"BatchCommitStarted": {"batchId": "a", "commitMessage": "", "eventContext": null}},
+    {"APINamed": {"name": "api", "eventContext": null}},
+    {"BatchCommitStarted": {"batchId": "b", "commitMessage": "", "eventContext": null}},
+    {"BatchCommitEnded": {"batchId": "b", "eventContext": null}},
+    {"BatchCommitEnded": {"batchId": "b", "eventContext": null}},
+    {"APINamed": {"name": "api", "eventContext": null}},
+    {"BatchCommitStarted": {"batchId": "a", "commitMessage": "", "eventContext": null}},
+    {"APINamed": {"name": "api", "eventContext": null}},
+    {"BatchCommitEnded": {"batchId": "c", "eventContext": null}},
+    {"BatchCommitStarted": {"batchId": "d", "commitMessage": "", "eventContext": null}},
+  ]))
+  .unwrap();
+  assert_eq!(
+    validate_batches(&malformed),
+    vec![
+      BatchProblem::NestedBatch {
+        index: 2,
+        batch_id: String::from("b"),
+        open_batch_id: String::from("a")
+      },
+      BatchProblem::UnmatchedEnd {
+        index: 4,
+        batch_id: String::from("b")
+      },
+      BatchProblem::EventsOutsideBatches { index: 5, count: 1 },
+      BatchProblem::RepeatedBatchId {
+        index: 6,
+        batch_id: String::from("a")
+      },
+      BatchProblem::MismatchedEnd {
+        index: 8,
+        batch_id: String::from("c"),
+        open_batch_id: String::from("a")
+      },
+      BatchProblem::UnterminatedBatch {
+        index: 9,
+        batch_id: String::from("d")
+      },
+    ]
+  );
+  // the batches that aren't ended by their own end are the ones with problems
+  let grouped: Vec<(Option<&str>, usize, bool)> = batches(&malformed)
+    .map(|batch| (batch.batch_id(), batch.events.len(), batch.ended.is_some()))
+    .collect();
+  assert_eq!(
+    grouped,
+    vec![
+      (Some("a"), 1, false),
+      (Some("b"), 0, true),
+      (None, 1, false),
+      (Some("a"), 1, false),
+      (Some("d"), 0, false)
+    ]
+  );
+}
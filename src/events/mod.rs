@@ -1,28 +1,104 @@
 #![allow(dead_code)]
 
-use cqrs_core::Event;
+use cqrs_core::{Aggregate, Event};
+use serde::Deserialize;
+use serde_json::Value;
 
+use crate::aggregate::requests::RequestsAggregate;
+use crate::aggregate::rfc::RfcAggregate;
+use crate::aggregate::shape::ShapeAggregate;
+
+pub mod batches;
 pub mod requests;
 pub mod rfc;
 pub mod shape;
+pub mod upcasting;
 
-#[derive(Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct EventContext {
+pub struct EventContext {
   client_id: String,
   client_session_id: String,
   client_command_batch_id: String,
   created_at: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(untagged)]
+#[allow(clippy::enum_variant_names)] // variant names are part of the serialized format
 pub enum OpticEvent {
   RequestsEvent(requests::RequestsEvent),
   RfcEvent(rfc::RfcEvent),
   ShapeEvent(shape::ShapeEvent),
 }
 
+impl EventContext {
+  pub fn client_id(&self) -> &str {
+    &self.client_id
+  }
+
+  pub fn client_session_id(&self) -> &str {
+    &self.client_session_id
+  }
+
+  // the batch of the client command that emitted the event, not the batch commit it's part of
+  pub fn client_command_batch_id(&self) -> &str {
+    &self.client_command_batch_id
+  }
+
+  pub fn created_at(&self) -> &str {
+    &self.created_at
+  }
+}
+
+impl OpticEvent {
+  // Deserializes a raw event. Where `OpticEvent` itself can only tell that the event is none of the
+  // events, the events of its aggregate tell what's wrong with it (like an unknown parameter
+  // location).
+  pub fn from_value(raw_event: &Value) -> Result<Self, String> {
+    let err = match OpticEvent::deserialize(raw_event) {
+      Ok(event) => return Ok(event),
+      Err(err) => err,
+    };
+
+    let event_type = raw_event
+      .as_object()
+      .and_then(|event| event.keys().next())
+      .map_or("", String::as_str);
+    let not_of_aggregate = format!("unknown variant `{}`", event_type);
+    let errors = [
+      requests::RequestsEvent::deserialize(raw_event).err(),
+      rfc::RfcEvent::deserialize(raw_event).err(),
+      shape::ShapeEvent::deserialize(raw_event).err(),
+    ];
+    Err(
+      errors
+        .iter()
+        .flatten()
+        .map(ToString::to_string)
+        .find(|message| !message.starts_with(&not_of_aggregate))
+        .unwrap_or_else(|| err.to_string()),
+    )
+  }
+
+  pub fn event_context(&self) -> Option<&EventContext> {
+    match self {
+      OpticEvent::RequestsEvent(evt) => evt.event_context(),
+      OpticEvent::RfcEvent(evt) => evt.event_context(),
+      OpticEvent::ShapeEvent(evt) => evt.event_context(),
+    }
+  }
+
+  // the type of the aggregate the event applies to
+  pub fn aggregate_type(&self) -> &'static str {
+    match self {
+      OpticEvent::RequestsEvent(_) => RequestsAggregate::aggregate_type(),
+      OpticEvent::RfcEvent(_) => RfcAggregate::aggregate_type(),
+      OpticEvent::ShapeEvent(_) => ShapeAggregate::aggregate_type(),
+    }
+  }
+}
+
 impl Event for OpticEvent {
   fn event_type(&self) -> &'static str {
     match *self {
@@ -1,12 +1,13 @@
 use super::EventContext;
 use cqrs_core::Event;
 
+use crate::state::http::{HttpMethod, StatusCode};
 use crate::state::requests::{
-  PathComponentId, RequestId, RequestParameterId, ResponseId, ShapedBodyDescriptor,
-  ShapedRequestParameterShapeDescriptor,
+  ParameterLocation, PathComponentId, RequestId, RequestParameterId, ResponseId,
+  ShapedBodyDescriptor, ShapedRequestParameterShapeDescriptor,
 };
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub enum RequestsEvent {
   // path components
   PathComponentAdded(PathComponentAdded),
@@ -15,6 +16,7 @@ pub enum RequestsEvent {
 
   // path parameters
   PathParameterAdded(PathParameterAdded),
+  PathParameterShapeSet(PathParameterShapeSet),
   PathParameterRenamed(PathParameterRenamed),
   PathParameterRemoved(PathParameterRemoved),
 
@@ -30,6 +32,7 @@ pub enum RequestsEvent {
   RequestContentTypeSet(RequestContentTypeSet),
   RequestBodySet(RequestBodySet),
   RequestBodyUnset(RequestBodyUnset),
+  RequestRemoved(RequestRemoved),
 
   // Response events
   ResponseAddedByPathAndMethod(ResponseAddedByPathAndMethod),
@@ -40,181 +43,237 @@ pub enum RequestsEvent {
   ResponseRemoved(ResponseRemoved),
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PathComponentAdded {
   pub path_id: PathComponentId,
   pub parent_path_id: PathComponentId,
   pub name: String,
-  event_context: Option<EventContext>,
+  pub event_context: Option<EventContext>,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PathComponentRenamed {
-  path_id: PathComponentId,
-  name: String,
-  event_context: Option<EventContext>,
+  pub path_id: PathComponentId,
+  pub name: String,
+  pub event_context: Option<EventContext>,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PathComponentRemoved {
-  path_id: PathComponentId,
-  event_context: Option<EventContext>,
+  pub path_id: PathComponentId,
+  // a removal that reverts the addition of the entity, like undoing it, forgets it altogether
+  // instead of leaving it behind as removed
+  #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+  pub reverts_addition: bool,
+  pub event_context: Option<EventContext>,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PathParameterAdded {
-  path_id: PathComponentId,
-  parent_path_id: PathComponentId,
-  name: String,
-  event_context: Option<EventContext>,
+  pub path_id: PathComponentId,
+  pub parent_path_id: PathComponentId,
+  pub name: String,
+  pub event_context: Option<EventContext>,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PathParameterShapeSet {
+  pub path_id: PathComponentId,
+  pub shape_descriptor: ShapedRequestParameterShapeDescriptor,
+  pub event_context: Option<EventContext>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PathParameterRenamed {
-  path_id: PathComponentId,
-  name: String,
-  event_context: Option<EventContext>,
+  pub path_id: PathComponentId,
+  pub name: String,
+  pub event_context: Option<EventContext>,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PathParameterRemoved {
-  path_id: PathComponentId,
-  name: String,
-  event_context: Option<EventContext>,
+  pub path_id: PathComponentId,
+  pub name: String,
+  #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+  pub reverts_addition: bool,
+  pub event_context: Option<EventContext>,
 }
 
-#[derive(Deserialize)] // request parameters
+#[derive(Clone, Serialize, Deserialize)] // request parameters
 #[serde(rename_all = "camelCase")]
 pub struct RequestParameterAddedByPathAndMethod {
   pub parameter_id: RequestParameterId,
   pub path_id: PathComponentId,
-  pub http_method: String,
-  pub parameter_location: String,
+  pub http_method: HttpMethod,
+  pub parameter_location: ParameterLocation,
   pub name: String,
-  event_context: Option<EventContext>,
+  pub event_context: Option<EventContext>,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RequestParameterRenamed {
-  parameter_id: RequestParameterId,
-  name: String,
-  event_context: Option<EventContext>,
+  pub parameter_id: RequestParameterId,
+  pub name: String,
+  pub event_context: Option<EventContext>,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RequestParameterShapeSet {
   pub parameter_id: RequestParameterId,
   pub parameter_descriptor: ShapedRequestParameterShapeDescriptor,
-  event_context: Option<EventContext>,
+  pub event_context: Option<EventContext>,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RequestParameterShapeUnset {
-  parameter_id: RequestParameterId,
-  event_context: Option<EventContext>,
+  pub parameter_id: RequestParameterId,
+  pub event_context: Option<EventContext>,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RequestParameterRemoved {
-  parameter_id: RequestParameterId,
-  event_context: Option<EventContext>,
+  pub parameter_id: RequestParameterId,
+  #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+  pub reverts_addition: bool,
+  pub event_context: Option<EventContext>,
 }
 
-#[derive(Deserialize)] // Request events
+#[derive(Clone, Serialize, Deserialize)] // Request events
 #[serde(rename_all = "camelCase")]
 pub struct RequestAdded {
   pub request_id: RequestId,
   pub path_id: PathComponentId,
-  pub http_method: String,
-  event_context: Option<EventContext>,
+  pub http_method: HttpMethod,
+  pub event_context: Option<EventContext>,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RequestContentTypeSet {
-  request_id: RequestId,
-  http_content_type: String,
-  event_context: Option<EventContext>,
+  pub request_id: RequestId,
+  pub http_content_type: String,
+  pub event_context: Option<EventContext>,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RequestBodySet {
-  request_id: RequestId,
-  // bodyDescriptor: ShapedBodyDescriptor,
-  event_context: Option<EventContext>,
+  pub request_id: RequestId,
+  pub body_descriptor: ShapedBodyDescriptor,
+  pub event_context: Option<EventContext>,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RequestBodyUnset {
-  request_id: RequestId,
-  event_context: Option<EventContext>,
+  pub request_id: RequestId,
+  // the body of one content type, or all bodies when missing
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub http_content_type: Option<String>,
+  pub event_context: Option<EventContext>,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RequestRemoved {
-  request_id: RequestId,
-  event_context: Option<EventContext>,
+  pub request_id: RequestId,
+  #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+  pub reverts_addition: bool,
+  pub event_context: Option<EventContext>,
 }
 
-#[derive(Deserialize)] // Response events
+#[derive(Clone, Serialize, Deserialize)] // Response events
 #[serde(rename_all = "camelCase")]
 pub struct ResponseAddedByPathAndMethod {
   pub response_id: ResponseId,
   pub path_id: PathComponentId,
-  pub http_method: String,
-  pub http_status_code: u16,
-  event_context: Option<EventContext>,
+  pub http_method: HttpMethod,
+  pub http_status_code: StatusCode,
+  pub event_context: Option<EventContext>,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ResponseStatusCodeSet {
-  response_id: ResponseId,
-  http_status_code: u16,
-  event_context: Option<EventContext>,
+  pub response_id: ResponseId,
+  pub http_status_code: StatusCode,
+  pub event_context: Option<EventContext>,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ResponseContentTypeSet {
-  response_id: ResponseId,
-  http_content_type: String,
-  event_context: Option<EventContext>,
+  pub response_id: ResponseId,
+  pub http_content_type: String,
+  pub event_context: Option<EventContext>,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ResponseBodySet {
   pub response_id: ResponseId,
   pub body_descriptor: ShapedBodyDescriptor,
-  event_context: Option<EventContext>,
+  pub event_context: Option<EventContext>,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ResponseBodyUnset {
-  response_id: ResponseId,
-  event_context: Option<EventContext>,
+  pub response_id: ResponseId,
+  // the body of one content type, or all bodies when missing
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub http_content_type: Option<String>,
+  pub event_context: Option<EventContext>,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ResponseRemoved {
-  response_id: ResponseId,
-  event_context: Option<EventContext>,
+  pub response_id: ResponseId,
+  #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+  pub reverts_addition: bool,
+  pub event_context: Option<EventContext>,
+}
+
+impl RequestsEvent {
+  pub fn event_context(&self) -> Option<&EventContext> {
+    match self {
+      RequestsEvent::PathComponentAdded(evt) => evt.event_context.as_ref(),
+      RequestsEvent::PathComponentRenamed(evt) => evt.event_context.as_ref(),
+      RequestsEvent::PathComponentRemoved(evt) => evt.event_context.as_ref(),
+      RequestsEvent::PathParameterAdded(evt) => evt.event_context.as_ref(),
+      RequestsEvent::PathParameterShapeSet(evt) => evt.event_context.as_ref(),
+      RequestsEvent::PathParameterRenamed(evt) => evt.event_context.as_ref(),
+      RequestsEvent::PathParameterRemoved(evt) => evt.event_context.as_ref(),
+      RequestsEvent::RequestParameterAddedByPathAndMethod(evt) => evt.event_context.as_ref(),
+      RequestsEvent::RequestParameterRenamed(evt) => evt.event_context.as_ref(),
+      RequestsEvent::RequestParameterShapeSet(evt) => evt.event_context.as_ref(),
+      RequestsEvent::RequestParameterShapeUnset(evt) => evt.event_context.as_ref(),
+      RequestsEvent::RequestParameterRemoved(evt) => evt.event_context.as_ref(),
+      RequestsEvent::RequestAdded(evt) => evt.event_context.as_ref(),
+      RequestsEvent::RequestContentTypeSet(evt) => evt.event_context.as_ref(),
+      RequestsEvent::RequestBodySet(evt) => evt.event_context.as_ref(),
+      RequestsEvent::RequestBodyUnset(evt) => evt.event_context.as_ref(),
+      RequestsEvent::RequestRemoved(evt) => evt.event_context.as_ref(),
+      RequestsEvent::ResponseAddedByPathAndMethod(evt) => evt.event_context.as_ref(),
+      RequestsEvent::ResponseStatusCodeSet(evt) => evt.event_context.as_ref(),
+      RequestsEvent::ResponseContentTypeSet(evt) => evt.event_context.as_ref(),
+      RequestsEvent::ResponseBodySet(evt) => evt.event_context.as_ref(),
+      RequestsEvent::ResponseBodyUnset(evt) => evt.event_context.as_ref(),
+      RequestsEvent::ResponseRemoved(evt) => evt.event_context.as_ref(),
+    }
+  }
 }
 
 impl Event for RequestsEvent {
@@ -226,6 +285,7 @@ impl Event for RequestsEvent {
 
       // path parameters
       RequestsEvent::PathParameterAdded(ref evt) => evt.event_type(),
+      RequestsEvent::PathParameterShapeSet(ref evt) => evt.event_type(),
       RequestsEvent::PathParameterRenamed(ref evt) => evt.event_type(),
       RequestsEvent::PathParameterRemoved(ref evt) => evt.event_type(),
 
@@ -241,6 +301,7 @@ impl Event for RequestsEvent {
       RequestsEvent::RequestContentTypeSet(ref evt) => evt.event_type(),
       RequestsEvent::RequestBodySet(ref evt) => evt.event_type(),
       RequestsEvent::RequestBodyUnset(ref evt) => evt.event_type(),
+      RequestsEvent::RequestRemoved(ref evt) => evt.event_type(),
 
       // Response events
       RequestsEvent::ResponseAddedByPathAndMethod(ref evt) => evt.event_type(),
@@ -277,6 +338,12 @@ impl Event for PathParameterAdded {
   }
 }
 
+impl Event for PathParameterShapeSet {
+  fn event_type(&self) -> &'static str {
+    "PathParameterShapeSet"
+  }
+}
+
 impl Event for PathParameterRenamed {
   fn event_type(&self) -> &'static str {
     "PathParameterRenamed"
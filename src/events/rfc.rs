@@ -3,7 +3,7 @@ use cqrs_core::Event;
 
 // RFC Events
 // -----------
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub enum RfcEvent {
   ContributionAdded(ContributionAdded),
   APINamed(APINamed),
@@ -12,43 +12,55 @@ pub enum RfcEvent {
   BatchCommitEnded(BatchCommitEnded),
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ContributionAdded {
-  id: String,
-  key: String,
-  value: String,
-  event_context: Option<EventContext>,
+  pub id: String,
+  pub key: String,
+  pub value: String,
+  pub event_context: Option<EventContext>,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct APINamed {
-  name: String,
-  event_context: Option<EventContext>,
+  pub name: String,
+  pub event_context: Option<EventContext>,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GitStateSet {
-  branch_name: String,
-  commit_id: String,
-  event_context: Option<EventContext>,
+  pub branch_name: String,
+  pub commit_id: String,
+  pub event_context: Option<EventContext>,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct BatchCommitStarted {
-  batch_id: String,
-  commit_message: String,
-  event_context: Option<EventContext>,
+  pub batch_id: String,
+  pub commit_message: String,
+  pub event_context: Option<EventContext>,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct BatchCommitEnded {
-  batch_id: String,
-  event_context: Option<EventContext>,
+  pub batch_id: String,
+  pub event_context: Option<EventContext>,
+}
+
+impl RfcEvent {
+  pub fn event_context(&self) -> Option<&EventContext> {
+    match self {
+      RfcEvent::ContributionAdded(evt) => evt.event_context.as_ref(),
+      RfcEvent::APINamed(evt) => evt.event_context.as_ref(),
+      RfcEvent::GitStateSet(evt) => evt.event_context.as_ref(),
+      RfcEvent::BatchCommitStarted(evt) => evt.event_context.as_ref(),
+      RfcEvent::BatchCommitEnded(evt) => evt.event_context.as_ref(),
+    }
+  }
 }
 
 impl Event for RfcEvent {
@@ -1,12 +1,11 @@
 use super::EventContext;
-use crate::state::shape::{FieldShapeDescriptor, ShapeParametersDescriptor};
+use crate::state::shape::{
+  FieldId, FieldShapeDescriptor, ParameterShapeDescriptor, ShapeId, ShapeParameterId,
+  ShapeParametersDescriptor,
+};
 use cqrs_core::Event;
 
-type ShapeId = String;
-type ShapeParameterId = String;
-type FieldId = String;
-
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub enum ShapeEvent {
   ShapeAdded(ShapeAdded),
   BaseShapeSet(BaseShapeSet),
@@ -23,101 +22,125 @@ pub enum ShapeEvent {
   FieldRemoved(FieldRemoved),
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ShapeAdded {
   pub shape_id: ShapeId,
   pub base_shape_id: ShapeId,
   pub parameters: ShapeParametersDescriptor,
   pub name: String,
-  event_context: Option<EventContext>,
+  pub event_context: Option<EventContext>,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct BaseShapeSet {
-  shape_id: ShapeId,
-  base_shape_id: ShapeId,
-  event_context: Option<EventContext>,
+  pub shape_id: ShapeId,
+  pub base_shape_id: ShapeId,
+  pub event_context: Option<EventContext>,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ShapeRenamed {
-  shape_id: ShapeId,
-  name: String,
-  event_context: Option<EventContext>,
+  pub shape_id: ShapeId,
+  pub name: String,
+  pub event_context: Option<EventContext>,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ShapeRemoved {
-  shape_id: ShapeId,
-  event_context: Option<EventContext>,
+  pub shape_id: ShapeId,
+  // forgets the shape altogether when reverting its addition, like `PathComponentRemoved`
+  #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+  pub reverts_addition: bool,
+  pub event_context: Option<EventContext>,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ShapeParameterAdded {
-  shape_parameter_id: ShapeParameterId,
-  shape_id: ShapeId,
-  name: String,
+  pub shape_parameter_id: ShapeParameterId,
+  pub shape_id: ShapeId,
+  pub name: String,
   // shapeDescriptor: ParameterShapeDescriptor,
-  event_context: Option<EventContext>,
+  pub event_context: Option<EventContext>,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ShapeParameterShapeSet {
-  // shapeDescriptor: ParameterShapeDescriptor,
-  event_context: Option<EventContext>,
+  pub shape_descriptor: ParameterShapeDescriptor,
+  pub event_context: Option<EventContext>,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ShapeParameterRenamed {
-  shape_parameter_id: ShapeParameterId,
-  name: String,
-  event_context: Option<EventContext>,
+  pub shape_parameter_id: ShapeParameterId,
+  pub name: String,
+  pub event_context: Option<EventContext>,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ShapeParameterRemoved {
-  shape_parameter_id: ShapeParameterId,
-  event_context: Option<EventContext>,
+  pub shape_parameter_id: ShapeParameterId,
+  pub event_context: Option<EventContext>,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct FieldAdded {
   pub field_id: FieldId,
   pub shape_id: ShapeId,
   pub name: String,
   pub shape_descriptor: FieldShapeDescriptor,
-  event_context: Option<EventContext>,
+  pub event_context: Option<EventContext>,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct FieldShapeSet {
-  shape_descriptor: FieldShapeDescriptor,
-  event_context: Option<EventContext>,
+  pub shape_descriptor: FieldShapeDescriptor,
+  pub event_context: Option<EventContext>,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct FieldRenamed {
-  field_id: FieldId,
-  name: String,
-  event_context: Option<EventContext>,
+  pub field_id: FieldId,
+  pub name: String,
+  pub event_context: Option<EventContext>,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct FieldRemoved {
-  field_id: FieldId,
-  event_context: Option<EventContext>,
+  pub field_id: FieldId,
+  #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+  pub reverts_addition: bool,
+  pub event_context: Option<EventContext>,
+}
+
+impl ShapeEvent {
+  pub fn event_context(&self) -> Option<&EventContext> {
+    match self {
+      ShapeEvent::ShapeAdded(evt) => evt.event_context.as_ref(),
+      ShapeEvent::BaseShapeSet(evt) => evt.event_context.as_ref(),
+      ShapeEvent::ShapeRenamed(evt) => evt.event_context.as_ref(),
+      ShapeEvent::ShapeRemoved(evt) => evt.event_context.as_ref(),
+      ShapeEvent::ShapeParameterAdded(evt) => evt.event_context.as_ref(),
+      ShapeEvent::ShapeParameterShapeSet(evt) => evt.event_context.as_ref(),
+      ShapeEvent::ShapeParameterRenamed(evt) => evt.event_context.as_ref(),
+      ShapeEvent::ShapeParameterRemoved(evt) => evt.event_context.as_ref(),
+      ShapeEvent::FieldAdded(evt) => evt.event_context.as_ref(),
+      ShapeEvent::FieldShapeSet(evt) => evt.event_context.as_ref(),
+      ShapeEvent::FieldRenamed(evt) => evt.event_context.as_ref(),
+      ShapeEvent::FieldRemoved(evt) => evt.event_context.as_ref(),
+    }
+  }
 }
 
 impl Event for ShapeEvent {
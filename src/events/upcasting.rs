@@ -0,0 +1,219 @@
+use serde_json::{Map, Value};
+
+// Spec files written by older versions of Optic have slightly different events. The schema of the
+// events is versioned, and every migration below upcasts the events of one event type from one
+// version to the next.
+//
+// Spec files are plain arrays of events that don't record the version they were written in, so a
+// stream's version is detected instead: it's the version before the oldest migration that any of
+// its events still needs. The stream is then upcast through every migration from that version on,
+// in order. Events appended to an old spec by a newer version of Optic are already in a newer
+// layout, so a migration only rewrites the events it recognizes as being in its legacy layout.
+pub const SCHEMA_VERSION: u32 = 3;
+
+pub struct Migration {
+  pub from_version: u32,
+  pub to_version: u32,
+  pub event_type: &'static str,
+  // whether the fields of an event of the event type are in the layout of `from_version`
+  pub is_legacy: fn(&Map<String, Value>) -> bool,
+  pub upcast: fn(&mut Map<String, Value>),
+}
+
+// in the order of their versions
+pub const MIGRATIONS: &[Migration] = &[
+  Migration {
+    from_version: 1,
+    to_version: 2,
+    event_type: "RequestBodySet",
+    is_legacy: has_flat_body_descriptor,
+    upcast: nest_body_descriptor,
+  },
+  Migration {
+    from_version: 1,
+    to_version: 2,
+    event_type: "ResponseBodySet",
+    is_legacy: has_flat_body_descriptor,
+    upcast: nest_body_descriptor,
+  },
+  Migration {
+    from_version: 2,
+    to_version: 3,
+    event_type: "ShapeParameterShapeSet",
+    is_legacy: has_serialized_shape_descriptor,
+    upcast: parse_shape_descriptor,
+  },
+];
+
+#[derive(Debug, PartialEq)]
+pub struct Upcast {
+  // the version the stream was detected to be written in
+  pub from_version: u32,
+  // how many events each migration rewrote, in the order of `MIGRATIONS`
+  pub migrated: Vec<usize>,
+}
+
+#[derive(Debug)]
+pub struct UpcastError {
+  pub index: usize,
+  pub message: String,
+}
+
+// Upcasts a raw stream of events to `SCHEMA_VERSION`, in place
+pub fn upcast_events(events: &mut [Value]) -> Result<Upcast, UpcastError> {
+  let mut typed_events = vec![];
+  for (index, event) in events.iter_mut().enumerate() {
+    match event.as_object_mut().and_then(|e| e.iter_mut().next()) {
+      Some((event_type, Value::Object(fields))) => typed_events.push((event_type.clone(), fields)),
+      _ => {
+        return Err(UpcastError {
+          index,
+          message: String::from("an event must be an object of its type and fields"),
+        })
+      }
+    }
+  }
+
+  let from_version = MIGRATIONS
+    .iter()
+    .filter(|migration| {
+      typed_events.iter().any(|(event_type, fields)| {
+        migration.event_type == event_type && (migration.is_legacy)(fields)
+      })
+    })
+    .map(|migration| migration.from_version)
+    .min()
+    .unwrap_or(SCHEMA_VERSION);
+
+  let mut migrated = vec![0; MIGRATIONS.len()];
+  for (migration, count) in MIGRATIONS.iter().zip(migrated.iter_mut()) {
+    if migration.from_version < from_version {
+      continue;
+    }
+    for (event_type, fields) in typed_events.iter_mut() {
+      if migration.event_type == event_type && (migration.is_legacy)(fields) {
+        (migration.upcast)(fields);
+        *count += 1;
+      }
+    }
+  }
+
+  Ok(Upcast {
+    from_version,
+    migrated,
+  })
+}
+
+// Migrations
+// ----------
+
+// { requestId, httpContentType, shapeId } -> { requestId, bodyDescriptor: { httpContentType, .. } }
+fn has_flat_body_descriptor(fields: &Map<String, Value>) -> bool {
+  // anything else is left for deserialization to reject as it is
+  !fields.contains_key("bodyDescriptor")
+    && fields.contains_key("httpContentType")
+    && fields.contains_key("shapeId")
+}
+
+fn nest_body_descriptor(fields: &mut Map<String, Value>) {
+  let mut body_descriptor = Map::new();
+  for key in &["httpContentType", "shapeId"] {
+    let value = fields.remove(*key).unwrap_or_default();
+    body_descriptor.insert(String::from(*key), value);
+  }
+  body_descriptor.insert(String::from("isRemoved"), Value::Bool(false));
+  fields.insert(
+    String::from("bodyDescriptor"),
+    Value::Object(body_descriptor),
+  );
+}
+
+// { shapeDescriptor: "{\"ProviderInShape\":..}" } -> { shapeDescriptor: { ProviderInShape: .. } }
+fn has_serialized_shape_descriptor(fields: &Map<String, Value>) -> bool {
+  match fields.get("shapeDescriptor") {
+    Some(Value::String(descriptor)) => serde_json::from_str::<Value>(descriptor).is_ok(),
+    _ => false,
+  }
+}
+
+fn parse_shape_descriptor(fields: &mut Map<String, Value>) {
+  if let Some(Value::String(descriptor)) = fields.get("shapeDescriptor") {
+    let parsed = serde_json::from_str(descriptor).unwrap_or_default();
+    fields.insert(String::from("shapeDescriptor"), parsed);
+  }
+}
+
+#[test]
+fn legacy_specs_are_upcast_to_the_current_schema() {
+  use crate::events::OpticEvent;
+  use serde_json::json;
+
+  let contents = std::fs::read_to_string("test-fixtures/legacy-spec.json").unwrap();
+  let mut legacy: Vec<Value> = serde_json::from_str(&contents).unwrap();
+  let context = json!({
+    "clientId": "anonymous",
+    "clientSessionId": "session",
+    "clientCommandBatchId": "batch",
+    "createdAt": "2019-11-20T10:15:00.000Z"
+  });
+  let current = json!([
+    {"PathComponentAdded": {"pathId": "path_users", "parentPathId": "root", "name": "users", "eventContext": context}},
+    {"RequestAdded": {"requestId": "request_1", "pathId": "path_users", "httpMethod": "POST", "eventContext": context}},
+    {"ShapeAdded": {"shapeId": "shape_user", "baseShapeId": "$object", "parameters": {"DynamicParameterList": {"shapeParameterIds": []}}, "name": "User", "eventContext": context}},
+    {"RequestBodySet": {"requestId": "request_1", "bodyDescriptor": {"httpContentType": "application/json", "shapeId": "shape_user", "isRemoved": false}, "eventContext": context}},
+    {"ResponseAddedByPathAndMethod": {"responseId": "response_1", "pathId": "path_users", "httpMethod": "POST", "httpStatusCode": 201, "eventContext": context}},
+    {"ResponseBodySet": {"responseId": "response_1", "bodyDescriptor": {"httpContentType": "application/json", "shapeId": "shape_user", "isRemoved": false}, "eventContext": context}},
+    {"ShapeAdded": {"shapeId": "shape_users", "baseShapeId": "$list", "parameters": {"DynamicParameterList": {"shapeParameterIds": []}}, "name": "", "eventContext": context}},
+    {"ShapeParameterShapeSet": {"shapeDescriptor": {"ProviderInShape": {"shapeId": "shape_users", "providerDescriptor": {"ShapeProvider": {"shapeId": "shape_user"}}, "consumingParameterId": "$listItem"}}, "eventContext": context}}
+  ]);
+
+  let upcast = upcast_events(&mut legacy).unwrap();
+  assert_eq!(
+    upcast,
+    Upcast {
+      from_version: 1,
+      migrated: vec![1, 1, 1]
+    }
+  );
+  assert_eq!(Value::Array(legacy.clone()), current);
+
+  // upcasting current events changes nothing
+  let mut upcast_again = legacy.clone();
+  assert_eq!(
+    upcast_events(&mut upcast_again).unwrap(),
+    Upcast {
+      from_version: SCHEMA_VERSION,
+      migrated: vec![0, 0, 0]
+    }
+  );
+  assert_eq!(Value::Array(upcast_again), current);
+
+  // a stream is as old as the oldest layout in it, and only upcast from there
+  let mut version_2 = vec![current[7].clone()];
+  version_2[0]["ShapeParameterShapeSet"]["shapeDescriptor"] =
+    Value::String(current[7]["ShapeParameterShapeSet"]["shapeDescriptor"].to_string());
+  assert_eq!(upcast_events(&mut version_2).unwrap().from_version, 2);
+  assert_eq!(version_2[0], current[7]);
+
+  let events: Vec<OpticEvent> = serde_json::from_value(Value::Array(legacy)).unwrap();
+  assert_eq!(events.len(), 8);
+
+  // events that are neither legacy nor current are left as they are
+  let mut partial = vec![
+    json!({"RequestBodySet": {"requestId": "request_1", "httpContentType": "application/json", "eventContext": null}}),
+  ];
+  let original = partial.clone();
+  assert_eq!(upcast_events(&mut partial).unwrap().migrated, vec![0, 0, 0]);
+  assert_eq!(partial, original);
+}
+
+#[test]
+fn migrations_upcast_one_version_at_a_time_up_to_the_schema_version() {
+  let mut version = 1;
+  for migration in MIGRATIONS {
+    assert_eq!(migration.to_version, migration.from_version + 1);
+    assert!(migration.from_version == version || migration.from_version == version + 1);
+    version = migration.from_version;
+  }
+  assert_eq!(MIGRATIONS.last().unwrap().to_version, SCHEMA_VERSION);
+}
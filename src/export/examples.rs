@@ -0,0 +1,21 @@
+use serde_json::{json, Map, Value};
+
+use crate::aggregate::OpticState;
+use crate::projections::endpoints::endpoints;
+use crate::projections::examples::response_examples;
+
+// An example body for every response of every endpoint, by endpoint, status code and content type
+pub fn examples(state: &OpticState, seed: u64) -> Value {
+  let mut document = Map::new();
+  for endpoint in endpoints(state) {
+    let mut responses = json!({});
+    for example in response_examples(state.shape, &endpoint, seed) {
+      responses[example.http_status_code.to_string()][example.http_content_type] = example.example;
+    }
+    document.insert(
+      format!("{} {}", endpoint.http_method, endpoint.path),
+      responses,
+    );
+  }
+  Value::Object(document)
+}
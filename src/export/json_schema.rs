@@ -0,0 +1,151 @@
+use serde_json::{json, Map, Value};
+
+use crate::state::shape::{is_core_shape, ShapeId, ShapeParametersDescriptor, ShapeState};
+
+// Translates shapes into JSON Schema. User-defined objects become named definitions referenced by
+// `$ref` (which also keeps recursive shapes finite), everything else is inlined.
+pub struct SchemaGenerator<'a> {
+  shapes: &'a ShapeState,
+  ref_prefix: &'static str,
+  definitions: Map<String, Value>,
+  resolving: Vec<ShapeId>,
+}
+
+impl<'a> SchemaGenerator<'a> {
+  pub fn new(shapes: &'a ShapeState, ref_prefix: &'static str) -> Self {
+    SchemaGenerator {
+      shapes,
+      ref_prefix,
+      definitions: Map::new(),
+      resolving: vec![],
+    }
+  }
+
+  pub fn into_definitions(self) -> Map<String, Value> {
+    self.definitions
+  }
+
+  pub fn schema_for(&mut self, shape_id: ShapeId) -> Value {
+    if self.resolving.contains(&shape_id) {
+      return json!({});
+    }
+
+    self.resolving.push(shape_id);
+    let schema = self.resolve(shape_id);
+    self.resolving.pop();
+    schema
+  }
+
+  fn resolve(&mut self, shape_id: ShapeId) -> Value {
+    let core_shape_id = self.shapes.core_shape_id(shape_id);
+    match core_shape_id.as_str() {
+      "$object" if !is_core_shape(shape_id) => {
+        self.define_object(shape_id);
+        json!({ "$ref": format!("{}{}", self.ref_prefix, shape_id) })
+      }
+      "$object" => json!({ "type": "object" }),
+      "$string" => json!({ "type": "string" }),
+      "$number" => json!({ "type": "number" }),
+      "$boolean" => json!({ "type": "boolean" }),
+      "$list" => json!({
+        "type": "array",
+        "items": self.bound_schema(shape_id, "$listItem"),
+      }),
+      "$map" => json!({
+        "type": "object",
+        "additionalProperties": self.bound_schema(shape_id, "$mapValue"),
+      }),
+      "$nullable" => json!({
+        "anyOf": [self.bound_schema(shape_id, "$nullableInner"), { "type": "null" }],
+      }),
+      "$optional" => self.bound_schema(shape_id, "$optionalInner"),
+      "$identifier" => self.bound_schema(shape_id, "$identifierInner"),
+      "$reference" => self.bound_schema(shape_id, "$referenceInner"),
+      "$oneOf" => {
+        let options: Vec<Value> = self
+          .parameter_ids(shape_id)
+          .iter()
+          .map(|parameter_id| self.bound_schema(shape_id, parameter_id))
+          .collect();
+        json!({ "oneOf": options })
+      }
+      _ => json!({}),
+    }
+  }
+
+  fn define_object(&mut self, shape_id: ShapeId) {
+    let name = String::from(shape_id.as_str());
+    if self.definitions.contains_key(&name) {
+      return;
+    }
+    // reserve the definition before resolving fields, so fields referring back to it terminate
+    self.definitions.insert(name.clone(), Value::Null);
+
+    let mut properties = Map::new();
+    let mut required = vec![];
+    for field in self.shapes.resolved_fields_of(shape_id) {
+      let field_shape_id = field.descriptor.shape_descriptor.shape_id();
+      let schema = match field_shape_id {
+        Some(field_shape_id) => self.schema_for(field_shape_id),
+        None => json!({}),
+      };
+      let is_optional = field_shape_id
+        .map(|field_shape_id| self.shapes.core_shape_id(field_shape_id).as_str() == "$optional")
+        .unwrap_or(false);
+
+      if !is_optional {
+        required.push(Value::from(field.descriptor.name.clone()));
+      }
+      properties.insert(field.descriptor.name.clone(), schema);
+    }
+
+    let mut definition = json!({
+      "type": "object",
+      "properties": properties,
+      "required": required,
+    });
+    if let Some(shape) = self.shapes.shape(shape_id) {
+      if !shape.descriptor.name.is_empty() {
+        definition["title"] = Value::from(shape.descriptor.name.clone());
+      }
+    }
+    self.definitions.insert(name, definition);
+  }
+
+  fn bound_schema(&mut self, shape_id: ShapeId, parameter_id: &str) -> Value {
+    match self.shapes.bound_shape_id(shape_id, parameter_id) {
+      Some(bound_shape_id) => self.schema_for(bound_shape_id),
+      None => json!({}),
+    }
+  }
+
+  fn parameter_ids(&self, shape_id: ShapeId) -> Vec<String> {
+    let parameters = self
+      .shapes
+      .shape(shape_id)
+      .map(|shape| &shape.descriptor.parameters);
+    match parameters {
+      Some(ShapeParametersDescriptor::StaticParameterList(list)) => &list.shape_parameter_ids,
+      Some(ShapeParametersDescriptor::DynamicParameterList(list)) => &list.shape_parameter_ids,
+      _ => return vec![],
+    }
+    .iter()
+    .map(|parameter_id| String::from(parameter_id.as_str()))
+    .collect()
+  }
+}
+
+// A JSON Schema document with a definition for every user-defined object shape
+pub fn json_schema(shapes: &ShapeState) -> Value {
+  let mut generator = SchemaGenerator::new(shapes, "#/definitions/");
+  for shape in shapes.all_shapes().filter(|shape| !shape.is_removed) {
+    if shapes.core_shape_id(shape.shape_id).as_str() == "$object" {
+      generator.schema_for(shape.shape_id);
+    }
+  }
+
+  json!({
+    "$schema": "http://json-schema.org/draft-07/schema#",
+    "definitions": generator.into_definitions(),
+  })
+}
@@ -0,0 +1,6 @@
+pub mod examples;
+pub mod json_schema;
+pub mod names;
+pub mod openapi;
+pub mod rust;
+pub mod typescript;
@@ -0,0 +1,123 @@
+use std::collections::HashSet;
+
+use crate::state::http::HttpMethod;
+
+// Type names for generated code: PascalCase words of whatever the name is derived from, like a
+// field name or the path of an endpoint
+pub fn type_name(words: &str) -> String {
+  let name: String = words
+    .split(|c: char| !c.is_ascii_alphanumeric())
+    .flat_map(|word| {
+      let mut chars = word.chars();
+      chars
+        .next()
+        .map(|first| first.to_ascii_uppercase())
+        .into_iter()
+        .chain(chars)
+    })
+    .collect();
+  match name.chars().next() {
+    Some(first) if first.is_ascii_alphabetic() => name,
+    _ => format!("T{}", name),
+  }
+}
+
+// `GET /users/{userId}/posts` is named `GetUsersUserIdPosts`
+pub fn endpoint_type_name(http_method: &HttpMethod, path: &str) -> String {
+  let path_name = match type_name(path).as_str() {
+    "T" => String::from("Root"),
+    path_name => String::from(path_name),
+  };
+  format!(
+    "{}{}",
+    type_name(&http_method.as_str().to_lowercase()),
+    path_name
+  )
+}
+
+// Field names for generated Rust: snake_case words, as raw identifiers if they're keywords
+pub fn field_name(name: &str) -> String {
+  let chars: Vec<char> = name.chars().collect();
+  let mut snake = String::new();
+  for (index, c) in chars.iter().enumerate() {
+    if !c.is_ascii_alphanumeric() {
+      if !snake.is_empty() && !snake.ends_with('_') {
+        snake.push('_');
+      }
+      continue;
+    }
+    let previous = index.checked_sub(1).map(|index| chars[index]);
+    let next = chars.get(index + 1);
+    let starts_word = c.is_ascii_uppercase()
+      && match previous {
+        Some(previous) if previous.is_ascii_lowercase() || previous.is_ascii_digit() => true,
+        Some(previous) if previous.is_ascii_uppercase() => {
+          next.is_some_and(|next| next.is_ascii_lowercase())
+        }
+        _ => false,
+      };
+    if starts_word && !snake.is_empty() && !snake.ends_with('_') {
+      snake.push('_');
+    }
+    snake.push(c.to_ascii_lowercase());
+  }
+  let snake = snake.trim_end_matches('_');
+
+  match snake {
+    "" => String::from("field"),
+    "self" | "super" | "crate" => format!("{}_", snake),
+    _ if snake.starts_with(|c: char| c.is_ascii_digit()) => format!("field_{}", snake),
+    _ if RUST_KEYWORDS.contains(&snake) => format!("r#{}", snake),
+    _ => String::from(snake),
+  }
+}
+
+const RUST_KEYWORDS: &[&str] = &[
+  "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn",
+  "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in", "let", "loop",
+  "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return", "static",
+  "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use", "virtual",
+  "where", "while", "yield",
+];
+
+// Hands out names that weren't handed out before, numbering the ones that were
+#[derive(Default)]
+pub struct UniqueNames {
+  taken: HashSet<String>,
+}
+
+impl UniqueNames {
+  pub fn unique(&mut self, name: String) -> String {
+    let mut unique = name.clone();
+    let mut number = 2;
+    while self.taken.contains(&unique) {
+      unique = format!("{}{}", name, number);
+      number += 1;
+    }
+    self.taken.insert(unique.clone());
+    unique
+  }
+}
+
+#[test]
+fn names_types_after_what_they_describe() {
+  assert_eq!(type_name("MRData"), "MRData");
+  assert_eq!(type_name("raceName"), "RaceName");
+  assert_eq!(type_name("x-rate_limit"), "XRateLimit");
+  assert_eq!(type_name("2020"), "T2020");
+  assert_eq!(
+    endpoint_type_name(&HttpMethod::Get, "/api/f1/{season}"),
+    "GetApiF1Season"
+  );
+  assert_eq!(endpoint_type_name(&HttpMethod::Post, "/"), "PostRoot");
+
+  assert_eq!(field_name("MRData"), "mr_data");
+  assert_eq!(field_name("dateOfBirth"), "date_of_birth");
+  assert_eq!(field_name("flag-list"), "flag_list");
+  assert_eq!(field_name("type"), "r#type");
+  assert_eq!(field_name("2fa"), "field_2fa");
+
+  let mut names = UniqueNames::default();
+  assert_eq!(names.unique(String::from("Season")), "Season");
+  assert_eq!(names.unique(String::from("Season")), "Season2");
+}
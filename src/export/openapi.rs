@@ -0,0 +1,239 @@
+use serde_json::{json, Map, Value};
+
+use super::json_schema::SchemaGenerator;
+use crate::aggregate::OpticState;
+use crate::projections::endpoints::{endpoints, path_parameters};
+use crate::state::requests::live_bodies;
+
+pub const OPENAPI_VERSION: &str = "3.1.0";
+
+// An OpenAPI document describing every endpoint, with the shapes of bodies and parameters as
+// component schemas
+pub fn openapi(state: &OpticState) -> Value {
+  let mut schemas = SchemaGenerator::new(state.shape, "#/components/schemas/");
+  let mut paths = Map::new();
+
+  for endpoint in endpoints(state) {
+    let mut operation = Map::new();
+    if let Some(purpose) = endpoint.purpose {
+      operation.insert(String::from("summary"), Value::from(purpose));
+    }
+
+    let mut parameters = vec![];
+    for parameter in path_parameters(state, endpoint.path_id) {
+      let schema = parameter
+        .shape_id
+        .map(|shape_id| schemas.schema_for(shape_id))
+        .unwrap_or_else(|| json!({ "type": "string" }));
+      parameters.push(json!({
+        "name": parameter.name,
+        "in": "path",
+        "required": true,
+        "schema": schema,
+      }));
+    }
+    for parameter in &endpoint.parameters {
+      let descriptor = &parameter.request_parameter_descriptor;
+      let schema = descriptor
+        .shape_descriptor
+        .shaped()
+        .map(|shaped| schemas.schema_for(shaped.shape_id))
+        .unwrap_or_else(|| json!({}));
+      parameters.push(json!({
+        "name": descriptor.name,
+        "in": descriptor.location.as_str(),
+        "schema": schema,
+      }));
+    }
+    if !parameters.is_empty() {
+      operation.insert(String::from("parameters"), Value::from(parameters));
+    }
+
+    for request in &endpoint.requests {
+      for body in live_bodies(&request.request_descriptor.bodies) {
+        operation
+          .entry("requestBody")
+          .or_insert_with(|| json!({ "content": {} }))["content"][&body.http_content_type] =
+          json!({ "schema": schemas.schema_for(body.shape_id) });
+      }
+    }
+
+    let mut responses = Map::new();
+    for response in &endpoint.responses {
+      let descriptor = &response.response_descriptor;
+      let documented = responses
+        .entry(descriptor.http_status_code.to_string())
+        .or_insert_with(
+          || json!({ "description": format!("{} response", descriptor.http_status_code) }),
+        );
+      for body in live_bodies(&descriptor.bodies) {
+        documented["content"][&body.http_content_type] =
+          json!({ "schema": schemas.schema_for(body.shape_id) });
+      }
+    }
+    if responses.is_empty() {
+      responses.insert(
+        String::from("default"),
+        json!({ "description": "no documented responses" }),
+      );
+    }
+    operation.insert(String::from("responses"), Value::from(responses));
+
+    let path_item = paths
+      .entry(endpoint.path.clone())
+      .or_insert_with(|| json!({}));
+    path_item[endpoint.http_method.as_str().to_lowercase()] = Value::from(operation);
+  }
+
+  json!({
+    "openapi": OPENAPI_VERSION,
+    "info": {
+      "title": state.rfc.api_name.as_deref().unwrap_or("API"),
+      "version": "1.0.0",
+    },
+    "paths": paths,
+    "components": { "schemas": schemas.into_definitions() },
+  })
+}
+
+#[test]
+fn documents_every_endpoint_with_its_responses() {
+  use crate::aggregate::OpticAggregate;
+
+  let aggregate =
+    OpticAggregate::fold(crate::events_from_file("test-fixtures/uncompacted-spec.json").unwrap());
+  let document = openapi(&aggregate.get_state());
+
+  let operation = &document["paths"]["/api/f1/{season}"]["get"];
+  assert_eq!(operation["summary"], "Get Season");
+  assert_eq!(operation["parameters"][0]["in"], "path");
+  assert_eq!(
+    operation["responses"]["200"]["content"]["application/json; charset=utf-8"]["schema"]["$ref"],
+    "#/components/schemas/EQSZqM_0"
+  );
+  assert!(document["components"]["schemas"]["EQSZqM_0"]["properties"]["MRData"].is_object());
+
+  let request_body = &document["paths"]["/following/drivers"]["post"]["requestBody"];
+  assert_eq!(
+    request_body["content"]["application/json"]["schema"]["$ref"],
+    "#/components/schemas/SGyna3_0"
+  );
+}
+
+#[test]
+fn documents_a_body_per_content_type() {
+  use crate::aggregate::{Aggregate, OpticAggregate};
+  use crate::events::OpticEvent;
+
+  let mut aggregate =
+    OpticAggregate::fold(crate::events_from_file("test-fixtures/uncompacted-spec.json").unwrap());
+  let events: Vec<OpticEvent> = serde_json::from_str(
+    r#"[
+      {"ResponseBodySet":{"responseId":"response_WkmtI23TF7","bodyDescriptor":{"httpContentType":"text/csv","shapeId":"$string","isRemoved":false},"eventContext":null}},
+      {"ResponseContentTypeSet":{"responseId":"response_WkmtI23TF7","httpContentType":"application/xml","eventContext":null}},
+      {"ResponseContentTypeSet":{"responseId":"response_WkmtI23TF7","httpContentType":"text/csv","eventContext":null}}
+    ]"#,
+  )
+  .unwrap();
+  for event in events {
+    aggregate.apply(event);
+  }
+  let document = openapi(&aggregate.get_state());
+
+  let content = &document["paths"]["/api/f1/{season}"]["get"]["responses"]["200"]["content"];
+  let content_types: Vec<&String> = content.as_object().unwrap().keys().collect();
+  // setting a content type changes the content type of the first body, unless there's a body of
+  // the content type already
+  assert_eq!(content_types, vec!["application/xml", "text/csv"]);
+  assert_eq!(
+    content["application/xml"]["schema"]["$ref"],
+    "#/components/schemas/EQSZqM_0"
+  );
+  assert_eq!(content["text/csv"]["schema"]["type"], "string");
+}
+
+#[test]
+fn documents_request_bodies_as_they_are_set_and_unset() {
+  use crate::aggregate::{Aggregate, OpticAggregate};
+  use crate::events::OpticEvent;
+  use crate::projections::reachability::root_shapes;
+  use crate::state::json::state_to_json;
+  use crate::state::requests::RequestId;
+  use crate::state::shape::{ShapeId, UNKNOWN_SHAPE_ID};
+
+  let mut aggregate =
+    OpticAggregate::fold(crate::events_from_file("test-fixtures/uncompacted-spec.json").unwrap());
+  let apply = |aggregate: &mut OpticAggregate, events: &str| {
+    for event in serde_json::from_str::<Vec<OpticEvent>>(events).unwrap() {
+      aggregate.apply(event);
+    }
+  };
+  apply(
+    &mut aggregate,
+    r#"[
+      {"RequestContentTypeSet":{"requestId":"request_NR43nZPaOr","httpContentType":"text/plain","eventContext":null}},
+      {"RequestContentTypeSet":{"requestId":"request_dfwMS3YkPW","httpContentType":"application/json","eventContext":null}},
+      {"ShapeAdded":{"shapeId":"xml_body","baseShapeId":"$string","parameters":{"DynamicParameterList":{"shapeParameterIds":[]}},"name":"","eventContext":null}},
+      {"RequestBodySet":{"requestId":"request_NR43nZPaOr","bodyDescriptor":{"httpContentType":"application/xml","shapeId":"xml_body","isRemoved":false},"eventContext":null}},
+      {"RequestBodyUnset":{"requestId":"request_NR43nZPaOr","httpContentType":"application/json","eventContext":null}}
+    ]"#,
+  );
+  let xml_body = ShapeId::from("xml_body");
+  assert!(root_shapes(&aggregate.get_state()).contains(&xml_body));
+  apply(
+    &mut aggregate,
+    r#"[
+      {"RequestBodySet":{"requestId":"request_NR43nZPaOr","bodyDescriptor":{"httpContentType":"application/xml","shapeId":"xml_body","isRemoved":true},"eventContext":null}}
+    ]"#,
+  );
+
+  // setting the content type changes the content type of the body, where a content type set
+  // before any body has a body of unknown shape
+  let state = aggregate.get_state();
+  let shapes = |request_id: &str| -> Vec<(&str, &str)> {
+    state
+      .requests
+      .request(RequestId::from(request_id))
+      .unwrap()
+      .request_descriptor
+      .bodies
+      .values()
+      .map(|body| (body.http_content_type.as_str(), body.shape_id.as_str()))
+      .collect()
+  };
+  assert_eq!(
+    shapes("request_NR43nZPaOr"),
+    vec![("text/plain", "SGyna3_0"), ("application/xml", "xml_body")]
+  );
+  assert_eq!(
+    shapes("request_dfwMS3YkPW"),
+    vec![("application/json", UNKNOWN_SHAPE_ID)]
+  );
+
+  // removed bodies are left out of the document, the JSON model and the shapes in use
+  let document = openapi(&state);
+  let content = &document["paths"]["/following/drivers"]["post"]["requestBody"]["content"];
+  let content_types: Vec<&String> = content.as_object().unwrap().keys().collect();
+  assert_eq!(content_types, vec!["text/plain"]);
+  assert_eq!(
+    content["text/plain"],
+    json!({ "schema": { "$ref": "#/components/schemas/SGyna3_0" } })
+  );
+  let bodies = |include_removed: bool| {
+    let json = state_to_json(&state, include_removed).unwrap();
+    json["requests"]["requests"]["request_NR43nZPaOr"]["requestDescriptor"]["bodies"]
+      .as_object()
+      .unwrap()
+      .len()
+  };
+  assert_eq!((bodies(false), bodies(true)), (1, 2));
+  assert!(!root_shapes(&state).contains(&xml_body));
+
+  // unsetting without a content type unsets every body
+  apply(
+    &mut aggregate,
+    r#"[{"RequestBodyUnset":{"requestId":"request_NR43nZPaOr","eventContext":null}}]"#,
+  );
+  let document = openapi(&aggregate.get_state());
+  assert!(document["paths"]["/following/drivers"]["post"]["requestBody"].is_null());
+}
@@ -0,0 +1,367 @@
+use indexmap::{IndexMap, IndexSet};
+
+use super::names::{endpoint_type_name, field_name, type_name, UniqueNames};
+use crate::aggregate::OpticState;
+use crate::projections::endpoints::endpoints;
+use crate::state::requests::live_bodies;
+use crate::state::shape::{is_core_shape, ShapeId, ShapeParametersDescriptor, ShapeState};
+
+// Translates shapes into Rust types that (de)serialize like the shapes with serde. User-defined
+// objects become structs and one-ofs untagged enums, named like TypeScript interfaces are, and
+// everything else is inlined as a type.
+pub struct RustGenerator<'a> {
+  shapes: &'a ShapeState,
+  named_types: IndexMap<ShapeId, String>,
+  names: UniqueNames,
+  items: Vec<String>,
+  resolving: Vec<ShapeId>,
+  // the types being defined, which have to be boxed to be fields of themselves
+  defining: IndexSet<String>,
+}
+
+impl<'a> RustGenerator<'a> {
+  pub fn new(shapes: &'a ShapeState) -> Self {
+    RustGenerator {
+      shapes,
+      named_types: IndexMap::new(),
+      names: UniqueNames::default(),
+      items: vec![],
+      resolving: vec![],
+      defining: IndexSet::new(),
+    }
+  }
+
+  pub fn into_items(self) -> Vec<String> {
+    self.items
+  }
+
+  // a type alias for the shapes, unless it's one object or one-of whose type gets the name
+  pub fn declare_type(&mut self, name: &str, shape_ids: &[ShapeId]) {
+    if let [shape_id] = shape_ids {
+      let rust_type = self.type_for(*shape_id, name);
+      if rust_type != name {
+        let name = self.names.unique(String::from(name));
+        self
+          .items
+          .push(format!("pub type {} = {};\n", name, rust_type));
+      }
+      return;
+    }
+
+    // bodies of several content types are the options of an enum
+    let name = self.names.unique(String::from(name));
+    let types: IndexSet<String> = shape_ids
+      .iter()
+      .map(|shape_id| self.type_for(*shape_id, &format!("{}Body", name)))
+      .collect();
+    let item = untagged_enum(&name, types);
+    self.items.push(item);
+  }
+
+  pub fn type_for(&mut self, shape_id: ShapeId, name_hint: &str) -> String {
+    // objects and one-ofs are referred to by name, which keeps recursive shapes finite
+    if let Some(name) = self.named_types.get(&shape_id) {
+      return name.clone();
+    }
+    if self.resolving.contains(&shape_id) {
+      return String::from("serde_json::Value");
+    }
+
+    self.resolving.push(shape_id);
+    let resolved = self.resolve(shape_id, name_hint);
+    self.resolving.pop();
+    resolved
+  }
+
+  fn resolve(&mut self, shape_id: ShapeId, name_hint: &str) -> String {
+    let core_shape_id = self.shapes.core_shape_id(shape_id);
+    match core_shape_id.as_str() {
+      "$object" if !is_core_shape(shape_id) => self.declare_struct(shape_id, name_hint),
+      "$object" => String::from("HashMap<String, serde_json::Value>"),
+      "$string" => String::from("String"),
+      "$number" => String::from("f64"),
+      "$boolean" => String::from("bool"),
+      "$list" => format!(
+        "Vec<{}>",
+        self.bound_type(shape_id, "$listItem", &format!("{}Item", name_hint))
+      ),
+      "$map" => format!(
+        "HashMap<String, {}>",
+        self.bound_type(shape_id, "$mapValue", &format!("{}Value", name_hint))
+      ),
+      "$nullable" => optional(self.bound_type(shape_id, "$nullableInner", name_hint)),
+      "$optional" => optional(self.bound_type(shape_id, "$optionalInner", name_hint)),
+      "$identifier" => self.bound_type(shape_id, "$identifierInner", name_hint),
+      "$reference" => self.bound_type(shape_id, "$referenceInner", name_hint),
+      "$oneOf" => self.declare_enum(shape_id, name_hint),
+      _ => String::from("serde_json::Value"),
+    }
+  }
+
+  fn declare_struct(&mut self, shape_id: ShapeId, name_hint: &str) -> String {
+    let name = self.reserve_name(shape_id, name_hint);
+    let index = self.items.len();
+    self.items.push(String::new());
+    self.defining.insert(name.clone());
+
+    let mut item = format!(
+      "#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]\npub struct {} {{\n",
+      name
+    );
+    let mut field_names = UniqueNames::default();
+    for field in self.shapes.resolved_fields_of(shape_id) {
+      let json_name = &field.descriptor.name;
+      let field_shape_id = field.descriptor.shape_descriptor.shape_id();
+      let is_optional = field_shape_id.is_some_and(|field_shape_id| {
+        self.shapes.core_shape_id(field_shape_id).as_str() == "$optional"
+      });
+      let field_type = match field_shape_id {
+        Some(field_shape_id) => self.type_for(field_shape_id, json_name),
+        None => String::from("serde_json::Value"),
+      };
+
+      let rust_name = field_names.unique(field_name(json_name));
+      let mut attributes = vec![];
+      if rust_name.trim_start_matches("r#") != json_name {
+        attributes.push(format!("rename = {:?}", json_name));
+      }
+      // missing optional fields deserialize to `None`, and `None` serializes to a missing field
+      if is_optional {
+        attributes.push(String::from(
+          "default, skip_serializing_if = \"Option::is_none\"",
+        ));
+      }
+      if !attributes.is_empty() {
+        item.push_str(&format!("    #[serde({})]\n", attributes.join(", ")));
+      }
+      item.push_str(&format!(
+        "    pub {}: {},\n",
+        rust_name,
+        self.boxed_if_defining(field_type)
+      ));
+    }
+    item.push_str("}\n");
+
+    self.defining.shift_remove(&name);
+    self.items[index] = item;
+    name
+  }
+
+  fn declare_enum(&mut self, shape_id: ShapeId, name_hint: &str) -> String {
+    let name = self.reserve_name(shape_id, name_hint);
+    let index = self.items.len();
+    self.items.push(String::new());
+    self.defining.insert(name.clone());
+
+    let types: IndexSet<String> = self
+      .parameter_ids(shape_id)
+      .iter()
+      .map(|parameter_id| {
+        let option_type = self.bound_type(shape_id, parameter_id, &format!("{}Option", name));
+        self.boxed_if_defining(option_type)
+      })
+      .collect();
+
+    self.defining.shift_remove(&name);
+    self.items[index] = untagged_enum(&name, types);
+    name
+  }
+
+  fn reserve_name(&mut self, shape_id: ShapeId, name_hint: &str) -> String {
+    let name = match self.shapes.shape(shape_id) {
+      Some(shape) if !shape.descriptor.name.is_empty() => type_name(&shape.descriptor.name),
+      _ => type_name(name_hint),
+    };
+    let name = self.names.unique(name);
+    // reserved before resolving what's in it, so it's declared before the types of its fields
+    self.named_types.insert(shape_id, name.clone());
+    name
+  }
+
+  fn boxed_if_defining(&self, rust_type: String) -> String {
+    match rust_type.strip_prefix("Option<") {
+      Some(inner) if self.defining.contains(inner.trim_end_matches('>')) => {
+        format!("Option<Box<{}>>", inner.trim_end_matches('>'))
+      }
+      _ if self.defining.contains(&rust_type) => format!("Box<{}>", rust_type),
+      _ => rust_type,
+    }
+  }
+
+  fn bound_type(&mut self, shape_id: ShapeId, parameter_id: &str, name_hint: &str) -> String {
+    match self.shapes.bound_shape_id(shape_id, parameter_id) {
+      Some(bound_shape_id) => self.type_for(bound_shape_id, name_hint),
+      None => String::from("serde_json::Value"),
+    }
+  }
+
+  fn parameter_ids(&self, shape_id: ShapeId) -> Vec<String> {
+    let parameters = self
+      .shapes
+      .shape(shape_id)
+      .map(|shape| &shape.descriptor.parameters);
+    match parameters {
+      Some(ShapeParametersDescriptor::StaticParameterList(list)) => &list.shape_parameter_ids,
+      Some(ShapeParametersDescriptor::DynamicParameterList(list)) => &list.shape_parameter_ids,
+      _ => return vec![],
+    }
+    .iter()
+    .map(|parameter_id| String::from(parameter_id.as_str()))
+    .collect()
+  }
+}
+
+// nullable optionals are just optional
+fn optional(rust_type: String) -> String {
+  if rust_type.starts_with("Option<") {
+    rust_type
+  } else {
+    format!("Option<{}>", rust_type)
+  }
+}
+
+// an enum deserializing from whichever of its options the JSON matches first, each type once
+fn untagged_enum(name: &str, types: IndexSet<String>) -> String {
+  let mut variant_names = UniqueNames::default();
+  let mut item = format!(
+    "#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]\n#[serde(untagged)]\npub enum {} {{\n",
+    name
+  );
+  for option_type in types {
+    let variant_name = match option_type.as_str() {
+      "f64" => String::from("Number"),
+      "bool" => String::from("Boolean"),
+      "serde_json::Value" => String::from("Any"),
+      _ if option_type.starts_with("Vec<") => String::from("List"),
+      _ if option_type.starts_with("HashMap<") => String::from("Map"),
+      _ if option_type.starts_with("Option<") => String::from("Null"),
+      _ => type_name(option_type.trim_start_matches("Box<")),
+    };
+    item.push_str(&format!(
+      "    {}({}),\n",
+      variant_names.unique(variant_name),
+      option_type
+    ));
+  }
+  item.push_str("}\n");
+  item
+}
+
+// Rust types for the bodies of every endpoint, named after its method and path (like
+// `GetUsersUserIdResponse200`), with the structs and enums in them, and the other object shapes
+// after those
+pub fn rust(state: &OpticState) -> String {
+  let mut generator = RustGenerator::new(state.shape);
+  for endpoint in endpoints(state) {
+    let endpoint_name = endpoint_type_name(endpoint.http_method, &endpoint.path);
+
+    let request_shape_ids: Vec<ShapeId> = endpoint
+      .requests
+      .iter()
+      .flat_map(|request| live_bodies(&request.request_descriptor.bodies))
+      .map(|body| body.shape_id)
+      .collect();
+    if !request_shape_ids.is_empty() {
+      generator.declare_type(&format!("{}Request", endpoint_name), &request_shape_ids);
+    }
+
+    let mut response_shape_ids: IndexMap<u16, Vec<ShapeId>> = IndexMap::new();
+    for response in &endpoint.responses {
+      let descriptor = &response.response_descriptor;
+      response_shape_ids
+        .entry(descriptor.http_status_code.as_u16())
+        .or_default()
+        .extend(live_bodies(&descriptor.bodies).map(|body| body.shape_id));
+    }
+    for (status_code, shape_ids) in response_shape_ids {
+      if !shape_ids.is_empty() {
+        let name = format!("{}Response{}", endpoint_name, status_code);
+        generator.declare_type(&name, &shape_ids);
+      }
+    }
+  }
+
+  for shape in state.shape.all_shapes().filter(|shape| !shape.is_removed) {
+    if state.shape.core_shape_id(shape.shape_id).as_str() == "$object" {
+      generator.type_for(shape.shape_id, shape.shape_id.as_str());
+    }
+  }
+
+  let items = generator.into_items();
+  let mut output =
+    String::from("// Generated from the spec\n\nuse serde::{Deserialize, Serialize};\n");
+  if items.iter().any(|item| item.contains("HashMap<")) {
+    output.push_str("use std::collections::HashMap;\n");
+  }
+  for item in items {
+    output.push('\n');
+    output.push_str(&item);
+  }
+  output
+}
+
+#[test]
+fn declares_structs_and_enums_for_shapes() {
+  use crate::aggregate::OpticAggregate;
+
+  let aggregate =
+    OpticAggregate::fold(crate::events_from_file("test-fixtures/uncompacted-spec.json").unwrap());
+  let items = rust(&aggregate.get_state());
+
+  assert!(items.contains(
+    "pub struct GetApiF1SeasonResponse200 {\n    #[serde(rename = \"MRData\")]\n    pub mr_data: MRData,\n}\n"
+  ));
+  assert!(items.contains("    #[serde(rename = \"Races\")]\n    pub races: Vec<RacesItem>,\n"));
+  assert!(items.contains("    #[serde(rename = \"raceName\")]\n    pub race_name: String,\n"));
+  assert!(items.contains(
+    "#[serde(untagged)]\npub enum GetApiF1SeasonRoundResultsResponse200 {\n    \
+     GetApiF1SeasonRoundResultsResponse200Body(GetApiF1SeasonRoundResultsResponse200Body),\n"
+  ));
+
+  // optional, nullable and recursive fields, one-ofs (of a type more than once), lists and maps
+  let events: Vec<crate::events::OpticEvent> = serde_json::from_value(serde_json::json!([
+    {"ShapeAdded": {"shapeId": "user", "baseShapeId": "$object", "parameters": {"DynamicParameterList": {"shapeParameterIds": []}}, "name": "User", "eventContext": null}},
+    {"ShapeAdded": {"shapeId": "maybe_string", "baseShapeId": "$optional", "parameters": {"DynamicParameterList": {"shapeParameterIds": []}}, "name": "", "eventContext": null}},
+    {"ShapeParameterShapeSet": {"shapeDescriptor": {"ProviderInShape": {"shapeId": "maybe_string", "providerDescriptor": {"ShapeProvider": {"shapeId": "$string"}}, "consumingParameterId": "$optionalInner"}}, "eventContext": null}},
+    {"ShapeAdded": {"shapeId": "null_user", "baseShapeId": "$nullable", "parameters": {"DynamicParameterList": {"shapeParameterIds": []}}, "name": "", "eventContext": null}},
+    {"ShapeParameterShapeSet": {"shapeDescriptor": {"ProviderInShape": {"shapeId": "null_user", "providerDescriptor": {"ShapeProvider": {"shapeId": "user"}}, "consumingParameterId": "$nullableInner"}}, "eventContext": null}},
+    {"ShapeAdded": {"shapeId": "either", "baseShapeId": "$oneOf", "parameters": {"DynamicParameterList": {"shapeParameterIds": ["a", "b", "c"]}}, "name": "", "eventContext": null}},
+    {"ShapeParameterShapeSet": {"shapeDescriptor": {"ProviderInShape": {"shapeId": "either", "providerDescriptor": {"ShapeProvider": {"shapeId": "$string"}}, "consumingParameterId": "a"}}, "eventContext": null}},
+    {"ShapeParameterShapeSet": {"shapeDescriptor": {"ProviderInShape": {"shapeId": "either", "providerDescriptor": {"ShapeProvider": {"shapeId": "$boolean"}}, "consumingParameterId": "b"}}, "eventContext": null}},
+    {"ShapeParameterShapeSet": {"shapeDescriptor": {"ProviderInShape": {"shapeId": "either", "providerDescriptor": {"ShapeProvider": {"shapeId": "$string"}}, "consumingParameterId": "c"}}, "eventContext": null}},
+    {"ShapeAdded": {"shapeId": "eithers", "baseShapeId": "$list", "parameters": {"DynamicParameterList": {"shapeParameterIds": []}}, "name": "", "eventContext": null}},
+    {"ShapeParameterShapeSet": {"shapeDescriptor": {"ProviderInShape": {"shapeId": "eithers", "providerDescriptor": {"ShapeProvider": {"shapeId": "either"}}, "consumingParameterId": "$listItem"}}, "eventContext": null}},
+    {"ShapeAdded": {"shapeId": "friends", "baseShapeId": "$map", "parameters": {"DynamicParameterList": {"shapeParameterIds": []}}, "name": "", "eventContext": null}},
+    {"ShapeParameterShapeSet": {"shapeDescriptor": {"ProviderInShape": {"shapeId": "friends", "providerDescriptor": {"ShapeProvider": {"shapeId": "user"}}, "consumingParameterId": "$mapValue"}}, "eventContext": null}},
+    {"FieldAdded": {"fieldId": "nickname", "shapeId": "user", "name": "nickname", "shapeDescriptor": {"FieldShapeFromShape": {"fieldId": "nickname", "shapeId": "maybe_string"}}, "eventContext": null}},
+    {"FieldAdded": {"fieldId": "mentor", "shapeId": "user", "name": "mentor", "shapeDescriptor": {"FieldShapeFromShape": {"fieldId": "mentor", "shapeId": "null_user"}}, "eventContext": null}},
+    {"FieldAdded": {"fieldId": "flags", "shapeId": "user", "name": "flag-list", "shapeDescriptor": {"FieldShapeFromShape": {"fieldId": "flags", "shapeId": "eithers"}}, "eventContext": null}},
+    {"FieldAdded": {"fieldId": "friends", "shapeId": "user", "name": "friends", "shapeDescriptor": {"FieldShapeFromShape": {"fieldId": "friends", "shapeId": "friends"}}, "eventContext": null}},
+  ]))
+  .unwrap();
+  let aggregate = OpticAggregate::fold(events);
+  assert_eq!(
+    rust(&aggregate.get_state()),
+    "// Generated from the spec\n\
+     \n\
+     use serde::{Deserialize, Serialize};\n\
+     use std::collections::HashMap;\n\
+     \n\
+     #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]\n\
+     pub struct User {\n    \
+       #[serde(default, skip_serializing_if = \"Option::is_none\")]\n    \
+       pub nickname: Option<String>,\n    \
+       pub mentor: Option<Box<User>>,\n    \
+       #[serde(rename = \"flag-list\")]\n    \
+       pub flag_list: Vec<FlagListItem>,\n    \
+       pub friends: HashMap<String, User>,\n\
+     }\n\
+     \n\
+     #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]\n\
+     #[serde(untagged)]\n\
+     pub enum FlagListItem {\n    \
+       String(String),\n    \
+       Boolean(bool),\n\
+     }\n"
+  );
+}
@@ -0,0 +1,310 @@
+use indexmap::IndexMap;
+
+use super::names::{endpoint_type_name, type_name, UniqueNames};
+use crate::aggregate::OpticState;
+use crate::projections::endpoints::endpoints;
+use crate::state::requests::live_bodies;
+use crate::state::shape::{is_core_shape, ShapeId, ShapeParametersDescriptor, ShapeState};
+
+// Translates shapes into TypeScript. User-defined objects become interfaces, named after the shape
+// or else after where they're first found (like the field they're the shape of), and everything
+// else is inlined as a type expression.
+pub struct TypeScriptGenerator<'a> {
+  shapes: &'a ShapeState,
+  interfaces: IndexMap<ShapeId, String>,
+  names: UniqueNames,
+  declarations: Vec<String>,
+  resolving: Vec<ShapeId>,
+}
+
+impl<'a> TypeScriptGenerator<'a> {
+  pub fn new(shapes: &'a ShapeState) -> Self {
+    TypeScriptGenerator {
+      shapes,
+      interfaces: IndexMap::new(),
+      names: UniqueNames::default(),
+      declarations: vec![],
+      resolving: vec![],
+    }
+  }
+
+  pub fn into_declarations(self) -> Vec<String> {
+    self.declarations
+  }
+
+  // a type alias for the union of shapes, unless it's one object whose interface gets the name
+  pub fn declare_type(&mut self, name: &str, shape_ids: &[ShapeId]) {
+    if let [shape_id] = shape_ids {
+      if self.type_for(*shape_id, name) == name {
+        return;
+      }
+    }
+    let name = self.names.unique(String::from(name));
+    let types: Vec<String> = shape_ids
+      .iter()
+      .map(|shape_id| self.type_for(*shape_id, &format!("{}Body", name)))
+      .collect();
+    self
+      .declarations
+      .push(format!("export type {} = {};\n", name, union(types)));
+  }
+
+  pub fn type_for(&mut self, shape_id: ShapeId, name_hint: &str) -> String {
+    // objects are referred to by the name of their interface, which keeps recursive shapes finite
+    if let Some(name) = self.interfaces.get(&shape_id) {
+      return name.clone();
+    }
+    if self.resolving.contains(&shape_id) {
+      return String::from("unknown");
+    }
+
+    self.resolving.push(shape_id);
+    let resolved = self.resolve(shape_id, name_hint);
+    self.resolving.pop();
+    resolved
+  }
+
+  fn resolve(&mut self, shape_id: ShapeId, name_hint: &str) -> String {
+    let core_shape_id = self.shapes.core_shape_id(shape_id);
+    match core_shape_id.as_str() {
+      "$object" if !is_core_shape(shape_id) => self.declare_interface(shape_id, name_hint),
+      "$object" => String::from("Record<string, unknown>"),
+      "$string" => String::from("string"),
+      "$number" => String::from("number"),
+      "$boolean" => String::from("boolean"),
+      "$list" => {
+        let item = self.bound_type(shape_id, "$listItem", &format!("{}Item", name_hint));
+        if item.contains(' ') {
+          format!("({})[]", item)
+        } else {
+          format!("{}[]", item)
+        }
+      }
+      "$map" => format!(
+        "Record<string, {}>",
+        self.bound_type(shape_id, "$mapValue", &format!("{}Value", name_hint))
+      ),
+      "$nullable" => format!(
+        "{} | null",
+        self.bound_type(shape_id, "$nullableInner", name_hint)
+      ),
+      "$optional" => format!(
+        "{} | undefined",
+        self.bound_type(shape_id, "$optionalInner", name_hint)
+      ),
+      "$identifier" => self.bound_type(shape_id, "$identifierInner", name_hint),
+      "$reference" => self.bound_type(shape_id, "$referenceInner", name_hint),
+      "$oneOf" => {
+        let options: Vec<String> = self
+          .parameter_ids(shape_id)
+          .iter()
+          .map(|parameter_id| self.bound_type(shape_id, parameter_id, name_hint))
+          .collect();
+        union(options)
+      }
+      _ => String::from("unknown"),
+    }
+  }
+
+  fn declare_interface(&mut self, shape_id: ShapeId, name_hint: &str) -> String {
+    let name = match self.shapes.shape(shape_id) {
+      Some(shape) if !shape.descriptor.name.is_empty() => type_name(&shape.descriptor.name),
+      _ => type_name(name_hint),
+    };
+    let name = self.names.unique(name);
+    // reserve the interface before resolving fields, so fields referring back to it terminate, and
+    // so it's declared before the interfaces of its fields
+    self.interfaces.insert(shape_id, name.clone());
+    let index = self.declarations.len();
+    self.declarations.push(String::new());
+
+    let mut declaration = format!("export interface {} {{\n", name);
+    for field in self.shapes.resolved_fields_of(shape_id) {
+      let field_name = &field.descriptor.name;
+      let field_shape_id = field.descriptor.shape_descriptor.shape_id();
+      let optional_inner = field_shape_id.and_then(|field_shape_id| {
+        match self.shapes.core_shape_id(field_shape_id).as_str() {
+          "$optional" => Some(self.shapes.bound_shape_id(field_shape_id, "$optionalInner")),
+          _ => None,
+        }
+      });
+      let field_type = match optional_inner.unwrap_or(field_shape_id) {
+        Some(field_shape_id) => self.type_for(field_shape_id, field_name),
+        None => String::from("unknown"),
+      };
+
+      declaration.push_str(&format!(
+        "  {}{}: {};\n",
+        property_name(field_name),
+        if optional_inner.is_some() { "?" } else { "" },
+        field_type
+      ));
+    }
+    declaration.push_str("}\n");
+    self.declarations[index] = declaration;
+    name
+  }
+
+  fn bound_type(&mut self, shape_id: ShapeId, parameter_id: &str, name_hint: &str) -> String {
+    match self.shapes.bound_shape_id(shape_id, parameter_id) {
+      Some(bound_shape_id) => self.type_for(bound_shape_id, name_hint),
+      None => String::from("unknown"),
+    }
+  }
+
+  fn parameter_ids(&self, shape_id: ShapeId) -> Vec<String> {
+    let parameters = self
+      .shapes
+      .shape(shape_id)
+      .map(|shape| &shape.descriptor.parameters);
+    match parameters {
+      Some(ShapeParametersDescriptor::StaticParameterList(list)) => &list.shape_parameter_ids,
+      Some(ShapeParametersDescriptor::DynamicParameterList(list)) => &list.shape_parameter_ids,
+      _ => return vec![],
+    }
+    .iter()
+    .map(|parameter_id| String::from(parameter_id.as_str()))
+    .collect()
+  }
+}
+
+fn union(mut types: Vec<String>) -> String {
+  types.dedup();
+  match types.len() {
+    0 => String::from("never"),
+    _ => types.join(" | "),
+  }
+}
+
+// field names that aren't identifiers are quoted
+fn property_name(name: &str) -> String {
+  let mut chars = name.chars();
+  let is_identifier = chars
+    .next()
+    .is_some_and(|first| first.is_ascii_alphabetic() || first == '_' || first == '$')
+    && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+  if is_identifier {
+    String::from(name)
+  } else {
+    serde_json::to_string(name).unwrap_or_else(|_| String::from(name))
+  }
+}
+
+// TypeScript declarations for the bodies of every endpoint, named after its method and path (like
+// `GetUsersUserIdResponse200`), with interfaces for the objects in them, and for the other object
+// shapes after those
+pub fn typescript(state: &OpticState) -> String {
+  let mut generator = TypeScriptGenerator::new(state.shape);
+  for endpoint in endpoints(state) {
+    let endpoint_name = endpoint_type_name(endpoint.http_method, &endpoint.path);
+
+    let request_shape_ids: Vec<ShapeId> = endpoint
+      .requests
+      .iter()
+      .flat_map(|request| live_bodies(&request.request_descriptor.bodies))
+      .map(|body| body.shape_id)
+      .collect();
+    if !request_shape_ids.is_empty() {
+      generator.declare_type(&format!("{}Request", endpoint_name), &request_shape_ids);
+    }
+
+    let mut response_shape_ids: IndexMap<u16, Vec<ShapeId>> = IndexMap::new();
+    for response in &endpoint.responses {
+      let descriptor = &response.response_descriptor;
+      response_shape_ids
+        .entry(descriptor.http_status_code.as_u16())
+        .or_default()
+        .extend(live_bodies(&descriptor.bodies).map(|body| body.shape_id));
+    }
+    for (status_code, shape_ids) in response_shape_ids {
+      if !shape_ids.is_empty() {
+        let name = format!("{}Response{}", endpoint_name, status_code);
+        generator.declare_type(&name, &shape_ids);
+      }
+    }
+  }
+
+  for shape in state.shape.all_shapes().filter(|shape| !shape.is_removed) {
+    if state.shape.core_shape_id(shape.shape_id).as_str() == "$object" {
+      generator.type_for(shape.shape_id, shape.shape_id.as_str());
+    }
+  }
+
+  let mut output = String::from("// Generated from the spec\n");
+  for declaration in generator.into_declarations() {
+    output.push('\n');
+    output.push_str(&declaration);
+  }
+  output
+}
+
+#[test]
+fn declares_interfaces_and_endpoint_types() {
+  use crate::aggregate::OpticAggregate;
+
+  let aggregate =
+    OpticAggregate::fold(crate::events_from_file("test-fixtures/uncompacted-spec.json").unwrap());
+  let declarations = typescript(&aggregate.get_state());
+
+  // response bodies are interfaces named after their endpoint, and nested objects after their field
+  assert!(
+    declarations.contains("export interface GetApiF1SeasonResponse200 {\n  MRData: MRData;\n")
+  );
+  assert!(declarations.contains("  Races: RacesItem[];\n"));
+  assert!(declarations.contains("export interface Location {\n  country: string;\n"));
+  assert!(declarations.contains("export interface PostFollowingDriversRequest {\n"));
+  assert!(declarations.contains("export interface PostFollowingDriversResponse400 {\n"));
+  // bodies of several content types are a union
+  assert!(declarations.contains(
+    "export type GetApiF1SeasonRoundResultsResponse200 = GetApiF1SeasonRoundResultsResponse200Body | GetApiF1SeasonRoundResultsResponse200Body2;\n"
+  ));
+  let interfaces = declarations.matches("export interface ").count();
+  let object_shapes = aggregate
+    .get_state()
+    .shape
+    .all_shapes()
+    .filter(|shape| !shape.is_removed && !is_core_shape(shape.shape_id))
+    .filter(|shape| {
+      aggregate
+        .get_state()
+        .shape
+        .core_shape_id(shape.shape_id)
+        .as_str()
+        == "$object"
+    })
+    .count();
+  assert_eq!(interfaces, object_shapes);
+
+  // optional and nullable fields, unions, lists and maps
+  let events: Vec<crate::events::OpticEvent> = serde_json::from_value(serde_json::json!([
+    {"ShapeAdded": {"shapeId": "user", "baseShapeId": "$object", "parameters": {"DynamicParameterList": {"shapeParameterIds": []}}, "name": "User", "eventContext": null}},
+    {"ShapeAdded": {"shapeId": "maybe_string", "baseShapeId": "$optional", "parameters": {"DynamicParameterList": {"shapeParameterIds": []}}, "name": "", "eventContext": null}},
+    {"ShapeParameterShapeSet": {"shapeDescriptor": {"ProviderInShape": {"shapeId": "maybe_string", "providerDescriptor": {"ShapeProvider": {"shapeId": "$string"}}, "consumingParameterId": "$optionalInner"}}, "eventContext": null}},
+    {"ShapeAdded": {"shapeId": "null_number", "baseShapeId": "$nullable", "parameters": {"DynamicParameterList": {"shapeParameterIds": []}}, "name": "", "eventContext": null}},
+    {"ShapeParameterShapeSet": {"shapeDescriptor": {"ProviderInShape": {"shapeId": "null_number", "providerDescriptor": {"ShapeProvider": {"shapeId": "$number"}}, "consumingParameterId": "$nullableInner"}}, "eventContext": null}},
+    {"ShapeAdded": {"shapeId": "either", "baseShapeId": "$oneOf", "parameters": {"DynamicParameterList": {"shapeParameterIds": ["a", "b"]}}, "name": "", "eventContext": null}},
+    {"ShapeParameterShapeSet": {"shapeDescriptor": {"ProviderInShape": {"shapeId": "either", "providerDescriptor": {"ShapeProvider": {"shapeId": "$string"}}, "consumingParameterId": "a"}}, "eventContext": null}},
+    {"ShapeParameterShapeSet": {"shapeDescriptor": {"ProviderInShape": {"shapeId": "either", "providerDescriptor": {"ShapeProvider": {"shapeId": "$boolean"}}, "consumingParameterId": "b"}}, "eventContext": null}},
+    {"ShapeAdded": {"shapeId": "eithers", "baseShapeId": "$list", "parameters": {"DynamicParameterList": {"shapeParameterIds": []}}, "name": "", "eventContext": null}},
+    {"ShapeParameterShapeSet": {"shapeDescriptor": {"ProviderInShape": {"shapeId": "eithers", "providerDescriptor": {"ShapeProvider": {"shapeId": "either"}}, "consumingParameterId": "$listItem"}}, "eventContext": null}},
+    {"ShapeAdded": {"shapeId": "friends", "baseShapeId": "$map", "parameters": {"DynamicParameterList": {"shapeParameterIds": []}}, "name": "", "eventContext": null}},
+    {"ShapeParameterShapeSet": {"shapeDescriptor": {"ProviderInShape": {"shapeId": "friends", "providerDescriptor": {"ShapeProvider": {"shapeId": "user"}}, "consumingParameterId": "$mapValue"}}, "eventContext": null}},
+    {"FieldAdded": {"fieldId": "nickname", "shapeId": "user", "name": "nickname", "shapeDescriptor": {"FieldShapeFromShape": {"fieldId": "nickname", "shapeId": "maybe_string"}}, "eventContext": null}},
+    {"FieldAdded": {"fieldId": "age", "shapeId": "user", "name": "age", "shapeDescriptor": {"FieldShapeFromShape": {"fieldId": "age", "shapeId": "null_number"}}, "eventContext": null}},
+    {"FieldAdded": {"fieldId": "flags", "shapeId": "user", "name": "flag-list", "shapeDescriptor": {"FieldShapeFromShape": {"fieldId": "flags", "shapeId": "eithers"}}, "eventContext": null}},
+    {"FieldAdded": {"fieldId": "friends", "shapeId": "user", "name": "friends", "shapeDescriptor": {"FieldShapeFromShape": {"fieldId": "friends", "shapeId": "friends"}}, "eventContext": null}},
+  ]))
+  .unwrap();
+  let aggregate = OpticAggregate::fold(events);
+  assert_eq!(
+    typescript(&aggregate.get_state()),
+    "// Generated from the spec\n\
+     \n\
+     export interface User {\n  \
+       nickname?: string;\n  \
+       age: number | null;\n  \
+       \"flag-list\": (string | boolean)[];\n  \
+       friends: Record<string, User>;\n\
+     }\n"
+  );
+}
@@ -2,36 +2,71 @@
 
 use std::env;
 use std::fs;
+use std::process;
 
 #[macro_use]
 extern crate serde_derive;
 
 mod aggregate;
+mod cli;
+mod compaction;
 mod events;
+mod export;
+mod merge;
+mod mock;
+mod projections;
+mod reports;
+mod snapshot;
 mod state;
 
-use aggregate::{Aggregate, OpticAggregate};
-
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    let filename = &args[1];
+    let args: Vec<String> = env::args().skip(1).collect();
 
-    let incoming_events = events_from_file(filename);
-    let mut aggregate = OpticAggregate::default();
-    for event in incoming_events {
-        aggregate.apply(event)
+    if let Err(err) = cli::parse(&args).and_then(cli::run) {
+        eprintln!("{}", err);
+        process::exit(err.exit_code());
     }
-    println!("Current state: {:?}", aggregate.get_state())
 }
 
-fn events_from_file(filename: &str) -> Vec<events::OpticEvent> {
-    let file_contents =
-        fs::read_to_string(filename).expect(&format!("File at {} could not be read", &filename));
+fn events_from_file(filename: &str) -> Result<Vec<events::OpticEvent>, String> {
+    let file_contents = fs::read_to_string(filename)
+        .map_err(|err| format!("File at {} could not be read: {}", filename, err))?;
 
-    let events: Vec<events::OpticEvent> =
-        serde_json::from_str(&file_contents).expect("File must be valid JSON");
+    let mut raw_events: Vec<serde_json::Value> =
+        serde_json::from_str(&file_contents).map_err(|err| {
+            format!(
+                "File at {} must be a JSON array of events: {}",
+                filename, err
+            )
+        })?;
+    let upcast = events::upcasting::upcast_events(&mut raw_events).map_err(|err| {
+        format!(
+            "Event {} in file at {} could not be upcast: {}",
+            err.index, filename, err.message
+        )
+    })?;
 
-    events
+    // events are deserialized from the file itself when none were upcast, so errors can point at
+    // their line, and otherwise one by one, so errors point at their event and what's wrong with it
+    let mut position = String::new();
+    if upcast.from_version == events::upcasting::SCHEMA_VERSION {
+        match serde_json::from_str(&file_contents) {
+            Ok(events) => return Ok(events),
+            Err(err) => position = format!(" at line {} column {}", err.line(), err.column()),
+        }
+    }
+    raw_events
+        .iter()
+        .enumerate()
+        .map(|(index, raw_event)| {
+            events::OpticEvent::from_value(raw_event).map_err(|err| {
+                format!(
+                    "Event {} in file at {} must be a valid event: {}{}",
+                    index, filename, err, position
+                )
+            })
+        })
+        .collect()
 }
 
 #[test]
@@ -43,5 +78,6 @@ fn can_read_events() {
             .join("test-fixtures/uncompacted-spec.json")
             .to_str()
             .unwrap(),
-    );
+    )
+    .unwrap();
 }
@@ -0,0 +1,636 @@
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::events::batches::batches;
+use crate::events::requests::RequestsEvent;
+use crate::events::rfc::RfcEvent;
+use crate::events::shape::ShapeEvent;
+use crate::events::OpticEvent;
+use crate::state::requests::{PathComponentId, RequestId, RequestParameterId, ResponseId};
+use crate::state::shape::{
+  FieldId, FieldShapeDescriptor, ParameterShapeDescriptor, ShapeId, ShapeParameterId,
+};
+
+// Merging two specs that diverged from a common ancestor: both streams start with the events of
+// the ancestor, after which each side appended its own. The merged stream is the ancestor, our
+// events and then their events, leaving out their batches we already have (by batch id, like when
+// a branch was merged before) and their events that conflict with ours.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Merge {
+  pub events: Vec<OpticEvent>,
+  pub conflicts: Vec<Conflict>,
+  // the branches each side was last on, when recorded with `GitStateSet`
+  pub our_branch: Option<String>,
+  pub their_branch: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Conflict {
+  pub entity: String,
+  pub message: String,
+  pub ours: Vec<String>,
+  pub theirs: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum MergeError {
+  NotDescendedFromBase(&'static str),
+}
+
+impl fmt::Display for MergeError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      MergeError::NotDescendedFromBase(side) => {
+        write!(f, "{} events don't start with the events of the base", side)
+      }
+    }
+  }
+}
+
+// Merges the events appended to ours and theirs since base. Without a base, the longest common
+// prefix of both streams is taken as their common ancestor.
+pub fn merge(
+  base: Option<&[OpticEvent]>,
+  ours: &[OpticEvent],
+  theirs: &[OpticEvent],
+) -> Result<Merge, MergeError> {
+  let ours_json: Vec<Value> = ours.iter().map(to_value).collect();
+  let theirs_json: Vec<Value> = theirs.iter().map(to_value).collect();
+
+  let ancestor_len = match base {
+    Some(base) => {
+      let base_json: Vec<Value> = base.iter().map(to_value).collect();
+      if !ours_json.starts_with(&base_json) {
+        return Err(MergeError::NotDescendedFromBase("our"));
+      }
+      if !theirs_json.starts_with(&base_json) {
+        return Err(MergeError::NotDescendedFromBase("their"));
+      }
+      base_json.len()
+    }
+    None => ours_json
+      .iter()
+      .zip(&theirs_json)
+      .take_while(|(ours, theirs)| ours == theirs)
+      .count(),
+  };
+  let (ours_tail, theirs_tail) = (&ours[ancestor_len..], &theirs[ancestor_len..]);
+
+  // their batches and events we already have
+  let our_batch_ids: HashSet<&str> = ours_tail.iter().filter_map(batch_started).collect();
+  let ours_tail_json = &ours_json[ancestor_len..];
+  let mut already_merged = vec![false; theirs_tail.len()];
+  let mut current_batch_merged = false;
+  for (index, event) in theirs_tail.iter().enumerate() {
+    if let Some(batch_id) = batch_started(event) {
+      current_batch_merged = our_batch_ids.contains(batch_id);
+    }
+    already_merged[index] =
+      current_batch_merged || ours_tail_json.contains(&theirs_json[ancestor_len + index]);
+    if let OpticEvent::RfcEvent(RfcEvent::BatchCommitEnded(_)) = event {
+      current_batch_merged = false;
+    }
+  }
+
+  let our_touches: Vec<(usize, Entity, Touch)> = touches_of(ours_tail, |_| true);
+  let their_touches: Vec<(usize, Entity, Touch)> =
+    touches_of(theirs_tail, |index| !already_merged[index]);
+
+  let mut conflicts = vec![];
+  // the conflict each of their events is left out for
+  let mut conflicting: Vec<Option<usize>> = vec![None; theirs_tail.len()];
+  let mut entities: Vec<&Entity> = vec![];
+  for (_, entity, _) in &their_touches {
+    if !entities.contains(&entity) {
+      entities.push(entity);
+    }
+  }
+
+  for entity in entities {
+    let ours_of = |predicate: &dyn Fn(&Touch) -> bool| -> Vec<usize> {
+      our_touches
+        .iter()
+        .filter(|(_, e, touch)| e == entity && predicate(touch))
+        .map(|(index, _, _)| *index)
+        .collect()
+    };
+    let theirs_of = |predicate: &dyn Fn(&Touch) -> bool| -> Vec<usize> {
+      their_touches
+        .iter()
+        .filter(|(_, e, touch)| e == entity && predicate(touch))
+        .map(|(index, _, _)| *index)
+        .collect()
+    };
+    let mut conflict = |message: String, ours: Vec<usize>, theirs: Vec<usize>| {
+      for index in &theirs {
+        conflicting[*index] = Some(conflicts.len());
+      }
+      conflicts.push(Conflict {
+        entity: entity.to_string(),
+        message,
+        ours: ours
+          .iter()
+          .map(|index| describe(&ours_tail[*index]))
+          .collect(),
+        theirs: theirs
+          .iter()
+          .map(|index| describe(&theirs_tail[*index]))
+          .collect(),
+      });
+    };
+
+    let (ours_removed, theirs_removed) =
+      (ours_of(&Touch::is_removal), theirs_of(&Touch::is_removal));
+    let ours_modified = ours_of(&|touch| !touch.is_removal());
+    let theirs_modified = theirs_of(&|touch| !touch.is_removal());
+    if !ours_removed.is_empty() && !theirs_modified.is_empty() {
+      conflict(
+        String::from("removed by us but modified by them"),
+        ours_removed,
+        theirs_modified,
+      );
+      continue;
+    }
+    if !theirs_removed.is_empty() && !ours_modified.is_empty() {
+      conflict(
+        String::from("modified by us but removed by them"),
+        ours_modified,
+        theirs_removed,
+      );
+      continue;
+    }
+
+    // the same attribute set to different values, where the last value set on each side counts
+    let mut attributes: Vec<&str> = vec![];
+    for (_, e, touch) in &their_touches {
+      if let Touch::Changed(attribute, _) = touch {
+        if e == entity && !attributes.contains(&attribute.as_str()) {
+          attributes.push(attribute);
+        }
+      }
+    }
+    for attribute in attributes {
+      let changes_of = |touches: &[(usize, Entity, Touch)]| -> Vec<(usize, Value)> {
+        touches
+          .iter()
+          .filter(|(_, e, _)| e == entity)
+          .filter_map(|(index, _, touch)| match touch {
+            Touch::Changed(a, value) if a == attribute => Some((*index, value.clone())),
+            _ => None,
+          })
+          .collect()
+      };
+      let (our_changes, their_changes) = (changes_of(&our_touches), changes_of(&their_touches));
+      if let (Some((_, ours)), Some((_, theirs))) = (our_changes.last(), their_changes.last()) {
+        if ours != theirs {
+          conflict(
+            format!("{} changed differently", attribute),
+            our_changes.iter().map(|(index, _)| *index).collect(),
+            their_changes.iter().map(|(index, _)| *index).collect(),
+          );
+        }
+      }
+    }
+
+    let (our_additions, their_additions) =
+      (ours_of(&Touch::is_addition), theirs_of(&Touch::is_addition));
+    if let (Some(ours), Some(theirs)) = (our_additions.first(), their_additions.first()) {
+      if payload(&ours_tail[*ours]) != payload(&theirs_tail[*theirs]) {
+        conflict(
+          String::from("added differently by both"),
+          our_additions,
+          their_additions,
+        );
+      }
+    }
+  }
+
+  // their events on entities that one of their left out events added can't be applied either, so
+  // they're left out for the same conflict
+  let mut left_out_entities: Vec<(Entity, usize)> = vec![];
+  for (index, event) in theirs_tail.iter().enumerate() {
+    if already_merged[index] {
+      continue;
+    }
+    let touches = event_touches(event);
+    if conflicting[index].is_none() {
+      conflicting[index] = touches.iter().find_map(|(entity, _)| {
+        left_out_entities
+          .iter()
+          .find(|(left_out, _)| left_out == entity)
+          .map(|(_, conflict_index)| *conflict_index)
+      });
+      if let Some(conflict_index) = conflicting[index] {
+        conflicts[conflict_index].theirs.push(describe(event));
+      }
+    }
+    if let Some(conflict_index) = conflicting[index] {
+      for (entity, touch) in touches {
+        if touch.is_addition() {
+          left_out_entities.push((entity, conflict_index));
+        }
+      }
+    }
+  }
+
+  // and of their batches, the ones none of whose events are kept aren't started or ended either
+  let mut left_out: Vec<bool> = (0..theirs_tail.len())
+    .map(|index| already_merged[index] || conflicting[index].is_some())
+    .collect();
+  for batch in batches(theirs_tail) {
+    let first = batch.index + 1;
+    let last = first + batch.events.len();
+    if batch.is_outside_batches() || !left_out[first..last].iter().all(|left_out| *left_out) {
+      continue;
+    }
+    left_out[batch.index] = true;
+    if batch.ended.is_some() {
+      left_out[last] = true;
+    }
+  }
+
+  let mut events: Vec<OpticEvent> = ours.to_vec();
+  events.extend(
+    theirs_tail
+      .iter()
+      .enumerate()
+      .filter(|(index, _)| !left_out[*index])
+      .map(|(_, event)| event.clone()),
+  );
+
+  Ok(Merge {
+    events,
+    conflicts,
+    our_branch: last_branch(ours),
+    their_branch: last_branch(theirs),
+  })
+}
+
+// Entities
+// --------
+
+#[derive(Debug, Clone, PartialEq)]
+enum Entity {
+  PathComponent(PathComponentId),
+  RequestParameter(RequestParameterId),
+  Request(RequestId),
+  Response(ResponseId),
+  Shape(ShapeId),
+  ShapeParameter(ShapeParameterId),
+  Field(FieldId),
+  Contribution(String),
+  Api,
+}
+
+impl fmt::Display for Entity {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Entity::PathComponent(id) => write!(f, "path {}", id),
+      Entity::RequestParameter(id) => write!(f, "request parameter {}", id),
+      Entity::Request(id) => write!(f, "request {}", id),
+      Entity::Response(id) => write!(f, "response {}", id),
+      Entity::Shape(id) => write!(f, "shape {}", id),
+      Entity::ShapeParameter(id) => write!(f, "shape parameter {}", id),
+      Entity::Field(id) => write!(f, "field {}", id),
+      Entity::Contribution(id) => write!(f, "contributions to {}", id),
+      Entity::Api => write!(f, "api"),
+    }
+  }
+}
+
+// How an event affects an entity: adding it, setting one of its attributes (to the value of the
+// event), adding something to it (like a field to a shape) or removing it
+enum Touch {
+  Added,
+  Changed(String, Value),
+  Extended,
+  Removed,
+}
+
+impl Touch {
+  fn is_removal(&self) -> bool {
+    matches!(self, Touch::Removed)
+  }
+
+  fn is_addition(&self) -> bool {
+    matches!(self, Touch::Added)
+  }
+}
+
+fn touches_of<F: Fn(usize) -> bool>(
+  events: &[OpticEvent],
+  include: F,
+) -> Vec<(usize, Entity, Touch)> {
+  let mut touches = vec![];
+  for (index, event) in events
+    .iter()
+    .enumerate()
+    .filter(|(index, _)| include(*index))
+  {
+    touches.extend(
+      event_touches(event)
+        .into_iter()
+        .map(|(entity, touch)| (index, entity, touch)),
+    );
+  }
+  touches
+}
+
+fn event_touches(event: &OpticEvent) -> Vec<(Entity, Touch)> {
+  let changed = |attribute: &str| Touch::Changed(String::from(attribute), payload(event));
+
+  match event {
+    OpticEvent::RequestsEvent(event) => match event {
+      RequestsEvent::PathComponentAdded(e) => vec![
+        (Entity::PathComponent(e.path_id), Touch::Added),
+        (Entity::PathComponent(e.parent_path_id), Touch::Extended),
+      ],
+      RequestsEvent::PathComponentRenamed(e) => {
+        vec![(Entity::PathComponent(e.path_id), changed("name"))]
+      }
+      RequestsEvent::PathComponentRemoved(e) => {
+        vec![(Entity::PathComponent(e.path_id), Touch::Removed)]
+      }
+      RequestsEvent::PathParameterAdded(e) => vec![
+        (Entity::PathComponent(e.path_id), Touch::Added),
+        (Entity::PathComponent(e.parent_path_id), Touch::Extended),
+      ],
+      RequestsEvent::PathParameterShapeSet(e) => {
+        vec![(Entity::PathComponent(e.path_id), changed("shape"))]
+      }
+      RequestsEvent::PathParameterRenamed(e) => {
+        vec![(Entity::PathComponent(e.path_id), changed("name"))]
+      }
+      RequestsEvent::PathParameterRemoved(e) => {
+        vec![(Entity::PathComponent(e.path_id), Touch::Removed)]
+      }
+      RequestsEvent::RequestParameterAddedByPathAndMethod(e) => vec![
+        (Entity::RequestParameter(e.parameter_id), Touch::Added),
+        (Entity::PathComponent(e.path_id), Touch::Extended),
+      ],
+      RequestsEvent::RequestParameterRenamed(e) => {
+        vec![(Entity::RequestParameter(e.parameter_id), changed("name"))]
+      }
+      RequestsEvent::RequestParameterShapeSet(e) => {
+        vec![(Entity::RequestParameter(e.parameter_id), changed("shape"))]
+      }
+      RequestsEvent::RequestParameterShapeUnset(e) => {
+        vec![(Entity::RequestParameter(e.parameter_id), changed("shape"))]
+      }
+      RequestsEvent::RequestParameterRemoved(e) => {
+        vec![(Entity::RequestParameter(e.parameter_id), Touch::Removed)]
+      }
+      RequestsEvent::RequestAdded(e) => vec![
+        (Entity::Request(e.request_id), Touch::Added),
+        (Entity::PathComponent(e.path_id), Touch::Extended),
+      ],
+      RequestsEvent::RequestContentTypeSet(e) => vec![(
+        Entity::Request(e.request_id),
+        changed(&format!("body {}", e.http_content_type)),
+      )],
+      RequestsEvent::RequestBodySet(e) => vec![(
+        Entity::Request(e.request_id),
+        changed(&body_attribute(Some(&e.body_descriptor.http_content_type))),
+      )],
+      RequestsEvent::RequestBodyUnset(e) => vec![(
+        Entity::Request(e.request_id),
+        changed(&body_attribute(e.http_content_type.as_deref())),
+      )],
+      RequestsEvent::RequestRemoved(e) => vec![(Entity::Request(e.request_id), Touch::Removed)],
+      RequestsEvent::ResponseAddedByPathAndMethod(e) => vec![
+        (Entity::Response(e.response_id), Touch::Added),
+        (Entity::PathComponent(e.path_id), Touch::Extended),
+      ],
+      RequestsEvent::ResponseStatusCodeSet(e) => {
+        vec![(Entity::Response(e.response_id), changed("status code"))]
+      }
+      RequestsEvent::ResponseContentTypeSet(e) => vec![(
+        Entity::Response(e.response_id),
+        changed(&format!("body {}", e.http_content_type)),
+      )],
+      RequestsEvent::ResponseBodySet(e) => vec![(
+        Entity::Response(e.response_id),
+        changed(&body_attribute(Some(&e.body_descriptor.http_content_type))),
+      )],
+      RequestsEvent::ResponseBodyUnset(e) => vec![(
+        Entity::Response(e.response_id),
+        changed(&body_attribute(e.http_content_type.as_deref())),
+      )],
+      RequestsEvent::ResponseRemoved(e) => vec![(Entity::Response(e.response_id), Touch::Removed)],
+    },
+    OpticEvent::ShapeEvent(event) => match event {
+      ShapeEvent::ShapeAdded(e) => vec![(Entity::Shape(e.shape_id), Touch::Added)],
+      ShapeEvent::BaseShapeSet(e) => vec![(Entity::Shape(e.shape_id), changed("base shape"))],
+      ShapeEvent::ShapeRenamed(e) => vec![(Entity::Shape(e.shape_id), changed("name"))],
+      ShapeEvent::ShapeRemoved(e) => vec![(Entity::Shape(e.shape_id), Touch::Removed)],
+      ShapeEvent::ShapeParameterAdded(e) => vec![
+        (Entity::ShapeParameter(e.shape_parameter_id), Touch::Added),
+        (Entity::Shape(e.shape_id), Touch::Extended),
+      ],
+      ShapeEvent::ShapeParameterShapeSet(e) => match &e.shape_descriptor {
+        ParameterShapeDescriptor::ProviderInShape(binding) => vec![(
+          Entity::Shape(binding.shape_id),
+          changed(&format!("binding of {}", binding.consuming_parameter_id)),
+        )],
+        ParameterShapeDescriptor::ProviderInField(binding) => vec![(
+          Entity::Field(binding.field_id),
+          changed(&format!("binding of {}", binding.consuming_parameter_id)),
+        )],
+      },
+      ShapeEvent::ShapeParameterRenamed(e) => {
+        vec![(
+          Entity::ShapeParameter(e.shape_parameter_id),
+          changed("name"),
+        )]
+      }
+      ShapeEvent::ShapeParameterRemoved(e) => {
+        vec![(Entity::ShapeParameter(e.shape_parameter_id), Touch::Removed)]
+      }
+      ShapeEvent::FieldAdded(e) => vec![
+        (Entity::Field(e.field_id), Touch::Added),
+        (Entity::Shape(e.shape_id), Touch::Extended),
+      ],
+      ShapeEvent::FieldShapeSet(e) => {
+        let field_id = match &e.shape_descriptor {
+          FieldShapeDescriptor::FieldShapeFromShape(descriptor) => descriptor.field_id,
+          FieldShapeDescriptor::FieldShapeFromParameter(descriptor) => descriptor.field_id,
+        };
+        vec![(Entity::Field(field_id), changed("shape"))]
+      }
+      ShapeEvent::FieldRenamed(e) => vec![(Entity::Field(e.field_id), changed("name"))],
+      ShapeEvent::FieldRemoved(e) => vec![(Entity::Field(e.field_id), Touch::Removed)],
+    },
+    OpticEvent::RfcEvent(event) => match event {
+      RfcEvent::ContributionAdded(e) => vec![(Entity::Contribution(e.id.clone()), changed(&e.key))],
+      RfcEvent::APINamed(_) => vec![(Entity::Api, changed("name"))],
+      RfcEvent::GitStateSet(_)
+      | RfcEvent::BatchCommitStarted(_)
+      | RfcEvent::BatchCommitEnded(_) => {
+        vec![]
+      }
+    },
+  }
+}
+
+// bodies are set per content type, so only changes to the body of the same content type conflict
+fn body_attribute(http_content_type: Option<&str>) -> String {
+  match http_content_type {
+    Some(http_content_type) => format!("body {}", http_content_type),
+    None => String::from("bodies"),
+  }
+}
+
+fn batch_started(event: &OpticEvent) -> Option<&str> {
+  match event {
+    OpticEvent::RfcEvent(RfcEvent::BatchCommitStarted(e)) => Some(&e.batch_id),
+    _ => None,
+  }
+}
+
+fn last_branch(events: &[OpticEvent]) -> Option<String> {
+  events.iter().rev().find_map(|event| match event {
+    OpticEvent::RfcEvent(RfcEvent::GitStateSet(e)) => Some(e.branch_name.clone()),
+    _ => None,
+  })
+}
+
+fn to_value(event: &OpticEvent) -> Value {
+  serde_json::to_value(event).expect("events can be represented as JSON")
+}
+
+// what an event does, regardless of who did it when
+fn payload(event: &OpticEvent) -> Value {
+  let mut value = to_value(event);
+  if let Some(Value::Object(event)) = value.as_object_mut().and_then(|e| e.values_mut().next()) {
+    event.remove("eventContext");
+  }
+  value
+}
+
+fn describe(event: &OpticEvent) -> String {
+  let payload = payload(event).to_string();
+  match event
+    .event_context()
+    .map(|context| context.client_command_batch_id())
+  {
+    Some(batch_id) => format!("{} (command batch {})", payload, batch_id),
+    None => payload,
+  }
+}
+
+impl fmt::Display for Conflict {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    writeln!(f, "conflict in {}: {}", self.entity, self.message)?;
+    for event in &self.ours {
+      writeln!(f, "  ours:   {}", event)?;
+    }
+    for event in &self.theirs {
+      writeln!(f, "  theirs: {}", event)?;
+    }
+    Ok(())
+  }
+}
+
+#[test]
+fn merges_divergent_streams_and_reports_conflicts() {
+  use crate::aggregate::OpticAggregate;
+
+  let base = crate::events_from_file("test-fixtures/uncompacted-spec.json").unwrap();
+  let appended = |events: &str| -> Vec<OpticEvent> {
+    let mut stream = base.clone();
+    stream.extend(serde_json::from_str::<Vec<OpticEvent>>(events).unwrap());
+    stream
+  };
+  let ours = appended(
+    r#"[
+      {"GitStateSet":{"branchName":"ours","commitId":"1","eventContext":null}},
+      {"FieldRenamed":{"fieldId":"EQSZqM_12","name":"nation","eventContext":null}},
+      {"FieldRemoved":{"fieldId":"EQSZqM_14","eventContext":null}},
+      {"ContributionAdded":{"id":"EQSZqM_11","key":"description","value":"same","eventContext":null}}
+    ]"#,
+  );
+  let theirs = appended(
+    r#"[
+      {"GitStateSet":{"branchName":"theirs","commitId":"2","eventContext":null}},
+      {"FieldRenamed":{"fieldId":"EQSZqM_12","name":"land","eventContext":null}},
+      {"FieldRenamed":{"fieldId":"EQSZqM_14","name":"latitude","eventContext":null}},
+      {"ContributionAdded":{"id":"EQSZqM_11","key":"description","value":"same","eventContext":null}},
+      {"ContributionAdded":{"id":"EQSZqM_11","key":"purpose","value":"theirs","eventContext":null}}
+    ]"#,
+  );
+
+  let merged = merge(Some(&base), &ours, &theirs).unwrap();
+  assert_eq!(merged.our_branch.as_deref(), Some("ours"));
+  assert_eq!(merged.their_branch.as_deref(), Some("theirs"));
+  let conflicts: Vec<(&str, &str)> = merged
+    .conflicts
+    .iter()
+    .map(|conflict| (conflict.entity.as_str(), conflict.message.as_str()))
+    .collect();
+  assert_eq!(
+    conflicts,
+    vec![
+      ("field EQSZqM_12", "name changed differently"),
+      ("field EQSZqM_14", "removed by us but modified by them"),
+    ]
+  );
+
+  // their branch and contribution are merged, their conflicting renames and duplicates are not
+  assert_eq!(merged.events.len(), ours.len() + 2);
+  let aggregate = OpticAggregate::fold(merged.events);
+  let state = aggregate.get_state();
+  assert_eq!(
+    state.rfc.contribution("EQSZqM_11", "purpose"),
+    Some("theirs")
+  );
+
+  // without a base, the common prefix is the ancestor
+  let without_base = merge(None, &ours, &theirs).unwrap();
+  assert_eq!(without_base.conflicts.len(), 2);
+}
+
+#[test]
+fn events_depending_on_left_out_events_are_left_out() {
+  use crate::aggregate::OpticAggregate;
+
+  let base = crate::events_from_file("test-fixtures/uncompacted-spec.json").unwrap();
+  let appended = |events: &str| -> Vec<OpticEvent> {
+    let mut stream = base.clone();
+    stream.extend(serde_json::from_str::<Vec<OpticEvent>>(events).unwrap());
+    stream
+  };
+  let ours = appended(r#"[{"ShapeRemoved":{"shapeId":"EQSZqM_11","eventContext":null}}]"#);
+  let theirs = appended(
+    r#"[
+      {"BatchCommitStarted":{"batchId":"shape","commitMessage":"","eventContext":null}},
+      {"ShapeAdded":{"shapeId":"new_s","baseShapeId":"$string","parameters":{"DynamicParameterList":{"shapeParameterIds":[]}},"name":"","eventContext":null}},
+      {"BatchCommitEnded":{"batchId":"shape","eventContext":null}},
+      {"BatchCommitStarted":{"batchId":"field","commitMessage":"","eventContext":null}},
+      {"FieldAdded":{"fieldId":"new_f","shapeId":"EQSZqM_11","name":"new","shapeDescriptor":{"FieldShapeFromShape":{"fieldId":"new_f","shapeId":"new_s"}},"eventContext":null}},
+      {"FieldRenamed":{"fieldId":"new_f","name":"newer","eventContext":null}},
+      {"FieldShapeSet":{"shapeDescriptor":{"FieldShapeFromShape":{"fieldId":"new_f","shapeId":"$number"}},"eventContext":null}},
+      {"BatchCommitEnded":{"batchId":"field","eventContext":null}}
+    ]"#,
+  );
+
+  let merged = merge(Some(&base), &ours, &theirs).unwrap();
+  assert_eq!(merged.conflicts.len(), 1);
+  assert_eq!(merged.conflicts[0].entity, "shape EQSZqM_11");
+  // the added field, and the changes to it
+  assert_eq!(merged.conflicts[0].theirs.len(), 3);
+  // the batch of the added shape, but not the batch left without events
+  assert_eq!(merged.events.len(), ours.len() + 3);
+  assert!(!merged
+    .events
+    .iter()
+    .any(|event| batch_started(event) == Some("field")));
+  assert!(crate::events::batches::validate_batches(&merged.events[ours.len()..]).is_empty());
+
+  let aggregate = OpticAggregate::fold(merged.events);
+  let state = aggregate.get_state();
+  assert!(state.shape.shape(ShapeId::from("new_s")).is_some());
+  assert!(state.shape.field(FieldId::from("new_f")).is_none());
+}
@@ -0,0 +1,695 @@
+use serde_json::Value;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::aggregate::OpticState;
+use crate::projections::endpoints::endpoints;
+use crate::projections::examples::response_examples;
+use crate::state::http::{HttpMethod, StatusCode};
+use crate::state::requests::{live_bodies, PathComponentId, ROOT_PATH_ID};
+
+// The header a client sets to get another documented response than the default one
+pub const STATUS_HEADER: &str = "x-mock-status";
+
+// connections served at once, beyond which they're turned away
+const MAX_CONNECTIONS: usize = 64;
+// bytes of the request line and headers of a request
+const MAX_HEAD_LENGTH: u64 = 16 * 1024;
+// bytes of the body of a request, which is read but not looked at
+const MAX_BODY_LENGTH: u64 = 1024 * 1024;
+
+// A stub backend for the documented endpoints. Requests are routed along the path components of
+// the spec, where a path parameter matches any segment, and answered with the documented response
+// of the lowest success status (or the one asked for with `X-Mock-Status`) and an example of its
+// body. Everything is generated up front, so requests can be served from any thread.
+pub struct MockServer {
+  path_components: Vec<MockPathComponent>,
+  endpoints: Vec<MockEndpoint>,
+}
+
+struct MockPathComponent {
+  path_id: PathComponentId,
+  parent_path_id: PathComponentId,
+  name: String,
+  is_parameter: bool,
+}
+
+struct MockEndpoint {
+  path_id: PathComponentId,
+  http_method: HttpMethod,
+  path: String,
+  responses: Vec<MockResponse>,
+}
+
+struct MockResponse {
+  http_status_code: StatusCode,
+  // a documented response without a body is answered with an empty one
+  http_content_type: Option<String>,
+  example: Value,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct MockReply {
+  pub http_status_code: u16,
+  pub headers: Vec<(String, String)>,
+  pub body: String,
+}
+
+impl MockServer {
+  pub fn from_state(state: &OpticState, seed: u64) -> Self {
+    let path_components = state
+      .requests
+      .all_path_components()
+      .filter(|component| !component.is_removed)
+      .map(|component| MockPathComponent {
+        path_id: component.path_id,
+        parent_path_id: component.descriptor.parent_path_id(),
+        name: String::from(component.descriptor.name()),
+        is_parameter: component.descriptor.is_parameter(),
+      })
+      .collect();
+
+    let endpoints = endpoints(state)
+      .iter()
+      .map(|endpoint| {
+        let mut responses: Vec<MockResponse> = response_examples(state.shape, endpoint, seed)
+          .into_iter()
+          .map(|example| MockResponse {
+            http_status_code: example.http_status_code,
+            http_content_type: Some(String::from(example.http_content_type)),
+            example: example.example,
+          })
+          .collect();
+        for response in &endpoint.responses {
+          let descriptor = &response.response_descriptor;
+          if live_bodies(&descriptor.bodies).next().is_none() {
+            responses.push(MockResponse {
+              http_status_code: descriptor.http_status_code,
+              http_content_type: None,
+              example: Value::Null,
+            });
+          }
+        }
+        // stable, so the bodies of a status stay in their documented order
+        responses.sort_by_key(|response| response.http_status_code);
+
+        MockEndpoint {
+          path_id: endpoint.path_id,
+          http_method: endpoint.http_method.clone(),
+          path: endpoint.path.clone(),
+          responses,
+        }
+      })
+      .collect();
+
+    MockServer {
+      path_components,
+      endpoints,
+    }
+  }
+
+  // Routing
+  // -------
+
+  // The path components a request path could be, from the most literal to the most parameterized:
+  // at every segment, a basic component named like the segment goes before any path parameter
+  fn matching_path_ids(
+    &self,
+    parent_path_id: PathComponentId,
+    segments: &[&str],
+  ) -> Vec<PathComponentId> {
+    let (segment, rest) = match segments.split_first() {
+      Some(split) => split,
+      None => return vec![parent_path_id],
+    };
+    let children = self
+      .path_components
+      .iter()
+      .filter(|component| component.parent_path_id == parent_path_id);
+    let (parameters, basics): (Vec<_>, Vec<_>) =
+      children.partition(|component| component.is_parameter);
+
+    basics
+      .into_iter()
+      .filter(|component| component.name == *segment)
+      .chain(parameters)
+      .flat_map(|component| self.matching_path_ids(component.path_id, rest))
+      .collect()
+  }
+
+  // Replies to a request, given its method, its target (like `/users/1?page=2`) and its headers
+  pub fn reply(&self, method: &str, target: &str, headers: &[(String, String)]) -> MockReply {
+    let path = target.split(['?', '#']).next().unwrap_or_default();
+    let segments: Vec<&str> = path
+      .split('/')
+      .filter(|segment| !segment.is_empty())
+      .collect();
+    let http_method: HttpMethod = match method.parse() {
+      Ok(http_method) => http_method,
+      Err(message) => return MockReply::text(400, message),
+    };
+
+    // the request is for the first path documenting its method, so a path documented for other
+    // methods only doesn't hide a more parameterized one
+    let path_endpoints: Vec<&MockEndpoint> = self
+      .matching_path_ids(PathComponentId::from(ROOT_PATH_ID), &segments)
+      .into_iter()
+      .flat_map(|path_id| {
+        self
+          .endpoints
+          .iter()
+          .filter(move |endpoint| endpoint.path_id == path_id)
+      })
+      .collect();
+    if path_endpoints.is_empty() {
+      return MockReply::text(404, format!("no endpoint is documented for {}", path));
+    }
+
+    let endpoint = match path_endpoints
+      .iter()
+      .find(|endpoint| endpoint.http_method == http_method)
+    {
+      Some(endpoint) => endpoint,
+      // browsers ask before cross-origin requests, which the spec rarely documents
+      None if http_method == HttpMethod::Options => return MockReply::preflight(headers),
+      None => {
+        let mut allowed: Vec<&str> = vec![];
+        for endpoint in &path_endpoints {
+          if !allowed.contains(&endpoint.http_method.as_str()) {
+            allowed.push(endpoint.http_method.as_str());
+          }
+        }
+        let mut reply = MockReply::text(
+          405,
+          format!("{} is not documented for {}", http_method, path),
+        );
+        reply
+          .headers
+          .push((String::from("Allow"), allowed.join(", ")));
+        return reply;
+      }
+    };
+
+    let responses: Vec<&MockResponse> = match header(headers, STATUS_HEADER) {
+      Some(status) => {
+        let status: u16 = match status.trim().parse() {
+          Ok(status) => status,
+          Err(_) => return MockReply::text(400, format!("{} needs a status code", STATUS_HEADER)),
+        };
+        endpoint
+          .responses
+          .iter()
+          .filter(|response| response.http_status_code.as_u16() == status)
+          .collect()
+      }
+      None => {
+        let default_status = endpoint
+          .responses
+          .iter()
+          .map(|response| response.http_status_code)
+          .find(StatusCode::is_success)
+          .or_else(|| {
+            endpoint
+              .responses
+              .first()
+              .map(|response| response.http_status_code)
+          });
+        endpoint
+          .responses
+          .iter()
+          .filter(|response| Some(response.http_status_code) == default_status)
+          .collect()
+      }
+    };
+
+    // of the bodies of a status, the first one the client prefers, or else the first one
+    let media_ranges = media_ranges(header(headers, "accept").unwrap_or("*/*"));
+    let mut response = responses.first();
+    let mut best_quality = 0.0;
+    for candidate in &responses {
+      let quality = candidate
+        .http_content_type
+        .as_deref()
+        .map_or(0.0, |http_content_type| {
+          quality(&media_ranges, http_content_type)
+        });
+      if quality > best_quality {
+        response = Some(candidate);
+        best_quality = quality;
+      }
+    }
+
+    match response {
+      Some(response) => MockReply::example(response),
+      None => MockReply::text(
+        501,
+        format!(
+          "no {} response is documented for {} {}",
+          header(headers, STATUS_HEADER).unwrap_or_default().trim(),
+          endpoint.http_method,
+          endpoint.path
+        ),
+      ),
+    }
+  }
+
+  // Serving
+  // -------
+
+  // Serves every connection on its own thread, answering one request per connection. Beyond
+  // `MAX_CONNECTIONS` at once, connections are answered with a 503 instead. Verbosely, every
+  // request is printed with the status it was answered with.
+  pub fn serve(self, listener: TcpListener, verbose: bool) -> io::Result<()> {
+    let server = Arc::new(self);
+    let connections = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+      let stream = stream?;
+      if connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+        connections.fetch_sub(1, Ordering::SeqCst);
+        let reply = MockReply::text(503, String::from("too many connections"));
+        if let Err(err) = reply.write_to(&mut &stream) {
+          eprintln!("error: request could not be served: {}", err);
+        }
+        continue;
+      }
+
+      let server = Arc::clone(&server);
+      let connection = Connection(Arc::clone(&connections));
+      thread::spawn(move || {
+        if let Err(err) = server.handle(stream, verbose) {
+          eprintln!("error: request could not be served: {}", err);
+        }
+        drop(connection);
+      });
+    }
+    Ok(())
+  }
+
+  fn handle(&self, stream: TcpStream, verbose: bool) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+    let mut reader = BufReader::new(&stream);
+    let mut head = reader.by_ref().take(MAX_HEAD_LENGTH);
+
+    let mut request_line = String::new();
+    head.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = match (parts.next(), parts.next()) {
+      (Some(method), Some(target)) => (method, target),
+      // a connection that was closed, or opened and never used
+      _ => return Ok(()),
+    };
+
+    let mut headers = vec![];
+    let mut is_complete = false;
+    loop {
+      let mut line = String::new();
+      if head.read_line(&mut line)? == 0 {
+        break;
+      }
+      let line = line.trim_end();
+      if line.is_empty() {
+        is_complete = true;
+        break;
+      }
+      if let Some((name, value)) = line.split_once(':') {
+        headers.push((String::from(name.trim()), String::from(value.trim())));
+      }
+    }
+    if !is_complete && head.limit() == 0 {
+      let reply = MockReply::text(
+        431,
+        format!(
+          "requests are limited to {} bytes of headers",
+          MAX_HEAD_LENGTH
+        ),
+      );
+      return reply.write_to(&mut &stream);
+    }
+
+    // the body isn't looked at, but is read so the client isn't cut off while sending it, unless
+    // it's too long to bother
+    let content_length: u64 = header(&headers, "content-length")
+      .and_then(|length| length.parse().ok())
+      .unwrap_or_default();
+    let reply = if content_length > MAX_BODY_LENGTH {
+      MockReply::text(
+        413,
+        format!("requests are limited to {} bytes of body", MAX_BODY_LENGTH),
+      )
+    } else {
+      io::copy(&mut reader.by_ref().take(content_length), &mut io::sink())?;
+      self.reply(method, target, &headers)
+    };
+    if verbose {
+      println!("{} {} {}", method, target, reply.http_status_code);
+    }
+    reply.write_to(&mut &stream)
+  }
+}
+
+// counts a connection as served until it's dropped, even when serving it panics
+struct Connection(Arc<AtomicUsize>);
+
+impl Drop for Connection {
+  fn drop(&mut self) {
+    self.0.fetch_sub(1, Ordering::SeqCst);
+  }
+}
+
+fn header<'h>(headers: &'h [(String, String)], name: &str) -> Option<&'h str> {
+  headers
+    .iter()
+    .find(|(header_name, _)| header_name.eq_ignore_ascii_case(name))
+    .map(|(_, value)| value.as_str())
+}
+
+// The media ranges of an `Accept` header, like `text/*;q=0.5`, lowercased and with their quality.
+// Ranges with a quality that isn't a number are left out.
+fn media_ranges(accept: &str) -> Vec<(String, f32)> {
+  accept
+    .split(',')
+    .filter_map(|media_range| {
+      let mut parameters = media_range.split(';');
+      let range = parameters.next()?.trim().to_ascii_lowercase();
+      let mut quality = 1.0;
+      for parameter in parameters {
+        if let Some((name, value)) = parameter.split_once('=') {
+          if name.trim().eq_ignore_ascii_case("q") {
+            quality = value.trim().parse().ok()?;
+          }
+        }
+      }
+      Some((range, quality))
+    })
+    .filter(|(range, _)| range.contains('/'))
+    .collect()
+}
+
+// The quality of a content type by the most specific media range it matches, where 0 means it's
+// not acceptable
+fn quality(media_ranges: &[(String, f32)], http_content_type: &str) -> f32 {
+  let media_type = http_content_type
+    .split(';')
+    .next()
+    .unwrap_or_default()
+    .trim()
+    .to_ascii_lowercase();
+  let main_type = media_type.split('/').next().unwrap_or_default();
+  media_ranges
+    .iter()
+    .filter_map(|(range, quality)| {
+      let specificity = match range.split_once('/') {
+        _ if *range == media_type => 2,
+        Some((range_type, "*")) if range_type == main_type => 1,
+        Some(("*", "*")) => 0,
+        _ => return None,
+      };
+      Some((specificity, *quality))
+    })
+    .max_by_key(|(specificity, _)| *specificity)
+    .map_or(0.0, |(_, quality)| quality)
+}
+
+impl MockReply {
+  fn example(response: &MockResponse) -> Self {
+    let mut reply = MockReply {
+      http_status_code: response.http_status_code.as_u16(),
+      headers: cors_headers(),
+      body: String::new(),
+    };
+    if let Some(http_content_type) = &response.http_content_type {
+      reply.body = serde_json::to_string_pretty(&response.example).unwrap_or_default() + "\n";
+      reply
+        .headers
+        .push((String::from("Content-Type"), http_content_type.clone()));
+    }
+    reply
+  }
+
+  fn text(http_status_code: u16, message: String) -> Self {
+    let mut headers = cors_headers();
+    headers.push((String::from("Content-Type"), String::from("text/plain")));
+    MockReply {
+      http_status_code,
+      headers,
+      body: message + "\n",
+    }
+  }
+
+  fn preflight(headers: &[(String, String)]) -> Self {
+    let mut reply = MockReply {
+      http_status_code: 204,
+      headers: cors_headers(),
+      body: String::new(),
+    };
+    reply.headers.push((
+      String::from("Access-Control-Allow-Methods"),
+      String::from(header(headers, "access-control-request-method").unwrap_or("*")),
+    ));
+    reply.headers.push((
+      String::from("Access-Control-Allow-Headers"),
+      String::from(header(headers, "access-control-request-headers").unwrap_or("*")),
+    ));
+    reply
+  }
+
+  fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+    write!(
+      writer,
+      "HTTP/1.1 {} {}\r\n",
+      self.http_status_code,
+      reason(self.http_status_code)
+    )?;
+    for (name, value) in &self.headers {
+      write!(writer, "{}: {}\r\n", name, value)?;
+    }
+    write!(writer, "Content-Length: {}\r\n", self.body.len())?;
+    write!(writer, "Connection: close\r\n\r\n")?;
+    writer.write_all(self.body.as_bytes())?;
+    writer.flush()
+  }
+}
+
+// so a frontend served from another origin can call the mock
+fn cors_headers() -> Vec<(String, String)> {
+  vec![(
+    String::from("Access-Control-Allow-Origin"),
+    String::from("*"),
+  )]
+}
+
+fn reason(http_status_code: u16) -> &'static str {
+  match http_status_code {
+    200 => "OK",
+    201 => "Created",
+    202 => "Accepted",
+    204 => "No Content",
+    301 => "Moved Permanently",
+    302 => "Found",
+    304 => "Not Modified",
+    400 => "Bad Request",
+    401 => "Unauthorized",
+    403 => "Forbidden",
+    404 => "Not Found",
+    405 => "Method Not Allowed",
+    409 => "Conflict",
+    422 => "Unprocessable Entity",
+    413 => "Payload Too Large",
+    431 => "Request Header Fields Too Large",
+    500 => "Internal Server Error",
+    501 => "Not Implemented",
+    503 => "Service Unavailable",
+    // the status line needs a reason, but clients don't read it
+    _ => "Mock",
+  }
+}
+
+#[test]
+fn replies_with_examples_of_documented_responses() {
+  use crate::aggregate::OpticAggregate;
+
+  let aggregate =
+    OpticAggregate::fold(crate::events_from_file("test-fixtures/uncompacted-spec.json").unwrap());
+  let server = MockServer::from_state(&aggregate.get_state(), 0);
+  let content_type = |reply: &MockReply| header(&reply.headers, "content-type").map(String::from);
+
+  // path parameters match any segment, with basic path components going first
+  let reply = server.reply("GET", "/api/f1/2020?limit=1", &[]);
+  assert_eq!(reply.http_status_code, 200);
+  assert_eq!(
+    content_type(&reply).as_deref(),
+    Some("application/json; charset=utf-8")
+  );
+  let body: Value = serde_json::from_str(&reply.body).unwrap();
+  assert!(body["MRData"]["RaceTable"]["Races"].is_array());
+  assert_eq!(
+    server.reply("GET", "/api/f1/2020/5/", &[]).http_status_code,
+    200
+  );
+  assert_eq!(
+    server
+      .reply("GET", "/api/f1/2020/drivers/hamilton", &[])
+      .http_status_code,
+    200
+  );
+  // the drivers of a season aren't documented, but they could be a round
+  assert_eq!(
+    server
+      .reply("GET", "/api/f1/2020/drivers", &[])
+      .http_status_code,
+    200
+  );
+
+  // the lowest success status is the default, and others can be asked for
+  let status_header = |status: &str| vec![(String::from("X-Mock-Status"), String::from(status))];
+  assert_eq!(
+    server
+      .reply("POST", "/following/drivers", &[])
+      .http_status_code,
+    201
+  );
+  let reply = server.reply("POST", "/following/drivers", &status_header("400"));
+  assert_eq!(reply.http_status_code, 400);
+  assert_eq!(content_type(&reply).as_deref(), Some("application/json"));
+  let reply = server.reply("POST", "/following/drivers", &status_header("418"));
+  assert_eq!(reply.http_status_code, 501);
+  assert_eq!(
+    server
+      .reply("POST", "/following/drivers", &status_header("x"))
+      .http_status_code,
+    400
+  );
+
+  // paths and methods that aren't documented
+  assert_eq!(
+    server.reply("GET", "/api/f2/2020", &[]).http_status_code,
+    404
+  );
+  let reply = server.reply("DELETE", "/following/drivers", &[]);
+  assert_eq!(reply.http_status_code, 405);
+  assert_eq!(header(&reply.headers, "allow"), Some("POST"));
+  assert_eq!(
+    server
+      .reply("OPTIONS", "/following/drivers", &[])
+      .http_status_code,
+    204
+  );
+
+  // over HTTP
+  let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+  let address = listener.local_addr().unwrap();
+  thread::spawn(move || server.serve(listener, false));
+  let mut stream = TcpStream::connect(address).unwrap();
+  stream
+    .write_all(b"POST /following/drivers HTTP/1.1\r\nHost: localhost\r\nX-Mock-Status: 400\r\nContent-Length: 2\r\n\r\n{}")
+    .unwrap();
+  let mut response = String::new();
+  stream.read_to_string(&mut response).unwrap();
+  assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+  assert!(response.contains("\r\nContent-Type: application/json\r\n"));
+  let body = response.split("\r\n\r\n").nth(1).unwrap();
+  assert!(serde_json::from_str::<Value>(body).is_ok());
+
+  // requests with too much of a head aren't read any further (here, it's all sent, so the server
+  // doesn't close the connection on unread bytes)
+  let mut stream = TcpStream::connect(address).unwrap();
+  let request_line = "GET /api/f1/2020 HTTP/1.1\r\nX-Long: ";
+  let head = request_line.to_string() + &"a".repeat(MAX_HEAD_LENGTH as usize - request_line.len());
+  stream.write_all(head.as_bytes()).unwrap();
+  let mut response = String::new();
+  stream.read_to_string(&mut response).unwrap();
+  assert!(response.starts_with("HTTP/1.1 431 Request Header Fields Too Large\r\n"));
+
+  // nor are requests with too much of a body
+  let mut stream = TcpStream::connect(address).unwrap();
+  let head = format!(
+    "POST /following/drivers HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+    MAX_BODY_LENGTH + 1
+  );
+  stream.write_all(head.as_bytes()).unwrap();
+  let mut response = String::new();
+  stream.read_to_string(&mut response).unwrap();
+  assert!(response.starts_with("HTTP/1.1 413 Payload Too Large\r\n"));
+}
+
+#[test]
+fn routes_to_the_first_path_documenting_the_method() {
+  use crate::aggregate::OpticAggregate;
+  use crate::events::OpticEvent;
+
+  // POST /users/me and GET /users/{id}
+  let events: Vec<OpticEvent> = serde_json::from_value(serde_json::json!([
+    {"PathComponentAdded": {"pathId": "users", "parentPathId": "root", "name": "users", "eventContext": null}},
+    {"PathComponentAdded": {"pathId": "me", "parentPathId": "users", "name": "me", "eventContext": null}},
+    {"PathParameterAdded": {"pathId": "user", "parentPathId": "users", "name": "id", "eventContext": null}},
+    {"ResponseAddedByPathAndMethod": {"responseId": "update_me", "pathId": "me", "httpMethod": "POST", "httpStatusCode": 204, "eventContext": null}},
+    {"ResponseAddedByPathAndMethod": {"responseId": "get_user", "pathId": "user", "httpMethod": "GET", "httpStatusCode": 200, "eventContext": null}},
+  ]))
+  .unwrap();
+  let aggregate = OpticAggregate::fold(events);
+  let server = MockServer::from_state(&aggregate.get_state(), 0);
+
+  assert_eq!(server.reply("GET", "/users/me", &[]).http_status_code, 200);
+  assert_eq!(server.reply("POST", "/users/me", &[]).http_status_code, 204);
+  assert_eq!(server.reply("GET", "/users/1", &[]).http_status_code, 200);
+  // the methods of every path it could be are allowed
+  let reply = server.reply("DELETE", "/users/me", &[]);
+  assert_eq!(reply.http_status_code, 405);
+  assert_eq!(header(&reply.headers, "allow"), Some("POST, GET"));
+  let reply = server.reply("POST", "/users/1", &[]);
+  assert_eq!(reply.http_status_code, 405);
+  assert_eq!(header(&reply.headers, "allow"), Some("GET"));
+}
+
+#[test]
+fn serves_the_body_the_client_prefers() {
+  use crate::aggregate::OpticAggregate;
+  use crate::events::OpticEvent;
+
+  let events: Vec<OpticEvent> = serde_json::from_value(serde_json::json!([
+    {"PathComponentAdded": {"pathId": "users", "parentPathId": "root", "name": "users", "eventContext": null}},
+    {"ResponseAddedByPathAndMethod": {"responseId": "users", "pathId": "users", "httpMethod": "GET", "httpStatusCode": 200, "eventContext": null}},
+    {"ResponseBodySet": {"responseId": "users", "bodyDescriptor": {"httpContentType": "application/json", "shapeId": "$string", "isRemoved": false}, "eventContext": null}},
+    {"ResponseBodySet": {"responseId": "users", "bodyDescriptor": {"httpContentType": "text/csv; charset=utf-8", "shapeId": "$string", "isRemoved": false}, "eventContext": null}},
+  ]))
+  .unwrap();
+  let aggregate = OpticAggregate::fold(events);
+  let server = MockServer::from_state(&aggregate.get_state(), 0);
+  let content_type = |accept: &str| {
+    let reply = server.reply(
+      "GET",
+      "/users",
+      &[(String::from("Accept"), String::from(accept))],
+    );
+    header(&reply.headers, "content-type").map(String::from)
+  };
+
+  assert_eq!(content_type("*/*").as_deref(), Some("application/json"));
+  assert_eq!(
+    content_type("Text/CSV").as_deref(),
+    Some("text/csv; charset=utf-8")
+  );
+  assert_eq!(
+    content_type("application/json;q=0.5, text/*").as_deref(),
+    Some("text/csv; charset=utf-8")
+  );
+  // the most specific range decides, so text/csv isn't acceptable here
+  assert_eq!(
+    content_type("text/*, text/csv;q=0, application/*;q=0.1").as_deref(),
+    Some("application/json")
+  );
+  // not a substring match
+  assert_eq!(
+    content_type("application/json-seq, text/csv;q=0.2").as_deref(),
+    Some("text/csv; charset=utf-8")
+  );
+  // nothing acceptable still gets the first body
+  assert_eq!(
+    content_type("image/png").as_deref(),
+    Some("application/json")
+  );
+}
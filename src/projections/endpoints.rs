@@ -0,0 +1,171 @@
+use crate::aggregate::OpticState;
+use crate::state::http::HttpMethod;
+use crate::state::requests::{
+  HttpRequest, HttpRequestParameter, HttpResponse, PathComponentDescriptor, PathComponentId,
+};
+use crate::state::shape::ShapeId;
+
+// An endpoint is every request, parameter and response documented for one path and method
+pub struct Endpoint<'a> {
+  pub path_id: PathComponentId,
+  pub http_method: &'a HttpMethod,
+  pub path: String,
+  pub purpose: Option<&'a str>,
+  pub requests: Vec<&'a HttpRequest>,
+  pub parameters: Vec<&'a HttpRequestParameter>,
+  pub responses: Vec<&'a HttpResponse>,
+}
+
+pub struct PathParameter<'a> {
+  pub name: &'a str,
+  pub shape_id: Option<ShapeId>,
+}
+
+impl<'a> Endpoint<'a> {
+  // the id contributions (like `purpose`) for this endpoint are recorded against
+  pub fn contribution_id(&self) -> String {
+    format!("{}.{}", self.path_id, self.http_method)
+  }
+}
+
+// All endpoints that have a request or response, in the order they were first documented. Removed
+// requests, parameters and responses are left out.
+pub fn endpoints<'a>(state: &OpticState<'a>) -> Vec<Endpoint<'a>> {
+  let requests_state = state.requests;
+  let mut endpoints: Vec<Endpoint<'a>> = vec![];
+
+  fn endpoint_for<'a, 'e>(
+    endpoints: &'e mut Vec<Endpoint<'a>>,
+    state: &OpticState<'a>,
+    path_id: PathComponentId,
+    http_method: &'a HttpMethod,
+  ) -> &'e mut Endpoint<'a> {
+    let position = endpoints
+      .iter()
+      .position(|endpoint| endpoint.path_id == path_id && endpoint.http_method == http_method);
+    let index = match position {
+      Some(index) => index,
+      None => {
+        let mut endpoint = Endpoint {
+          path_id,
+          http_method,
+          path: state.requests.absolute_path(path_id),
+          purpose: None,
+          requests: vec![],
+          parameters: vec![],
+          responses: vec![],
+        };
+        endpoint.purpose = state
+          .rfc
+          .contribution(&endpoint.contribution_id(), "purpose");
+        endpoints.push(endpoint);
+        endpoints.len() - 1
+      }
+    };
+    &mut endpoints[index]
+  }
+
+  for request in requests_state.all_requests().filter(|r| !r.is_removed) {
+    let descriptor = &request.request_descriptor;
+    endpoint_for(
+      &mut endpoints,
+      state,
+      descriptor.path_component_id,
+      &descriptor.http_method,
+    )
+    .requests
+    .push(request);
+  }
+
+  for response in requests_state.all_responses().filter(|r| !r.is_removed) {
+    let descriptor = &response.response_descriptor;
+    endpoint_for(
+      &mut endpoints,
+      state,
+      descriptor.path_id,
+      &descriptor.http_method,
+    )
+    .responses
+    .push(response);
+  }
+
+  for parameter in requests_state
+    .all_request_parameters()
+    .filter(|p| !p.is_removed)
+  {
+    let descriptor = &parameter.request_parameter_descriptor;
+    let position = endpoints.iter().position(|endpoint| {
+      endpoint.path_id == descriptor.path_id && endpoint.http_method == &descriptor.http_method
+    });
+    if let Some(index) = position {
+      endpoints[index].parameters.push(parameter);
+    }
+  }
+
+  endpoints
+}
+
+// The path parameters of an endpoint's path, from the root down
+pub fn path_parameters<'a>(
+  state: &OpticState<'a>,
+  path_id: PathComponentId,
+) -> Vec<PathParameter<'a>> {
+  let mut parameters = vec![];
+  let mut current = state.requests.path_component(path_id);
+  while let Some(component) = current {
+    if let PathComponentDescriptor::Parameterized(descriptor) = &component.descriptor {
+      parameters.push(PathParameter {
+        name: &descriptor.name,
+        shape_id: descriptor
+          .shape_descriptor
+          .shaped()
+          .map(|shaped| shaped.shape_id),
+      });
+    }
+    current = state
+      .requests
+      .path_component(component.descriptor.parent_path_id());
+  }
+  parameters.reverse();
+  parameters
+}
+
+#[test]
+fn responses_can_be_selected_by_method_and_status_class() {
+  use crate::aggregate::{Aggregate, OpticAggregate};
+  use crate::state::http::StatusClass;
+
+  let mut aggregate =
+    OpticAggregate::fold(crate::events_from_file("test-fixtures/uncompacted-spec.json").unwrap());
+  let client_errors = |aggregate: &OpticAggregate, http_method: HttpMethod| -> Vec<String> {
+    endpoints(&aggregate.get_state())
+      .iter()
+      .filter(|endpoint| endpoint.http_method == &http_method)
+      .flat_map(|endpoint| endpoint.responses.iter())
+      .filter(|response| {
+        response.response_descriptor.http_status_code.class() == StatusClass::ClientError
+      })
+      .map(|response| response.response_id.to_string())
+      .collect()
+  };
+  assert!(client_errors(&aggregate, HttpMethod::Get).is_empty());
+  assert_eq!(
+    client_errors(&aggregate, HttpMethod::Post),
+    vec!["response_ziGFJv9b29"]
+  );
+
+  aggregate.apply(
+    serde_json::from_value::<crate::events::OpticEvent>(serde_json::json!({
+      "ResponseStatusCodeSet": {
+        "responseId": "response_nxWT5qcYhF",
+        "httpStatusCode": 404,
+        "eventContext": null
+      }
+    }))
+    .unwrap(),
+  );
+  assert_eq!(
+    client_errors(&aggregate, HttpMethod::Get),
+    vec!["response_nxWT5qcYhF"]
+  );
+}
@@ -0,0 +1,114 @@
+use indexmap::IndexMap;
+use std::collections::HashMap;
+
+use crate::state::shape::{
+  is_core_shape, FieldShapeDescriptor, ProviderDescriptor, ShapeId, ShapeParameterId, ShapeState,
+};
+
+// Shapes are structurally equivalent when they're built on equivalent base shapes, have the same
+// name and parameters, the same fields (by name, in order) of equivalent shapes, and equivalent
+// shapes bound to the same parameters. The structural key of a shape spells all that out, with
+// core shapes as themselves, so equivalent shapes are the ones with equal keys.
+struct StructuralKeys<'a> {
+  shapes: &'a ShapeState,
+  keys: HashMap<ShapeId, String>,
+  visiting: Vec<ShapeId>,
+}
+
+impl<'a> StructuralKeys<'a> {
+  fn key(&mut self, shape_id: ShapeId) -> String {
+    if is_core_shape(shape_id) {
+      return String::from(shape_id.as_str());
+    }
+    if let Some(key) = self.keys.get(&shape_id) {
+      return key.clone();
+    }
+    // recursive shapes can't be spelled out, so they're only ever equivalent to themselves
+    if self.visiting.contains(&shape_id) {
+      return format!("#{}", shape_id);
+    }
+    let shape = match self.shapes.shape(shape_id) {
+      Some(shape) => shape,
+      None => return format!("#{}", shape_id),
+    };
+
+    self.visiting.push(shape_id);
+    let descriptor = &shape.descriptor;
+    let mut key = format!(
+      "{}({:?}){}",
+      self.key(descriptor.base_shape_id),
+      descriptor.name,
+      serde_json::to_string(&descriptor.parameters).unwrap_or_default()
+    );
+    key.push_str(&self.bindings_key(&descriptor.bindings));
+    key.push('{');
+    for field in self.shapes.fields_of(shape_id) {
+      let field_shape_key = match &field.descriptor.shape_descriptor {
+        FieldShapeDescriptor::FieldShapeFromShape(from_shape) => self.key(from_shape.shape_id),
+        FieldShapeDescriptor::FieldShapeFromParameter(from_parameter) => {
+          format!("<{}>", from_parameter.shape_parameter_id)
+        }
+      };
+      key.push_str(&format!(
+        "{:?}:{}{},",
+        field.descriptor.name,
+        field_shape_key,
+        self.bindings_key(&field.descriptor.bindings)
+      ));
+    }
+    key.push('}');
+    self.visiting.pop();
+
+    // a key that depends on a shape still being visited is only valid within that visit
+    if !key.contains('#') {
+      self.keys.insert(shape_id, key.clone());
+    }
+    key
+  }
+
+  fn bindings_key(&mut self, bindings: &IndexMap<ShapeParameterId, ProviderDescriptor>) -> String {
+    let mut bindings: Vec<_> = bindings
+      .iter()
+      .map(|(parameter_id, provider)| {
+        let provider_key = match provider {
+          ProviderDescriptor::ShapeProvider(provider) => self.key(provider.shape_id),
+          ProviderDescriptor::ParameterProvider(provider) => {
+            format!("<{}>", provider.shape_parameter_id)
+          }
+          ProviderDescriptor::NoProvider(_) => String::from("_"),
+        };
+        format!("{}={}", parameter_id, provider_key)
+      })
+      .collect();
+    bindings.sort();
+    format!("[{}]", bindings.join(","))
+  }
+}
+
+// The shapes of the spec grouped by structure, in the order each structure first appears. Removed
+// shapes are left out.
+pub fn equivalence_classes(shapes: &ShapeState) -> Vec<Vec<ShapeId>> {
+  let mut structural_keys = StructuralKeys {
+    shapes,
+    keys: HashMap::new(),
+    visiting: vec![],
+  };
+  let mut classes: IndexMap<String, Vec<ShapeId>> = IndexMap::new();
+  for shape in shapes.all_shapes().filter(|shape| !shape.is_removed) {
+    let key = structural_keys.key(shape.shape_id);
+    classes.entry(key).or_default().push(shape.shape_id);
+  }
+  classes.into_iter().map(|(_, class)| class).collect()
+}
+
+// For every shape with an equivalent shape that was added before it, that first shape
+pub fn canonical_shape_ids(shapes: &ShapeState) -> HashMap<ShapeId, ShapeId> {
+  let mut canonical_shape_ids = HashMap::new();
+  for class in equivalence_classes(shapes) {
+    let canonical_shape_id = class[0];
+    for shape_id in class.into_iter().skip(1) {
+      canonical_shape_ids.insert(shape_id, canonical_shape_id);
+    }
+  }
+  canonical_shape_ids
+}
@@ -0,0 +1,292 @@
+use serde_json::{Map, Value};
+
+use super::endpoints::Endpoint;
+use crate::state::http::StatusCode;
+use crate::state::requests::{live_bodies, ResponseId};
+use crate::state::shape::{is_core_shape, ShapeId, ShapeParametersDescriptor, ShapeState};
+
+// Generates example JSON for shapes: objects with their fields in order, lists with one item, the
+// first option of a one-of, and placeholder values that look like what their field is named after.
+// The same seed always generates the same examples.
+pub struct ExampleGenerator<'a> {
+  shapes: &'a ShapeState,
+  random: Random,
+  optional_fields: bool,
+  nulls: bool,
+  resolving: Vec<ShapeId>,
+}
+
+pub struct ResponseExample<'a> {
+  pub response_id: ResponseId,
+  pub http_status_code: StatusCode,
+  pub http_content_type: &'a str,
+  pub example: Value,
+}
+
+impl<'a> ExampleGenerator<'a> {
+  pub fn new(shapes: &'a ShapeState, seed: u64) -> Self {
+    ExampleGenerator {
+      shapes,
+      random: Random::new(seed),
+      optional_fields: true,
+      nulls: false,
+      resolving: vec![],
+    }
+  }
+
+  // whether optional fields are part of examples [default: they are]
+  pub fn with_optional_fields(mut self, optional_fields: bool) -> Self {
+    self.optional_fields = optional_fields;
+    self
+  }
+
+  // whether nullable values are null instead of an example of their inner shape [default: not]
+  pub fn with_nulls(mut self, nulls: bool) -> Self {
+    self.nulls = nulls;
+    self
+  }
+
+  pub fn example_for(&mut self, shape_id: ShapeId) -> Value {
+    self.example_named(shape_id, "")
+  }
+
+  // examples are named after the field they're the value of, for placeholders that fit
+  fn example_named(&mut self, shape_id: ShapeId, name: &str) -> Value {
+    // a recursive shape ends where it starts over
+    if self.resolving.contains(&shape_id) {
+      return Value::Null;
+    }
+
+    self.resolving.push(shape_id);
+    let example = self.resolve(shape_id, name);
+    self.resolving.pop();
+    example
+  }
+
+  fn resolve(&mut self, shape_id: ShapeId, name: &str) -> Value {
+    let core_shape_id = self.shapes.core_shape_id(shape_id);
+    match core_shape_id.as_str() {
+      "$object" if !is_core_shape(shape_id) => {
+        let mut object = Map::new();
+        for field in self.shapes.resolved_fields_of(shape_id) {
+          let field_shape_id = match field.descriptor.shape_descriptor.shape_id() {
+            Some(field_shape_id) => field_shape_id,
+            None => continue,
+          };
+          let is_optional = self.shapes.core_shape_id(field_shape_id).as_str() == "$optional";
+          if is_optional && !self.optional_fields {
+            continue;
+          }
+          let field_name = &field.descriptor.name;
+          let example = self.example_named(field_shape_id, field_name);
+          object.insert(field_name.clone(), example);
+        }
+        Value::Object(object)
+      }
+      "$object" => Value::Object(Map::new()),
+      "$string" => Value::from(self.placeholder_string(name)),
+      "$number" => Value::from(self.random.below(1000)),
+      "$boolean" => Value::from(self.random.below(2) == 0),
+      "$list" => {
+        let item = self.bound_example(shape_id, "$listItem", name);
+        Value::Array(vec![item])
+      }
+      "$map" => {
+        let mut map = Map::new();
+        let key = self.placeholder_string("key");
+        map.insert(key, self.bound_example(shape_id, "$mapValue", name));
+        Value::Object(map)
+      }
+      "$nullable" if self.nulls => Value::Null,
+      "$nullable" => self.bound_example(shape_id, "$nullableInner", name),
+      "$optional" => self.bound_example(shape_id, "$optionalInner", name),
+      "$identifier" => self.bound_example(shape_id, "$identifierInner", name),
+      "$reference" => self.bound_example(shape_id, "$referenceInner", name),
+      "$oneOf" => match self.parameter_ids(shape_id).first() {
+        Some(parameter_id) => self.bound_example(shape_id, parameter_id, name),
+        None => Value::Null,
+      },
+      _ => Value::Null,
+    }
+  }
+
+  fn bound_example(&mut self, shape_id: ShapeId, parameter_id: &str, name: &str) -> Value {
+    match self.shapes.bound_shape_id(shape_id, parameter_id) {
+      Some(bound_shape_id) => self.example_named(bound_shape_id, name),
+      None => Value::Null,
+    }
+  }
+
+  fn placeholder_string(&mut self, name: &str) -> String {
+    let lowercase_name = name.to_lowercase();
+    let word = WORDS[self.random.below(WORDS.len() as u64) as usize];
+    let number = self.random.below(100);
+    if lowercase_name.contains("url") {
+      format!("https://example.com/{}/{}", word, number)
+    } else if lowercase_name.contains("email") {
+      format!("{}{}@example.com", word, number)
+    } else if lowercase_name.contains("date") {
+      format!(
+        "20{:02}-{:02}-{:02}",
+        number,
+        1 + self.random.below(12),
+        1 + self.random.below(28)
+      )
+    } else if lowercase_name.contains("time") {
+      format!(
+        "{:02}:{:02}:00Z",
+        self.random.below(24),
+        self.random.below(60)
+      )
+    } else if lowercase_name.ends_with("id") {
+      format!("{}_{:04x}", word, self.random.below(0x10000))
+    } else {
+      format!("{} {}", word, number)
+    }
+  }
+
+  fn parameter_ids(&self, shape_id: ShapeId) -> Vec<String> {
+    let parameters = self
+      .shapes
+      .shape(shape_id)
+      .map(|shape| &shape.descriptor.parameters);
+    match parameters {
+      Some(ShapeParametersDescriptor::StaticParameterList(list)) => &list.shape_parameter_ids,
+      Some(ShapeParametersDescriptor::DynamicParameterList(list)) => &list.shape_parameter_ids,
+      _ => return vec![],
+    }
+    .iter()
+    .map(|parameter_id| String::from(parameter_id.as_str()))
+    .collect()
+  }
+}
+
+const WORDS: &[&str] = &[
+  "alpha", "bravo", "charlie", "delta", "echo", "foxtrot", "golf", "hotel", "india", "juliett",
+  "kilo", "lima", "mike", "november", "oscar", "papa", "quebec", "romeo", "sierra", "tango",
+];
+
+// An example for every body of every response of an endpoint, each generated from the seed and its
+// response, so examples don't change when other responses do
+pub fn response_examples<'a>(
+  shapes: &ShapeState,
+  endpoint: &Endpoint<'a>,
+  seed: u64,
+) -> Vec<ResponseExample<'a>> {
+  let mut examples = vec![];
+  for response in &endpoint.responses {
+    let descriptor = &response.response_descriptor;
+    for body in live_bodies(&descriptor.bodies) {
+      let response_seed = seed ^ fnv1a(response.response_id.as_str());
+      let mut generator = ExampleGenerator::new(shapes, response_seed);
+      examples.push(ResponseExample {
+        response_id: response.response_id,
+        http_status_code: descriptor.http_status_code,
+        http_content_type: &body.http_content_type,
+        example: generator.example_for(body.shape_id),
+      });
+    }
+  }
+  examples
+}
+
+fn fnv1a(text: &str) -> u64 {
+  text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+    (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+  })
+}
+
+// xorshift64*, which is plenty for placeholders
+struct Random {
+  state: u64,
+}
+
+impl Random {
+  fn new(seed: u64) -> Self {
+    // a zero state would stay zero
+    Random { state: seed.max(1) }
+  }
+
+  fn below(&mut self, bound: u64) -> u64 {
+    self.state ^= self.state >> 12;
+    self.state ^= self.state << 25;
+    self.state ^= self.state >> 27;
+    self.state.wrapping_mul(0x2545_f491_4f6c_dd1d) % bound.max(1)
+  }
+}
+
+#[test]
+fn examples_follow_their_shapes_and_seed() {
+  use super::endpoints::endpoints;
+  use crate::aggregate::OpticAggregate;
+
+  let aggregate =
+    OpticAggregate::fold(crate::events_from_file("test-fixtures/uncompacted-spec.json").unwrap());
+  let state = aggregate.get_state();
+  let all_endpoints = endpoints(&state);
+  let season = all_endpoints
+    .iter()
+    .find(|endpoint| endpoint.path == "/api/f1/{season}")
+    .unwrap();
+
+  let examples = response_examples(state.shape, season, 7);
+  assert_eq!(examples.len(), 1);
+  let example = &examples[0].example;
+  assert_eq!(examples[0].http_status_code.as_u16(), 200);
+  // fields are in their order, and lists have one item
+  let race_table = &example["MRData"]["RaceTable"];
+  let race_fields: Vec<&String> = race_table["Races"][0].as_object().unwrap().keys().collect();
+  assert_eq!(
+    race_fields,
+    vec!["Circuit", "date", "raceName", "round", "season", "time", "url"]
+  );
+  assert_eq!(race_table["Races"].as_array().unwrap().len(), 1);
+  assert!(race_table["Races"][0]["url"]
+    .as_str()
+    .unwrap()
+    .starts_with("https://example.com/"));
+
+  // the same seed gives the same examples, and another seed others
+  assert_eq!(
+    response_examples(state.shape, season, 7)[0].example,
+    *example
+  );
+  assert_ne!(
+    response_examples(state.shape, season, 8)[0].example,
+    *example
+  );
+
+  // an example for every body of every response
+  let follow = all_endpoints
+    .iter()
+    .find(|endpoint| endpoint.path == "/following/drivers")
+    .unwrap();
+  let status_codes: Vec<u16> = response_examples(state.shape, follow, 7)
+    .iter()
+    .map(|example| example.http_status_code.as_u16())
+    .collect();
+  assert_eq!(status_codes, vec![201, 400]);
+
+  // optional fields can be left out, and nullable values be null
+  let events: Vec<crate::events::OpticEvent> = serde_json::from_value(serde_json::json!([
+    {"ShapeAdded": {"shapeId": "user", "baseShapeId": "$object", "parameters": {"DynamicParameterList": {"shapeParameterIds": []}}, "name": "User", "eventContext": null}},
+    {"ShapeAdded": {"shapeId": "maybe_string", "baseShapeId": "$optional", "parameters": {"DynamicParameterList": {"shapeParameterIds": []}}, "name": "", "eventContext": null}},
+    {"ShapeParameterShapeSet": {"shapeDescriptor": {"ProviderInShape": {"shapeId": "maybe_string", "providerDescriptor": {"ShapeProvider": {"shapeId": "$string"}}, "consumingParameterId": "$optionalInner"}}, "eventContext": null}},
+    {"ShapeAdded": {"shapeId": "null_user", "baseShapeId": "$nullable", "parameters": {"DynamicParameterList": {"shapeParameterIds": []}}, "name": "", "eventContext": null}},
+    {"ShapeParameterShapeSet": {"shapeDescriptor": {"ProviderInShape": {"shapeId": "null_user", "providerDescriptor": {"ShapeProvider": {"shapeId": "user"}}, "consumingParameterId": "$nullableInner"}}, "eventContext": null}},
+    {"FieldAdded": {"fieldId": "nickname", "shapeId": "user", "name": "nickname", "shapeDescriptor": {"FieldShapeFromShape": {"fieldId": "nickname", "shapeId": "maybe_string"}}, "eventContext": null}},
+    {"FieldAdded": {"fieldId": "mentor", "shapeId": "user", "name": "mentor", "shapeDescriptor": {"FieldShapeFromShape": {"fieldId": "mentor", "shapeId": "null_user"}}, "eventContext": null}},
+  ]))
+  .unwrap();
+  let aggregate = OpticAggregate::fold(events);
+  let shapes = aggregate.get_state().shape;
+  let user = ExampleGenerator::new(shapes, 1).example_for(ShapeId::from("user"));
+  assert!(user["nickname"].is_string());
+  // the mentor would be another user, where the recursion ends
+  assert!(user["mentor"].is_null());
+  let user = ExampleGenerator::new(shapes, 1)
+    .with_optional_fields(false)
+    .with_nulls(true)
+    .example_for(ShapeId::from("user"));
+  assert_eq!(user, serde_json::json!({ "mentor": null }));
+}
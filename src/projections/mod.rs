@@ -0,0 +1,5 @@
+pub mod endpoints;
+pub mod equivalence;
+pub mod examples;
+pub mod reachability;
+pub mod subscriptions;
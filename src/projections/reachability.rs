@@ -0,0 +1,119 @@
+use indexmap::IndexSet;
+
+use crate::aggregate::OpticState;
+use crate::state::requests::{live_bodies, PathComponentDescriptor};
+use crate::state::shape::{is_core_shape, ShapeId, ShapeState};
+
+// Shapes referenced by the endpoints: the request and response bodies and the request and path
+// parameters of entities that haven't been removed.
+pub fn root_shapes(state: &OpticState) -> IndexSet<ShapeId> {
+  let requests_state = state.requests;
+  let mut roots = IndexSet::new();
+
+  for request in requests_state.all_requests().filter(|r| !r.is_removed) {
+    let bodies = live_bodies(&request.request_descriptor.bodies);
+    roots.extend(bodies.map(|body| body.shape_id));
+  }
+  for response in requests_state.all_responses().filter(|r| !r.is_removed) {
+    let bodies = live_bodies(&response.response_descriptor.bodies);
+    roots.extend(bodies.map(|body| body.shape_id));
+  }
+  for parameter in requests_state
+    .all_request_parameters()
+    .filter(|p| !p.is_removed)
+  {
+    let descriptor = &parameter.request_parameter_descriptor.shape_descriptor;
+    roots.extend(descriptor.shaped().map(|shaped| shaped.shape_id));
+  }
+  for component in requests_state
+    .all_path_components()
+    .filter(|c| !c.is_removed)
+  {
+    if let PathComponentDescriptor::Parameterized(descriptor) = &component.descriptor {
+      let shape_descriptor = &descriptor.shape_descriptor;
+      roots.extend(shape_descriptor.shaped().map(|shaped| shaped.shape_id));
+    }
+  }
+
+  roots
+}
+
+// Every shape reachable from the root shapes through base shapes, fields and the shapes bound to
+// shape parameters, in the order they were reached. Core shapes are included when referenced.
+pub fn reachable_shapes(state: &OpticState) -> IndexSet<ShapeId> {
+  shapes_reachable_from(state.shape, root_shapes(state))
+}
+
+// Every shape reachable from the given shapes, themselves included
+pub fn shapes_reachable_from<I: IntoIterator<Item = ShapeId>>(
+  shapes: &ShapeState,
+  roots: I,
+) -> IndexSet<ShapeId> {
+  let mut reachable = IndexSet::new();
+  let mut pending: Vec<ShapeId> = roots.into_iter().collect();
+
+  while let Some(shape_id) = pending.pop() {
+    if !reachable.insert(shape_id) {
+      continue;
+    }
+    let shape = match shapes.shape(shape_id) {
+      Some(shape) => shape,
+      None => continue,
+    };
+
+    pending.push(shape.descriptor.base_shape_id);
+    pending.extend(
+      shape
+        .descriptor
+        .bindings
+        .values()
+        .filter_map(|provider| provider.shape_id()),
+    );
+    for field in shapes.fields_of(shape_id) {
+      pending.extend(field.descriptor.shape_descriptor.shape_id());
+      pending.extend(
+        field
+          .descriptor
+          .bindings
+          .values()
+          .filter_map(|provider| provider.shape_id()),
+      );
+    }
+  }
+
+  reachable
+}
+
+// Shapes in the spec that no endpoint refers to, directly or indirectly, in event order
+pub fn unreachable_shapes(state: &OpticState) -> Vec<ShapeId> {
+  let reachable = reachable_shapes(state);
+  state
+    .shape
+    .all_shapes()
+    .filter(|shape| !shape.is_removed && !is_core_shape(shape.shape_id))
+    .map(|shape| shape.shape_id)
+    .filter(|shape_id| !reachable.contains(shape_id))
+    .collect()
+}
+
+#[test]
+fn shapes_are_reachable_through_fields_and_bindings() {
+  use crate::aggregate::OpticAggregate;
+
+  let aggregate =
+    OpticAggregate::fold(crate::events_from_file("test-fixtures/uncompacted-spec.json").unwrap());
+  let state = aggregate.get_state();
+  let reachable = reachable_shapes(&state);
+
+  for field in state.shape.all_fields() {
+    if reachable.contains(&field.descriptor.shape_id) {
+      let field_shape_id = field.descriptor.shape_descriptor.shape_id().unwrap();
+      assert!(reachable.contains(&field_shape_id));
+    }
+  }
+  for shape_id in unreachable_shapes(&state) {
+    assert!(!reachable.contains(&shape_id));
+    assert!(!root_shapes(&state).contains(&shape_id));
+  }
+  assert!(reachable.len() > root_shapes(&state).len());
+}
@@ -0,0 +1,80 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::aggregate::OpticState;
+use crate::events::OpticEvent;
+
+// A read model kept up to date as events are applied to an aggregate it's subscribed to, instead
+// of being derived by refolding. Each event is shown to the projection with the state before it's
+// applied, then with the state after, so a projection can compare whatever it needs of both.
+pub trait Projection {
+  fn before_apply(&mut self, _event: &OpticEvent, _state: &OpticState) {}
+
+  fn after_apply(&mut self, event: &OpticEvent, state: &OpticState);
+}
+
+// A projection shared with its subscriber, which can read it while the aggregate updates it
+impl<P: Projection> Projection for Rc<RefCell<P>> {
+  fn before_apply(&mut self, event: &OpticEvent, state: &OpticState) {
+    self.borrow_mut().before_apply(event, state)
+  }
+
+  fn after_apply(&mut self, event: &OpticEvent, state: &OpticState) {
+    self.borrow_mut().after_apply(event, state)
+  }
+}
+
+#[test]
+fn subscribed_projections_follow_every_applied_event() {
+  use crate::aggregate::{Aggregate, OpticAggregate};
+  use crate::events::shape::ShapeEvent;
+  use crate::state::shape::ShapeId;
+  use indexmap::IndexMap;
+
+  // the names of shapes, and how many requests were added by events that added requests
+  #[derive(Default)]
+  struct ShapeNames {
+    names: IndexMap<ShapeId, String>,
+    requests_added: usize,
+    requests_before: usize,
+  }
+
+  impl Projection for ShapeNames {
+    fn before_apply(&mut self, _event: &OpticEvent, state: &OpticState) {
+      self.requests_before = state.requests.all_requests().count();
+    }
+
+    fn after_apply(&mut self, event: &OpticEvent, state: &OpticState) {
+      self.requests_added += state.requests.all_requests().count() - self.requests_before;
+      let shape_id = match event {
+        OpticEvent::ShapeEvent(ShapeEvent::ShapeAdded(e)) => e.shape_id,
+        OpticEvent::ShapeEvent(ShapeEvent::ShapeRenamed(e)) => e.shape_id,
+        _ => return,
+      };
+      let shape = state.shape.shape(shape_id).unwrap();
+      self.names.insert(shape_id, shape.descriptor.name.clone());
+    }
+  }
+
+  let projection = Rc::new(RefCell::new(ShapeNames::default()));
+  let mut aggregate = OpticAggregate::default();
+  aggregate.subscribe(Box::new(projection.clone()));
+  for event in crate::events_from_file("test-fixtures/uncompacted-spec.json").unwrap() {
+    aggregate.apply(event);
+  }
+
+  let projection = projection.borrow();
+  let state = aggregate.get_state();
+  assert_eq!(
+    projection.requests_added,
+    state.requests.all_requests().count()
+  );
+  let names: IndexMap<ShapeId, String> = state
+    .shape
+    .all_shapes()
+    .filter(|shape| projection.names.contains_key(&shape.shape_id))
+    .map(|shape| (shape.shape_id, shape.descriptor.name.clone()))
+    .collect();
+  assert_eq!(names.len(), projection.names.len());
+  assert_eq!(names, projection.names);
+}
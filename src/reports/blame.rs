@@ -0,0 +1,286 @@
+use cqrs_core::Event;
+use indexmap::IndexSet;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::aggregate::provenance::{changed_entities, ProvenanceState};
+use crate::aggregate::OpticState;
+use crate::events::OpticEvent;
+use crate::projections::endpoints::{endpoints, path_parameters};
+use crate::projections::reachability::shapes_reachable_from;
+use crate::state::http::HttpMethod;
+use crate::state::provenance::EntityId;
+use crate::state::requests::live_bodies;
+use crate::state::shape::{is_core_shape, ShapeId};
+
+// What to blame: an endpoint, like `GET /users/{userId}`, or a shape by its id
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlameTarget {
+  Endpoint(HttpMethod, String),
+  // not interned, as it's not read from a spec
+  Shape(String),
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlameReport {
+  pub subject: String,
+  pub events: Vec<BlamedEvent>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlamedEvent {
+  pub event_type: &'static str,
+  pub entity: String,
+  // the batch the event was committed in, if any
+  pub batch_id: Option<String>,
+  pub commit_message: Option<String>,
+  pub client_id: Option<String>,
+  pub created_at: Option<String>,
+}
+
+impl FromStr for BlameTarget {
+  type Err = String;
+
+  fn from_str(target: &str) -> Result<Self, Self::Err> {
+    match target.trim().split_once(' ') {
+      Some((http_method, path)) => Ok(BlameTarget::Endpoint(
+        http_method.parse()?,
+        String::from(path.trim()),
+      )),
+      None => Ok(BlameTarget::Shape(String::from(target.trim()))),
+    }
+  }
+}
+
+impl fmt::Display for BlameTarget {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      BlameTarget::Endpoint(http_method, path) => write!(f, "{} {}", http_method, path),
+      BlameTarget::Shape(shape_id) => write!(f, "shape {}", shape_id),
+    }
+  }
+}
+
+impl BlameReport {
+  // Every event that contributed to the current form of the target: for a shape, the events of
+  // the shape, its fields and the shapes they refer to, and for an endpoint also those of its
+  // path, requests, parameters and responses
+  pub fn for_target(
+    events: &[OpticEvent],
+    state: &OpticState,
+    target: &BlameTarget,
+  ) -> Result<Self, String> {
+    let entities = match target {
+      BlameTarget::Shape(shape_id) => {
+        let shape_id = ShapeId::existing(shape_id)
+          .filter(|shape_id| state.shape.shape(*shape_id).is_some())
+          .ok_or_else(|| format!("there is no shape {}", shape_id))?;
+        shape_entities(state, vec![shape_id])
+      }
+      BlameTarget::Endpoint(http_method, path) => endpoint_entities(state, http_method, path)
+        .ok_or_else(|| format!("there is no endpoint {}", target))?,
+    };
+
+    // events are attributed to batches the way the provenance of entities is
+    let mut blamed = vec![];
+    let mut provenance = ProvenanceState::default();
+    for event in events {
+      provenance.apply(event);
+
+      let entity = changed_entities(event)
+        .into_iter()
+        .map(|(entity_id, _)| entity_id)
+        .find(|entity_id| entities.contains(entity_id));
+      if let Some(entity) = entity {
+        let context = event.event_context();
+        let batch = provenance.current_batch();
+        blamed.push(BlamedEvent {
+          event_type: event.event_type(),
+          entity: entity.to_string(),
+          batch_id: batch.map(|batch| batch.batch_id.clone()),
+          commit_message: batch.map(|batch| batch.commit_message.clone()),
+          client_id: context.map(|context| String::from(context.client_id())),
+          created_at: context.map(|context| String::from(context.created_at())),
+        });
+      }
+    }
+
+    Ok(BlameReport {
+      subject: target.to_string(),
+      events: blamed,
+    })
+  }
+}
+
+// the shapes reachable from the given ones, and all fields ever added to them
+fn shape_entities(state: &OpticState, shape_ids: Vec<ShapeId>) -> IndexSet<EntityId> {
+  let shape_ids = shapes_reachable_from(state.shape, shape_ids);
+  let mut entities: IndexSet<EntityId> = shape_ids
+    .iter()
+    .filter(|shape_id| !is_core_shape(**shape_id))
+    .map(|shape_id| EntityId::Shape(*shape_id))
+    .collect();
+  entities.extend(
+    state
+      .shape
+      .all_fields()
+      .filter(|field| shape_ids.contains(&field.descriptor.shape_id))
+      .map(|field| EntityId::Field(field.field_id)),
+  );
+  entities
+}
+
+fn endpoint_entities(
+  state: &OpticState,
+  http_method: &HttpMethod,
+  path: &str,
+) -> Option<IndexSet<EntityId>> {
+  let all_endpoints = endpoints(state);
+  let endpoint = all_endpoints
+    .iter()
+    .find(|endpoint| endpoint.http_method == http_method && endpoint.path == path)?;
+
+  let mut shape_ids: Vec<ShapeId> = path_parameters(state, endpoint.path_id)
+    .iter()
+    .filter_map(|parameter| parameter.shape_id)
+    .collect();
+  for request in &endpoint.requests {
+    shape_ids.extend(live_bodies(&request.request_descriptor.bodies).map(|body| body.shape_id));
+  }
+  for response in &endpoint.responses {
+    shape_ids.extend(live_bodies(&response.response_descriptor.bodies).map(|body| body.shape_id));
+  }
+  for parameter in &endpoint.parameters {
+    let descriptor = &parameter.request_parameter_descriptor.shape_descriptor;
+    shape_ids.extend(descriptor.shaped().map(|shaped| shaped.shape_id));
+  }
+
+  let mut entities = IndexSet::new();
+  let mut current = state.requests.path_component(endpoint.path_id);
+  while let Some(component) = current {
+    entities.insert(EntityId::PathComponent(component.path_id));
+    current = state
+      .requests
+      .path_component(component.descriptor.parent_path_id());
+  }
+  entities.extend(
+    endpoint
+      .requests
+      .iter()
+      .map(|request| EntityId::Request(request.request_id)),
+  );
+  entities.extend(
+    endpoint
+      .parameters
+      .iter()
+      .map(|parameter| EntityId::RequestParameter(parameter.parameter_id)),
+  );
+  entities.extend(
+    endpoint
+      .responses
+      .iter()
+      .map(|response| EntityId::Response(response.response_id)),
+  );
+  entities.extend(shape_entities(state, shape_ids));
+  Some(entities)
+}
+
+impl fmt::Display for BlameReport {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    writeln!(f, "blame {}", self.subject)?;
+    // consecutive events of the same batch are listed under it
+    let mut previous_batch_id = None;
+    for event in &self.events {
+      let batch_id = event.batch_id.as_deref();
+      if previous_batch_id != Some(batch_id) {
+        match batch_id {
+          Some(batch_id) => {
+            writeln!(f, "  batch {}", batch_id)?;
+            let commit_message = event.commit_message.as_deref().unwrap_or_default();
+            for line in commit_message
+              .lines()
+              .filter(|line| !line.trim().is_empty())
+            {
+              writeln!(f, "    | {}", line)?;
+            }
+          }
+          None => writeln!(f, "  outside of batches")?,
+        }
+        previous_batch_id = Some(batch_id);
+      }
+      writeln!(
+        f,
+        "    {}  {}  {} {}",
+        event.created_at.as_deref().unwrap_or("(unknown)"),
+        event.client_id.as_deref().unwrap_or("(unknown)"),
+        event.event_type,
+        event.entity
+      )?;
+    }
+    Ok(())
+  }
+}
+
+#[test]
+fn blames_the_events_that_shaped_a_shape_and_an_endpoint() {
+  use crate::aggregate::OpticAggregate;
+
+  let events = crate::events_from_file("test-fixtures/uncompacted-spec.json").unwrap();
+  let aggregate = OpticAggregate::fold(events.clone());
+  let state = aggregate.get_state();
+  let blame = |target: &str| BlameReport::for_target(&events, &state, &target.parse().unwrap());
+
+  let shape = blame("EQSZqM_11").unwrap();
+  let field_added = shape
+    .events
+    .iter()
+    .find(|event| event.entity == "field EQSZqM_12")
+    .unwrap();
+  assert_eq!(field_added.event_type, "FieldAdded");
+  assert_eq!(field_added.client_id.as_deref(), Some("anonymous"));
+  assert_eq!(
+    field_added.batch_id.as_deref(),
+    Some("3960ebac-7dba-4118-97df-3af91005dc61")
+  );
+  assert!(field_added
+    .commit_message
+    .as_deref()
+    .unwrap()
+    .contains("Added 200 Response"));
+
+  // an endpoint includes the events of its path and its responses, and of the shapes of its bodies
+  let endpoint = blame("GET /api/f1/{season}").unwrap();
+  for event_type in &[
+    "PathComponentAdded",
+    "ResponseAddedByPathAndMethod",
+    "FieldAdded",
+  ] {
+    assert!(endpoint
+      .events
+      .iter()
+      .any(|event| event.event_type == *event_type));
+  }
+  assert!(endpoint.events.len() > shape.events.len());
+
+  assert!(blame("GET /nowhere").is_err());
+  assert!(blame("no_such_shape").is_err());
+
+  // a batch started before the previous one ended ends it, as does an end of another batch
+  let renames: Vec<OpticEvent> = serde_json::from_value(serde_json::json!([
+    {"BatchCommitStarted": {"batchId": "first", "commitMessage": "First", "eventContext": null}},
+    {"BatchCommitStarted": {"batchId": "second", "commitMessage": "Second", "eventContext": null}},
+    {"FieldRenamed": {"fieldId": "EQSZqM_12", "name": "nation", "eventContext": null}},
+    {"BatchCommitEnded": {"batchId": "first", "eventContext": null}},
+    {"FieldRenamed": {"fieldId": "EQSZqM_12", "name": "land", "eventContext": null}},
+  ]))
+  .unwrap();
+  let events = [&events[..], &renames[..]].concat();
+  let shape = BlameReport::for_target(&events, &state, &"EQSZqM_11".parse().unwrap()).unwrap();
+  let batch_ids: Vec<Option<&str>> = shape.events[shape.events.len() - 2..]
+    .iter()
+    .map(|event| event.batch_id.as_deref())
+    .collect();
+  assert_eq!(batch_ids, vec![Some("second"), None]);
+}
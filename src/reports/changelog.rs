@@ -0,0 +1,70 @@
+use cqrs_core::Event;
+use indexmap::IndexMap;
+use std::fmt;
+
+use crate::events::batches::batches;
+use crate::events::OpticEvent;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangelogReport {
+  pub batches: Vec<BatchSummary>,
+  pub events_outside_batches: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchSummary {
+  pub batch_id: String,
+  pub commit_message: String,
+  pub changes: IndexMap<&'static str, usize>,
+}
+
+impl ChangelogReport {
+  pub fn from_events(events: &[OpticEvent]) -> Self {
+    let mut summaries = vec![];
+    let mut events_outside_batches = 0;
+
+    for batch in batches(events) {
+      match (batch.batch_id(), batch.commit_message()) {
+        (Some(batch_id), Some(commit_message)) => {
+          let mut changes = IndexMap::new();
+          for event in batch.events {
+            *changes.entry(event.event_type()).or_insert(0) += 1;
+          }
+          summaries.push(BatchSummary {
+            batch_id: String::from(batch_id),
+            commit_message: String::from(commit_message),
+            changes,
+          });
+        }
+        _ => events_outside_batches += batch.events.len(),
+      }
+    }
+
+    ChangelogReport {
+      batches: summaries,
+      events_outside_batches,
+    }
+  }
+}
+
+impl fmt::Display for ChangelogReport {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    for batch in &self.batches {
+      writeln!(f, "batch {}", batch.batch_id)?;
+      for line in batch.commit_message.lines() {
+        writeln!(f, "    {}", line)?;
+      }
+      for (event_type, count) in &batch.changes {
+        writeln!(f, "    * {} {}", count, event_type)?;
+      }
+      writeln!(f)?;
+    }
+    writeln!(
+      f,
+      "{} events outside of batches",
+      self.events_outside_batches
+    )
+  }
+}
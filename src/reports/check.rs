@@ -0,0 +1,190 @@
+use std::fmt;
+
+use crate::aggregate::OpticState;
+use crate::events::batches::validate_batches;
+use crate::events::OpticEvent;
+use crate::state::requests::{PathComponentId, ROOT_PATH_ID};
+use crate::state::shape::{is_core_shape, ShapeId};
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckReport {
+  pub problems: Vec<Problem>,
+  // worth knowing about, but not failing the check
+  pub warnings: Vec<Problem>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Problem {
+  pub entity_id: String,
+  pub message: String,
+}
+
+impl CheckReport {
+  // Checks every live entity only refers to entities that exist and haven't been removed
+  pub fn from_state(state: &OpticState) -> Self {
+    let mut problems = vec![];
+    let mut problem = |entity_id: &dyn fmt::Display, message: String| {
+      problems.push(Problem {
+        entity_id: entity_id.to_string(),
+        message,
+      })
+    };
+
+    let path_exists = |path_id: PathComponentId| {
+      path_id.as_str() == ROOT_PATH_ID
+        || state
+          .requests
+          .path_component(path_id)
+          .is_some_and(|component| !component.is_removed)
+    };
+    let shape_exists = |shape_id: ShapeId| {
+      is_core_shape(shape_id)
+        || state
+          .shape
+          .shape(shape_id)
+          .is_some_and(|shape| !shape.is_removed)
+    };
+
+    for component in state
+      .requests
+      .all_path_components()
+      .filter(|c| !c.is_removed)
+    {
+      let parent_path_id = component.descriptor.parent_path_id();
+      if !path_exists(parent_path_id) {
+        problem(
+          &component.path_id,
+          format!("parent path {} does not exist", parent_path_id),
+        );
+      }
+    }
+
+    for request in state.requests.all_requests().filter(|r| !r.is_removed) {
+      let path_id = request.request_descriptor.path_component_id;
+      if !path_exists(path_id) {
+        problem(
+          &request.request_id,
+          format!("path {} does not exist", path_id),
+        );
+      }
+      for body in request.request_descriptor.bodies.values() {
+        if !shape_exists(body.shape_id) {
+          problem(
+            &request.request_id,
+            format!("body shape {} does not exist", body.shape_id),
+          );
+        }
+      }
+    }
+
+    for parameter in state
+      .requests
+      .all_request_parameters()
+      .filter(|p| !p.is_removed)
+    {
+      let descriptor = &parameter.request_parameter_descriptor;
+      if !path_exists(descriptor.path_id) {
+        problem(
+          &parameter.parameter_id,
+          format!("path {} does not exist", descriptor.path_id),
+        );
+      }
+      if let Some(shaped) = descriptor.shape_descriptor.shaped() {
+        if !shape_exists(shaped.shape_id) {
+          problem(
+            &parameter.parameter_id,
+            format!("shape {} does not exist", shaped.shape_id),
+          );
+        }
+      }
+    }
+
+    for response in state.requests.all_responses().filter(|r| !r.is_removed) {
+      let descriptor = &response.response_descriptor;
+      if !path_exists(descriptor.path_id) {
+        problem(
+          &response.response_id,
+          format!("path {} does not exist", descriptor.path_id),
+        );
+      }
+      for body in descriptor.bodies.values() {
+        if !shape_exists(body.shape_id) {
+          problem(
+            &response.response_id,
+            format!("body shape {} does not exist", body.shape_id),
+          );
+        }
+      }
+    }
+
+    for shape in state.shape.all_shapes().filter(|s| !s.is_removed) {
+      let base_shape_id = shape.descriptor.base_shape_id;
+      if !shape_exists(base_shape_id) {
+        problem(
+          &shape.shape_id,
+          format!("base shape {} does not exist", base_shape_id),
+        );
+      }
+      for provider in shape.descriptor.bindings.values() {
+        if let Some(provided_shape_id) = provider.shape_id() {
+          if !shape_exists(provided_shape_id) {
+            problem(
+              &shape.shape_id,
+              format!("bound shape {} does not exist", provided_shape_id),
+            );
+          }
+        }
+      }
+    }
+
+    for field in state.shape.all_fields().filter(|f| !f.is_removed) {
+      if let Some(field_shape_id) = field.descriptor.shape_descriptor.shape_id() {
+        if !shape_exists(field_shape_id) {
+          problem(
+            &field.field_id,
+            format!("shape {} does not exist", field_shape_id),
+          );
+        }
+      }
+    }
+
+    CheckReport {
+      problems,
+      warnings: vec![],
+    }
+  }
+
+  // Also checks the batches of the events the state was folded from
+  pub fn with_batches(mut self, events: &[OpticEvent]) -> Self {
+    for batch_problem in validate_batches(events) {
+      let problem = Problem {
+        entity_id: format!("event {}", batch_problem.index()),
+        message: batch_problem.to_string(),
+      };
+      if batch_problem.is_warning() {
+        self.warnings.push(problem);
+      } else {
+        self.problems.push(problem);
+      }
+    }
+    self
+  }
+
+  pub fn is_ok(&self) -> bool {
+    self.problems.is_empty()
+  }
+}
+
+impl fmt::Display for CheckReport {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    for problem in &self.problems {
+      writeln!(f, "{}: {}", problem.entity_id, problem.message)?;
+    }
+    for warning in &self.warnings {
+      writeln!(f, "{}: warning: {}", warning.entity_id, warning.message)?;
+    }
+    writeln!(f, "{} problems found", self.problems.len())
+  }
+}
@@ -0,0 +1,214 @@
+use std::fmt;
+
+use crate::aggregate::OpticState;
+use crate::projections::endpoints::{endpoints, Endpoint};
+use crate::state::requests::{live_bodies, ShapedBodyDescriptor};
+use crate::state::shape::{ShapeId, ShapeState};
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffReport {
+  pub changes: Vec<Change>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Change {
+  pub kind: ChangeKind,
+  pub subject: String,
+}
+
+#[derive(Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum ChangeKind {
+  Added,
+  Removed,
+}
+
+impl DiffReport {
+  // The endpoints, request bodies, responses and their body fields added or removed going from
+  // `base` to `head`
+  pub fn between(base: &OpticState, head: &OpticState) -> Self {
+    let base_endpoints = endpoints(base);
+    let head_endpoints = endpoints(head);
+    let mut changes = vec![];
+
+    for endpoint in &base_endpoints {
+      if find_endpoint(&head_endpoints, endpoint).is_none() {
+        changes.push(Change::removed(endpoint_subject(endpoint)));
+      }
+    }
+
+    for head_endpoint in &head_endpoints {
+      let base_endpoint = match find_endpoint(&base_endpoints, head_endpoint) {
+        Some(base_endpoint) => base_endpoint,
+        None => {
+          changes.push(Change::added(endpoint_subject(head_endpoint)));
+          continue;
+        }
+      };
+
+      let base_responses = body_descriptions(base, base_endpoint);
+      let head_responses = body_descriptions(head, head_endpoint);
+      for (subject, base_fields) in &base_responses {
+        match head_responses
+          .iter()
+          .find(|(head_subject, _)| head_subject == subject)
+        {
+          None => changes.push(Change::removed(subject.clone())),
+          Some((_, head_fields)) => {
+            for field in base_fields
+              .iter()
+              .filter(|field| !head_fields.contains(field))
+            {
+              changes.push(Change::removed(format!("{} {}", subject, field)));
+            }
+            for field in head_fields
+              .iter()
+              .filter(|field| !base_fields.contains(field))
+            {
+              changes.push(Change::added(format!("{} {}", subject, field)));
+            }
+          }
+        }
+      }
+      for (subject, _) in &head_responses {
+        if !base_responses
+          .iter()
+          .any(|(base_subject, _)| base_subject == subject)
+        {
+          changes.push(Change::added(subject.clone()));
+        }
+      }
+    }
+
+    DiffReport { changes }
+  }
+
+  pub fn has_changes(&self) -> bool {
+    !self.changes.is_empty()
+  }
+}
+
+impl Change {
+  fn added(subject: String) -> Self {
+    Change {
+      kind: ChangeKind::Added,
+      subject,
+    }
+  }
+
+  fn removed(subject: String) -> Self {
+    Change {
+      kind: ChangeKind::Removed,
+      subject,
+    }
+  }
+}
+
+fn find_endpoint<'e, 'a>(
+  endpoints: &'e [Endpoint<'a>],
+  other: &Endpoint,
+) -> Option<&'e Endpoint<'a>> {
+  endpoints
+    .iter()
+    .find(|endpoint| endpoint.path == other.path && endpoint.http_method == other.http_method)
+}
+
+fn endpoint_subject(endpoint: &Endpoint) -> String {
+  format!("{} {}", endpoint.http_method, endpoint.path)
+}
+
+// every request body and response body of an endpoint (or the response, when it has no body), with
+// the fields of the body flattened into comparable lines
+fn body_descriptions(state: &OpticState, endpoint: &Endpoint) -> Vec<(String, Vec<String>)> {
+  let describe = |subject: String, body: &ShapedBodyDescriptor| {
+    let mut fields = vec![];
+    describe_shape(
+      state.shape,
+      body.shape_id,
+      String::from("$"),
+      &mut fields,
+      &mut vec![],
+    );
+    (format!("{} {}", subject, body.http_content_type), fields)
+  };
+  let mut descriptions = vec![];
+
+  for request in &endpoint.requests {
+    for body in live_bodies(&request.request_descriptor.bodies) {
+      let subject = format!("{} request", endpoint_subject(endpoint));
+      descriptions.push(describe(subject, body));
+    }
+  }
+
+  for response in &endpoint.responses {
+    let descriptor = &response.response_descriptor;
+    let subject = format!(
+      "{} {}",
+      endpoint_subject(endpoint),
+      descriptor.http_status_code
+    );
+    if live_bodies(&descriptor.bodies).next().is_none() {
+      descriptions.push((subject.clone(), vec![]));
+    }
+    for body in live_bodies(&descriptor.bodies) {
+      descriptions.push(describe(subject.clone(), body));
+    }
+  }
+
+  descriptions
+}
+
+// Flattens a shape into one `path: core shape` line per value, e.g. `$.items[].name: $string`
+pub fn describe_shape(
+  shapes: &ShapeState,
+  shape_id: ShapeId,
+  path: String,
+  lines: &mut Vec<String>,
+  visiting: &mut Vec<ShapeId>,
+) {
+  let core_shape_id = shapes.core_shape_id(shape_id);
+  lines.push(format!("{}: {}", path, core_shape_id));
+  if visiting.contains(&shape_id) {
+    return;
+  }
+
+  let (inner_path, parameter_id) = match core_shape_id.as_str() {
+    "$list" => (format!("{}[]", path), "$listItem"),
+    "$nullable" => (path, "$nullableInner"),
+    "$optional" => (path, "$optionalInner"),
+    "$object" => {
+      visiting.push(shape_id);
+      for field in shapes.resolved_fields_of(shape_id) {
+        if let Some(field_shape_id) = field.descriptor.shape_descriptor.shape_id() {
+          let field_path = format!("{}.{}", path, field.descriptor.name);
+          describe_shape(shapes, field_shape_id, field_path, lines, visiting);
+        }
+      }
+      visiting.pop();
+      return;
+    }
+    _ => return,
+  };
+
+  if let Some(inner_shape_id) = shapes.bound_shape_id(shape_id, parameter_id) {
+    visiting.push(shape_id);
+    describe_shape(shapes, inner_shape_id, inner_path, lines, visiting);
+    visiting.pop();
+  }
+}
+
+impl fmt::Display for DiffReport {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    for change in &self.changes {
+      let marker = if change.kind == ChangeKind::Added {
+        "+"
+      } else {
+        "-"
+      };
+      writeln!(f, "{} {}", marker, change.subject)?;
+    }
+    Ok(())
+  }
+}
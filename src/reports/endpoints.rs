@@ -0,0 +1,109 @@
+use std::fmt;
+
+use crate::aggregate::OpticState;
+use crate::projections::endpoints::endpoints;
+use crate::state::http::{HttpMethod, StatusCode};
+use crate::state::requests::Bodies;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EndpointsReport {
+  pub endpoints: Vec<EndpointSummary>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EndpointSummary {
+  pub http_method: HttpMethod,
+  pub path: String,
+  pub purpose: Option<String>,
+  pub request_bodies: Vec<BodySummary>,
+  pub responses: Vec<ResponseSummary>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BodySummary {
+  pub http_content_type: String,
+  pub shape_id: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResponseSummary {
+  pub http_status_code: StatusCode,
+  pub bodies: Vec<BodySummary>,
+}
+
+impl EndpointsReport {
+  pub fn from_state(state: &OpticState) -> Self {
+    let endpoints = endpoints(state)
+      .into_iter()
+      .map(|endpoint| EndpointSummary {
+        http_method: endpoint.http_method.clone(),
+        path: endpoint.path.clone(),
+        purpose: endpoint.purpose.map(String::from),
+        request_bodies: endpoint
+          .requests
+          .iter()
+          .flat_map(|request| body_summaries(&request.request_descriptor.bodies))
+          .collect(),
+        responses: endpoint
+          .responses
+          .iter()
+          .map(|response| {
+            let descriptor = &response.response_descriptor;
+            ResponseSummary {
+              http_status_code: descriptor.http_status_code,
+              bodies: body_summaries(&descriptor.bodies),
+            }
+          })
+          .collect(),
+      })
+      .collect();
+
+    EndpointsReport { endpoints }
+  }
+}
+
+fn body_summaries(bodies: &Bodies) -> Vec<BodySummary> {
+  bodies
+    .values()
+    .map(|body| BodySummary {
+      http_content_type: body.http_content_type.clone(),
+      shape_id: body.shape_id.to_string(),
+    })
+    .collect()
+}
+
+impl fmt::Display for EndpointsReport {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    for endpoint in &self.endpoints {
+      write!(f, "{} {}", endpoint.http_method, endpoint.path)?;
+      if let Some(purpose) = &endpoint.purpose {
+        write!(f, "  {}", purpose)?;
+      }
+      writeln!(f)?;
+      for body in &endpoint.request_bodies {
+        writeln!(
+          f,
+          "    request {} ({})",
+          body.http_content_type, body.shape_id
+        )?;
+      }
+      for response in &endpoint.responses {
+        write!(f, "    {}", response.http_status_code)?;
+        for (index, body) in response.bodies.iter().enumerate() {
+          let separator = if index == 0 { " " } else { ", " };
+          write!(
+            f,
+            "{}{} ({})",
+            separator, body.http_content_type, body.shape_id
+          )?;
+        }
+        writeln!(f)?;
+      }
+    }
+    Ok(())
+  }
+}
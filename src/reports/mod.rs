@@ -0,0 +1,8 @@
+// Reports are what the CLI prints: each serializes to JSON and displays as plain text
+pub mod blame;
+pub mod changelog;
+pub mod check;
+pub mod diff;
+pub mod endpoints;
+pub mod shapes;
+pub mod stats;
@@ -0,0 +1,73 @@
+use std::fmt;
+
+use crate::state::shape::ShapeState;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShapesReport {
+  pub shapes: Vec<ShapeSummary>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShapeSummary {
+  pub shape_id: String,
+  pub name: String,
+  pub base_shape_id: String,
+  pub core_shape_id: String,
+  pub fields: Vec<FieldSummary>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldSummary {
+  pub field_id: String,
+  pub name: String,
+  pub shape_id: Option<String>,
+}
+
+impl ShapesReport {
+  pub fn from_state(shapes: &ShapeState) -> Self {
+    let shapes = shapes
+      .all_shapes()
+      .filter(|shape| !shape.is_removed)
+      .map(|shape| ShapeSummary {
+        shape_id: shape.shape_id.to_string(),
+        name: shape.descriptor.name.clone(),
+        base_shape_id: shape.descriptor.base_shape_id.to_string(),
+        core_shape_id: shapes.core_shape_id(shape.shape_id).to_string(),
+        fields: shapes
+          .fields_of(shape.shape_id)
+          .map(|field| FieldSummary {
+            field_id: field.field_id.to_string(),
+            name: field.descriptor.name.clone(),
+            shape_id: field
+              .descriptor
+              .shape_descriptor
+              .shape_id()
+              .map(|shape_id| shape_id.to_string()),
+          })
+          .collect(),
+      })
+      .collect();
+
+    ShapesReport { shapes }
+  }
+}
+
+impl fmt::Display for ShapesReport {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    for shape in &self.shapes {
+      write!(f, "{} {}", shape.shape_id, shape.base_shape_id)?;
+      if !shape.name.is_empty() {
+        write!(f, " \"{}\"", shape.name)?;
+      }
+      writeln!(f)?;
+      for field in &shape.fields {
+        let field_shape_id = field.shape_id.as_deref().unwrap_or("(parameter)");
+        writeln!(f, "    {}: {}", field.name, field_shape_id)?;
+      }
+    }
+    Ok(())
+  }
+}
@@ -0,0 +1,188 @@
+use cqrs_core::Event;
+use indexmap::IndexMap;
+use std::fmt;
+
+use crate::aggregate::OpticState;
+use crate::events::OpticEvent;
+use crate::projections::endpoints::endpoints;
+use crate::projections::reachability::unreachable_shapes;
+use crate::state::shape::is_core_shape;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatsReport {
+  pub events: usize,
+  pub events_by_type: IndexMap<&'static str, usize>,
+  pub events_by_aggregate_type: IndexMap<&'static str, usize>,
+  pub events_by_client_id: IndexMap<String, usize>,
+  pub paths: usize,
+  pub endpoints: usize,
+  pub responses: usize,
+  pub responses_by_status_class: IndexMap<String, usize>,
+  pub shapes: usize,
+  pub user_defined_shapes: usize,
+  // the core shapes (`$string`, `$object`, ..) in the spec or its own shapes are built on
+  pub core_shapes: usize,
+  pub unused_shapes: usize,
+  pub fields: usize,
+  // how many events it takes per entity that's still in the spec, which compaction brings down
+  pub events_per_live_entity: f64,
+}
+
+impl StatsReport {
+  pub fn new(events: &[OpticEvent], state: &OpticState) -> Self {
+    let mut events_by_type = IndexMap::new();
+    let mut events_by_aggregate_type = IndexMap::new();
+    let mut events_by_client_id = IndexMap::new();
+    for event in events {
+      *events_by_type.entry(event.event_type()).or_insert(0) += 1;
+      *events_by_aggregate_type
+        .entry(event.aggregate_type())
+        .or_insert(0) += 1;
+      let client_id = event
+        .event_context()
+        .map_or("(unknown)", |context| context.client_id());
+      *events_by_client_id
+        .entry(String::from(client_id))
+        .or_insert(0) += 1;
+    }
+
+    let requests_state = state.requests;
+    let paths = requests_state
+      .all_path_components()
+      .filter(|c| !c.is_removed)
+      .count();
+    let parameters = requests_state
+      .all_request_parameters()
+      .filter(|p| !p.is_removed)
+      .count();
+    let requests = requests_state
+      .all_requests()
+      .filter(|r| !r.is_removed)
+      .count();
+
+    let mut responses = 0;
+    let mut responses_by_status_class = IndexMap::new();
+    for response in requests_state.all_responses().filter(|r| !r.is_removed) {
+      let status_class = response
+        .response_descriptor
+        .http_status_code
+        .class()
+        .to_string();
+      *responses_by_status_class.entry(status_class).or_insert(0) += 1;
+      responses += 1;
+    }
+    responses_by_status_class.sort_keys();
+
+    let live_shapes: Vec<_> = state.shape.all_shapes().filter(|s| !s.is_removed).collect();
+    let user_defined_shapes = live_shapes
+      .iter()
+      .filter(|shape| shape.descriptor.is_user_defined)
+      .count();
+    let mut core_shapes: Vec<_> = live_shapes
+      .iter()
+      .flat_map(|shape| vec![shape.shape_id, shape.descriptor.base_shape_id])
+      .filter(|shape_id| is_core_shape(*shape_id))
+      .collect();
+    core_shapes.sort_by_key(|shape_id| shape_id.as_str());
+    core_shapes.dedup();
+    let fields = state.shape.all_fields().filter(|f| !f.is_removed).count();
+
+    let live_entities = paths + parameters + requests + responses + live_shapes.len() + fields;
+    let events_per_live_entity = if live_entities == 0 {
+      0.0
+    } else {
+      events.len() as f64 / live_entities as f64
+    };
+
+    StatsReport {
+      events: events.len(),
+      events_by_type,
+      events_by_aggregate_type,
+      events_by_client_id,
+      paths,
+      endpoints: endpoints(state).len(),
+      responses,
+      responses_by_status_class,
+      shapes: live_shapes.len(),
+      user_defined_shapes,
+      core_shapes: core_shapes.len(),
+      unused_shapes: unreachable_shapes(state).len(),
+      fields,
+      events_per_live_entity,
+    }
+  }
+}
+
+impl fmt::Display for StatsReport {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    writeln!(f, "events:    {}", self.events)?;
+    for (event_type, count) in &self.events_by_type {
+      writeln!(f, "  {:<40} {}", event_type, count)?;
+    }
+    writeln!(f, "  by aggregate:")?;
+    for (aggregate_type, count) in &self.events_by_aggregate_type {
+      writeln!(f, "    {:<38} {}", aggregate_type, count)?;
+    }
+    writeln!(f, "  by client:")?;
+    for (client_id, count) in &self.events_by_client_id {
+      writeln!(f, "    {:<38} {}", client_id, count)?;
+    }
+    writeln!(f, "paths:     {}", self.paths)?;
+    writeln!(f, "endpoints: {}", self.endpoints)?;
+    writeln!(f, "responses: {}", self.responses)?;
+    for (status_class, count) in &self.responses_by_status_class {
+      writeln!(f, "  {:<40} {}", status_class, count)?;
+    }
+    writeln!(
+      f,
+      "shapes:    {} ({} user-defined) built on {} core shapes, {} unused",
+      self.shapes, self.user_defined_shapes, self.core_shapes, self.unused_shapes
+    )?;
+    writeln!(f, "fields:    {}", self.fields)?;
+    writeln!(
+      f,
+      "events per live entity: {:.2}",
+      self.events_per_live_entity
+    )
+  }
+}
+
+#[test]
+fn stats_count_the_live_entities_of_the_spec() {
+  use crate::aggregate::{Aggregate, OpticAggregate};
+
+  let events = crate::events_from_file("test-fixtures/uncompacted-spec.json").unwrap();
+  let mut aggregate = OpticAggregate::fold(events.clone());
+  let report = StatsReport::new(&events, &aggregate.get_state());
+
+  assert_eq!(report.events, 611);
+  assert_eq!(report.events_by_aggregate_type["shape"], 528);
+  assert_eq!(report.events_by_client_id["anonymous"], 602);
+  assert_eq!(
+    (report.paths, report.endpoints, report.responses),
+    (11, 7, 10)
+  );
+  assert_eq!(report.responses_by_status_class["2xx"], 9);
+  assert_eq!(report.responses_by_status_class["4xx"], 1);
+  assert_eq!((report.shapes, report.user_defined_shapes), (278, 278));
+  assert_eq!((report.core_shapes, report.unused_shapes), (6, 0));
+  assert_eq!(report.fields, 239);
+
+  // a core shape added to the spec is a shape, but not a user-defined one
+  aggregate.apply(
+    serde_json::from_value::<OpticEvent>(serde_json::json!({
+      "ShapeAdded": {
+        "shapeId": "$string",
+        "baseShapeId": "$string",
+        "parameters": {"DynamicParameterList": {"shapeParameterIds": []}},
+        "name": "",
+        "eventContext": null,
+      }
+    }))
+    .unwrap(),
+  );
+  let report = StatsReport::new(&events, &aggregate.get_state());
+  assert_eq!((report.shapes, report.user_defined_shapes), (279, 278));
+  assert_eq!(report.core_shapes, 6);
+}
@@ -0,0 +1,49 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use super::SnapshotError;
+
+// Layout: magic (4 bytes) | encoding version (u32 LE) | CRC-32 of payload (u32 LE) | bincode payload
+pub const MAGIC: &[u8; 4] = b"OPTS";
+pub const ENCODING_VERSION: u32 = 1;
+const HEADER_LEN: usize = 12;
+
+pub fn is_binary(bytes: &[u8]) -> bool {
+  bytes.starts_with(MAGIC)
+}
+
+pub fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, SnapshotError> {
+  let payload = bincode::serialize(value)?;
+
+  let mut bytes = Vec::with_capacity(HEADER_LEN + payload.len());
+  bytes.extend_from_slice(MAGIC);
+  bytes.extend_from_slice(&ENCODING_VERSION.to_le_bytes());
+  bytes.extend_from_slice(&crc32fast::hash(&payload).to_le_bytes());
+  bytes.extend_from_slice(&payload);
+  Ok(bytes)
+}
+
+pub fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, SnapshotError> {
+  if bytes.len() < HEADER_LEN || !is_binary(bytes) {
+    return Err(SnapshotError::InvalidHeader);
+  }
+
+  let version = read_u32(&bytes[4..8]);
+  if version != ENCODING_VERSION {
+    return Err(SnapshotError::UnsupportedEncodingVersion(version));
+  }
+
+  let checksum = read_u32(&bytes[8..12]);
+  let payload = &bytes[HEADER_LEN..];
+  if crc32fast::hash(payload) != checksum {
+    return Err(SnapshotError::ChecksumMismatch);
+  }
+
+  Ok(bincode::deserialize(payload)?)
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+  let mut word = [0; 4];
+  word.copy_from_slice(bytes);
+  u32::from_le_bytes(word)
+}
@@ -0,0 +1,347 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use cqrs_core::{
+  Aggregate, AggregateId, SnapshotSink, SnapshotSource, Version, VersionedAggregate,
+};
+
+use crate::aggregate::{OpticAggregate, OpticAggregateId};
+use crate::events::rfc::RfcEvent;
+use crate::events::OpticEvent;
+
+pub mod binary;
+
+// bump whenever the serialized shape of the aggregate state changes, so stale snapshots get refolded
+pub const SNAPSHOT_FORMAT_VERSION: u32 = 8;
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Snapshot<A> {
+  format_version: u32,
+  aggregate_id: String,
+  event_count: u64,
+  aggregate: A,
+}
+
+// the leading fields of every snapshot, read first so snapshots of another format version are
+// refolded instead of failing to decode
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SnapshotHeader {
+  format_version: u32,
+  aggregate_id: String,
+}
+
+// Snapshot store
+// --------------
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SnapshotEncoding {
+  Json,
+  Binary,
+}
+
+impl std::str::FromStr for SnapshotEncoding {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "json" => Ok(SnapshotEncoding::Json),
+      "binary" => Ok(SnapshotEncoding::Binary),
+      _ => Err(format!("unknown snapshot encoding '{}'", s)),
+    }
+  }
+}
+
+// Snapshots are written in the configured encoding, but read in whichever encoding the file was
+// written in, so switching encodings doesn't invalidate existing snapshots.
+pub struct SnapshotFile {
+  path: PathBuf,
+  encoding: SnapshotEncoding,
+}
+
+impl SnapshotFile {
+  pub fn new(path: impl Into<PathBuf>) -> Self {
+    Self::with_encoding(path, SnapshotEncoding::Json)
+  }
+
+  pub fn with_encoding(path: impl Into<PathBuf>, encoding: SnapshotEncoding) -> Self {
+    SnapshotFile {
+      path: path.into(),
+      encoding,
+    }
+  }
+}
+
+impl SnapshotSource<OpticAggregate> for SnapshotFile {
+  type Error = SnapshotError;
+
+  fn get_snapshot<I>(
+    &self,
+    id: &I,
+  ) -> Result<Option<VersionedAggregate<OpticAggregate>>, Self::Error>
+  where
+    I: AggregateId<OpticAggregate>,
+  {
+    let contents = match fs::read(&self.path) {
+      Ok(contents) => contents,
+      Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+      Err(err) => return Err(err.into()),
+    };
+
+    let header: SnapshotHeader = decode(&contents)?;
+    if header.format_version != SNAPSHOT_FORMAT_VERSION || header.aggregate_id != id.as_str() {
+      return Ok(None);
+    }
+
+    let snapshot: Snapshot<OpticAggregate> = decode(&contents)?;
+
+    Ok(Some(VersionedAggregate {
+      version: Version::new(snapshot.event_count),
+      payload: snapshot.aggregate,
+    }))
+  }
+}
+
+impl SnapshotSink<OpticAggregate> for SnapshotFile {
+  type Error = SnapshotError;
+
+  fn persist_snapshot<I>(
+    &self,
+    id: &I,
+    aggregate: &OpticAggregate,
+    version: Version,
+    _last_snapshot_version: Option<Version>,
+  ) -> Result<Version, Self::Error>
+  where
+    I: AggregateId<OpticAggregate>,
+  {
+    let snapshot = Snapshot {
+      format_version: SNAPSHOT_FORMAT_VERSION,
+      aggregate_id: String::from(id.as_str()),
+      event_count: version.get(),
+      aggregate,
+    };
+
+    let contents = match self.encoding {
+      SnapshotEncoding::Json => serde_json::to_vec(&snapshot)?,
+      SnapshotEncoding::Binary => binary::encode(&snapshot)?,
+    };
+
+    fs::write(&self.path, contents)?;
+    Ok(version)
+  }
+}
+
+fn decode<T: serde::de::DeserializeOwned>(contents: &[u8]) -> Result<T, SnapshotError> {
+  if binary::is_binary(contents) {
+    binary::decode(contents)
+  } else {
+    Ok(serde_json::from_slice(contents)?)
+  }
+}
+
+// Loading
+// -------
+
+// Restores the latest snapshot from the source and applies only the events recorded after it. When
+// the snapshot doesn't match the stream (fewer events, or a different last batch) it is discarded
+// and the aggregate is refolded from the first event.
+pub fn restore_aggregate<S>(
+  source: &S,
+  id: &OpticAggregateId,
+  events: Vec<OpticEvent>,
+) -> Result<VersionedAggregate<OpticAggregate>, S::Error>
+where
+  S: SnapshotSource<OpticAggregate>,
+{
+  let snapshot = source
+    .get_snapshot(id)?
+    .filter(|snapshot| is_snapshot_of(snapshot, &events));
+
+  let (mut version, mut aggregate) = match snapshot {
+    Some(snapshot) => (snapshot.version, snapshot.payload),
+    None => (Version::Initial, OpticAggregate::default()),
+  };
+
+  for event in events.into_iter().skip(version.get() as usize) {
+    aggregate.apply(event);
+    version.incr();
+  }
+
+  Ok(VersionedAggregate {
+    version,
+    payload: aggregate,
+  })
+}
+
+fn is_snapshot_of(snapshot: &VersionedAggregate<OpticAggregate>, events: &[OpticEvent]) -> bool {
+  let event_count = snapshot.version.get() as usize;
+  if event_count > events.len() {
+    return false;
+  }
+
+  let last_batch_id = events[..event_count]
+    .iter()
+    .rev()
+    .find_map(|event| match event {
+      OpticEvent::RfcEvent(RfcEvent::BatchCommitEnded(e)) => Some(&e.batch_id),
+      _ => None,
+    });
+
+  last_batch_id == snapshot.payload.get_state().rfc.last_batch_id.as_ref()
+}
+
+// Errors
+// ------
+
+#[derive(Debug)]
+pub enum SnapshotError {
+  Io(io::Error),
+  Json(serde_json::Error),
+  Binary(bincode::Error),
+  InvalidHeader,
+  UnsupportedEncodingVersion(u32),
+  ChecksumMismatch,
+}
+
+impl fmt::Display for SnapshotError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      SnapshotError::Io(err) => write!(f, "snapshot could not be read or written: {}", err),
+      SnapshotError::Json(err) => write!(f, "snapshot is not valid: {}", err),
+      SnapshotError::Binary(err) => write!(f, "binary snapshot is not valid: {}", err),
+      SnapshotError::InvalidHeader => write!(f, "binary snapshot has no valid header"),
+      SnapshotError::UnsupportedEncodingVersion(version) => {
+        write!(
+          f,
+          "binary snapshot encoding version {} is not supported",
+          version
+        )
+      }
+      SnapshotError::ChecksumMismatch => write!(f, "binary snapshot checksum does not match"),
+    }
+  }
+}
+
+impl From<io::Error> for SnapshotError {
+  fn from(err: io::Error) -> Self {
+    SnapshotError::Io(err)
+  }
+}
+
+impl From<serde_json::Error> for SnapshotError {
+  fn from(err: serde_json::Error) -> Self {
+    SnapshotError::Json(err)
+  }
+}
+
+impl From<bincode::Error> for SnapshotError {
+  fn from(err: bincode::Error) -> Self {
+    SnapshotError::Binary(err)
+  }
+}
+
+// a file name no other test, nor another run of the tests at the same time, writes to
+#[cfg(test)]
+fn temp_snapshot_path(name: &str) -> PathBuf {
+  std::env::temp_dir().join(format!("optic-{}-{}", std::process::id(), name))
+}
+
+#[cfg(test)]
+fn fixture_events(count: usize) -> Vec<OpticEvent> {
+  crate::events_from_file("test-fixtures/uncompacted-spec.json")
+    .unwrap()
+    .into_iter()
+    .take(count)
+    .collect()
+}
+
+#[test]
+fn restores_snapshot_and_applies_newer_events() {
+  let snapshots = SnapshotFile::new(temp_snapshot_path("restores-snapshot.json"));
+  let id = OpticAggregateId(String::from("uncompacted-spec"));
+
+  let partial = OpticAggregate::fold(fixture_events(300));
+  snapshots
+    .persist_snapshot(&id, &partial, Version::new(300), None)
+    .unwrap();
+
+  let events = fixture_events(usize::MAX);
+  let event_count = events.len() as u64;
+  let restored = restore_aggregate(&snapshots, &id, events).unwrap();
+
+  assert_eq!(restored.version.get(), event_count);
+  assert_eq!(
+    serde_json::to_value(&restored.payload).unwrap(),
+    serde_json::to_value(OpticAggregate::fold(fixture_events(usize::MAX))).unwrap()
+  );
+}
+
+#[test]
+fn refolds_when_snapshot_does_not_match_stream() {
+  let snapshots = SnapshotFile::new(temp_snapshot_path("mismatched-snapshot.json"));
+  let id = OpticAggregateId(String::from("uncompacted-spec"));
+
+  let events = fixture_events(usize::MAX);
+  let event_count = events.len() as u64;
+  snapshots
+    .persist_snapshot(
+      &id,
+      &OpticAggregate::fold(events),
+      Version::new(event_count),
+      None,
+    )
+    .unwrap();
+
+  let restored = restore_aggregate(&snapshots, &id, fixture_events(100)).unwrap();
+
+  assert_eq!(restored.version.get(), 100);
+  assert_eq!(
+    serde_json::to_value(&restored.payload).unwrap(),
+    serde_json::to_value(OpticAggregate::fold(fixture_events(100))).unwrap()
+  );
+}
+
+#[test]
+fn binary_and_json_snapshots_decode_to_equal_state() {
+  let id = OpticAggregateId(String::from("uncompacted-spec"));
+  let json = SnapshotFile::with_encoding(
+    temp_snapshot_path("round-trip-snapshot.json"),
+    SnapshotEncoding::Json,
+  );
+  let binary = SnapshotFile::with_encoding(
+    temp_snapshot_path("round-trip-snapshot.bin"),
+    SnapshotEncoding::Binary,
+  );
+
+  let aggregate = OpticAggregate::fold(fixture_events(usize::MAX));
+  for snapshots in &[&json, &binary] {
+    snapshots
+      .persist_snapshot(&id, &aggregate, Version::new(42), None)
+      .unwrap();
+  }
+
+  let from_json = json.get_snapshot(&id).unwrap().unwrap();
+  let from_binary = binary.get_snapshot(&id).unwrap().unwrap();
+
+  assert_eq!(from_json.version, from_binary.version);
+  assert_eq!(
+    serde_json::to_value(&from_json.payload).unwrap(),
+    serde_json::to_value(&from_binary.payload).unwrap()
+  );
+}
+
+#[test]
+fn binary_snapshot_with_bad_checksum_is_rejected() {
+  let mut bytes = binary::encode(&OpticAggregate::fold(fixture_events(50))).unwrap();
+  let last = bytes.len() - 1;
+  bytes[last] ^= 0xff;
+
+  match binary::decode::<OpticAggregate>(&bytes) {
+    Err(SnapshotError::ChecksumMismatch) => {}
+    _ => panic!("corrupted binary snapshot must fail its checksum"),
+  }
+}
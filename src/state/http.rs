@@ -0,0 +1,197 @@
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+// Methods
+// -------
+
+// The standard methods, with any other (like WebDAV's `PROPFIND`) preserved as an extension
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum HttpMethod {
+  Get,
+  Head,
+  Post,
+  Put,
+  Delete,
+  Connect,
+  Options,
+  Trace,
+  Patch,
+  Extension(String),
+}
+
+impl HttpMethod {
+  pub fn as_str(&self) -> &str {
+    match self {
+      HttpMethod::Get => "GET",
+      HttpMethod::Head => "HEAD",
+      HttpMethod::Post => "POST",
+      HttpMethod::Put => "PUT",
+      HttpMethod::Delete => "DELETE",
+      HttpMethod::Connect => "CONNECT",
+      HttpMethod::Options => "OPTIONS",
+      HttpMethod::Trace => "TRACE",
+      HttpMethod::Patch => "PATCH",
+      HttpMethod::Extension(method) => method,
+    }
+  }
+}
+
+impl FromStr for HttpMethod {
+  type Err = String;
+
+  // methods are case sensitive, and extensions must be a token as defined by RFC 7230
+  fn from_str(method: &str) -> Result<Self, Self::Err> {
+    Ok(match method {
+      "GET" => HttpMethod::Get,
+      "HEAD" => HttpMethod::Head,
+      "POST" => HttpMethod::Post,
+      "PUT" => HttpMethod::Put,
+      "DELETE" => HttpMethod::Delete,
+      "CONNECT" => HttpMethod::Connect,
+      "OPTIONS" => HttpMethod::Options,
+      "TRACE" => HttpMethod::Trace,
+      "PATCH" => HttpMethod::Patch,
+      extension => {
+        let is_token_char = |c: char| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c);
+        if extension.is_empty() || !extension.chars().all(is_token_char) {
+          return Err(format!("'{}' is not a valid HTTP method", extension));
+        }
+        HttpMethod::Extension(String::from(extension))
+      }
+    })
+  }
+}
+
+impl fmt::Display for HttpMethod {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str(self.as_str())
+  }
+}
+
+impl Serialize for HttpMethod {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(self.as_str())
+  }
+}
+
+impl<'de> Deserialize<'de> for HttpMethod {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    let method = String::deserialize(deserializer)?;
+    method.parse().map_err(de::Error::custom)
+  }
+}
+
+// Status codes
+// ------------
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct StatusCode(u16);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StatusClass {
+  Informational,
+  Success,
+  Redirection,
+  ClientError,
+  ServerError,
+}
+
+impl StatusCode {
+  pub fn new(code: u16) -> Result<Self, String> {
+    if (100..=599).contains(&code) {
+      Ok(StatusCode(code))
+    } else {
+      Err(format!("{} is not a valid HTTP status code", code))
+    }
+  }
+
+  pub fn as_u16(&self) -> u16 {
+    self.0
+  }
+
+  pub fn class(&self) -> StatusClass {
+    match self.0 {
+      100..=199 => StatusClass::Informational,
+      200..=299 => StatusClass::Success,
+      300..=399 => StatusClass::Redirection,
+      400..=499 => StatusClass::ClientError,
+      _ => StatusClass::ServerError,
+    }
+  }
+
+  pub fn is_success(&self) -> bool {
+    self.class() == StatusClass::Success
+  }
+
+  pub fn is_client_error(&self) -> bool {
+    self.class() == StatusClass::ClientError
+  }
+
+  pub fn is_server_error(&self) -> bool {
+    self.class() == StatusClass::ServerError
+  }
+}
+
+impl StatusClass {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      StatusClass::Informational => "1xx",
+      StatusClass::Success => "2xx",
+      StatusClass::Redirection => "3xx",
+      StatusClass::ClientError => "4xx",
+      StatusClass::ServerError => "5xx",
+    }
+  }
+}
+
+impl fmt::Display for StatusCode {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.0)
+  }
+}
+
+impl fmt::Display for StatusClass {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str(self.as_str())
+  }
+}
+
+impl Serialize for StatusCode {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u16(self.0)
+  }
+}
+
+impl<'de> Deserialize<'de> for StatusCode {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    let code = u16::deserialize(deserializer)?;
+    StatusCode::new(code).map_err(de::Error::custom)
+  }
+}
+
+#[test]
+fn methods_and_status_codes_are_validated() {
+  let method = |json: &str| serde_json::from_str::<HttpMethod>(json);
+  assert_eq!(method("\"GET\"").unwrap(), HttpMethod::Get);
+  assert_eq!(
+    method("\"PROPFIND\"").unwrap(),
+    HttpMethod::Extension(String::from("PROPFIND"))
+  );
+  assert_eq!(
+    serde_json::to_string(&method("\"PROPFIND\"").unwrap()).unwrap(),
+    "\"PROPFIND\""
+  );
+  assert!(method("\"GET /\"").is_err());
+  assert!(method("\"\"").is_err());
+
+  let status_code = |json: &str| serde_json::from_str::<StatusCode>(json);
+  assert_eq!(
+    status_code("404").unwrap().class(),
+    StatusClass::ClientError
+  );
+  assert!(status_code("201").unwrap().is_success());
+  assert!(status_code("42").is_err());
+  assert!(status_code("600").is_err());
+}
@@ -0,0 +1,158 @@
+use serde::de::{Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{OnceLock, RwLock};
+
+// Entity ids are interned into a process-wide table, so the state only stores and hashes compact
+// handles: a handle is the index of its string in the table. Strings are stored in chunks that are
+// never moved or freed once allocated, so reading a handle's string back takes no lock.
+//
+// Interned strings are kept for the rest of the process. That's fine for the bounded set of ids
+// in a spec, but means ids must only ever be created from specs: interning untrusted input (like
+// the paths of requests to the mock server) would grow the table without bound. Use `existing` to
+// look up ids from anywhere else.
+const CHUNK_LENGTH: usize = 4096;
+const MAX_CHUNKS: usize = 1024;
+
+type Chunk = Box<[OnceLock<Box<str>>]>;
+
+#[allow(clippy::declare_interior_mutable_const)] // only used to initialize `STRINGS`
+const UNALLOCATED: OnceLock<Chunk> = OnceLock::new();
+static STRINGS: [OnceLock<Chunk>; MAX_CHUNKS] = [UNALLOCATED; MAX_CHUNKS];
+
+fn symbols() -> &'static RwLock<HashMap<&'static str, Symbol>> {
+  static SYMBOLS: OnceLock<RwLock<HashMap<&'static str, Symbol>>> = OnceLock::new();
+  SYMBOLS.get_or_init(Default::default)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct Symbol(u32);
+
+impl Symbol {
+  fn intern(string: &str) -> Self {
+    if let Some(symbol) = Symbol::existing(string) {
+      return symbol;
+    }
+
+    let mut symbols = symbols().write().unwrap();
+    if let Some(symbol) = symbols.get(string) {
+      return *symbol;
+    }
+    let index = symbols.len();
+    let chunk = STRINGS
+      .get(index / CHUNK_LENGTH)
+      .expect("more ids than the interner can hold")
+      .get_or_init(|| (0..CHUNK_LENGTH).map(|_| OnceLock::new()).collect());
+    let slot = &chunk[index % CHUNK_LENGTH];
+    let _ = slot.set(Box::from(string));
+    let symbol = Symbol(index as u32);
+    symbols.insert(slot.get().unwrap(), symbol);
+    symbol
+  }
+
+  fn existing(string: &str) -> Option<Self> {
+    symbols().read().unwrap().get(string).copied()
+  }
+
+  fn as_str(self) -> &'static str {
+    let index = self.0 as usize;
+    STRINGS[index / CHUNK_LENGTH]
+      .get()
+      .and_then(|chunk| chunk[index % CHUNK_LENGTH].get())
+      .expect("symbols are only made by interning")
+  }
+}
+
+macro_rules! interned_id {
+  ($name:ident) => {
+    #[derive(Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct $name(Symbol);
+
+    impl $name {
+      pub fn as_str(&self) -> &'static str {
+        self.0.as_str()
+      }
+
+      // the id, if it was ever read from a spec, without interning it
+      pub fn existing(id: &str) -> Option<Self> {
+        Symbol::existing(id).map($name)
+      }
+    }
+
+    // only for ids read from a spec, see `interner`
+    impl From<&str> for $name {
+      fn from(id: &str) -> Self {
+        $name(Symbol::intern(id))
+      }
+    }
+
+    impl From<String> for $name {
+      fn from(id: String) -> Self {
+        $name(Symbol::intern(&id))
+      }
+    }
+
+    impl fmt::Display for $name {
+      fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+      }
+    }
+
+    impl fmt::Debug for $name {
+      fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+      }
+    }
+
+    impl Serialize for $name {
+      fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+      }
+    }
+
+    impl<'de> Deserialize<'de> for $name {
+      fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map($name::from)
+      }
+    }
+  };
+}
+
+// Requests
+interned_id!(PathComponentId);
+interned_id!(RequestId);
+interned_id!(RequestParameterId);
+interned_id!(ResponseId);
+
+// Shapes
+interned_id!(ShapeId);
+interned_id!(FieldId);
+interned_id!(ShapeParameterId);
+
+#[test]
+fn interned_ids_round_trip_to_their_strings() {
+  let shape_id = ShapeId::from("shape_Ba53AWXhVW");
+
+  assert_eq!(shape_id, ShapeId::from(String::from("shape_Ba53AWXhVW")));
+  assert_eq!(shape_id.as_str(), "shape_Ba53AWXhVW");
+  assert_ne!(shape_id, ShapeId::from("shape_kxrzRC2Klt"));
+  // the same id is only ever stored once, and a handle is just its index
+  assert!(std::ptr::eq(
+    shape_id.as_str(),
+    ShapeId::from("shape_Ba53AWXhVW").as_str()
+  ));
+  assert_eq!(std::mem::size_of::<ShapeId>(), 4);
+  // looking an id up doesn't intern it
+  assert_eq!(ShapeId::existing("shape_Ba53AWXhVW"), Some(shape_id));
+  assert_eq!(ShapeId::existing("never_interned_shape"), None);
+  assert_eq!(ShapeId::existing("never_interned_shape"), None);
+  assert_eq!(
+    serde_json::to_string(&shape_id).unwrap(),
+    "\"shape_Ba53AWXhVW\""
+  );
+  assert_eq!(
+    serde_json::from_str::<ShapeId>("\"shape_Ba53AWXhVW\"").unwrap(),
+    shape_id
+  );
+}
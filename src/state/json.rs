@@ -0,0 +1,131 @@
+use serde_json::Value;
+use std::collections::HashSet;
+
+use crate::aggregate::OpticState;
+
+// The JSON model of a folded spec, as printed by `fold`:
+//
+//   {
+//     "requests": {
+//       "pathComponents":    { <pathId>: { pathId, descriptor: { Basic | Parameterized }, isRemoved } },
+//       "requestParameters": { <parameterId>: { parameterId, requestParameterDescriptor, isRemoved } },
+//       "requests":          { <requestId>: { requestId, requestDescriptor: { pathComponentId,
+//                                httpMethod, bodies }, isRemoved } },
+//       "responses":         { <responseId>: { responseId, responseDescriptor: { pathId, httpMethod,
+//                                httpStatusCode, bodies }, isRemoved } }
+//     },
+//     "rfc": { "apiName", "contributions": { <id>: { <key>: <value> } }, "lastBatchId" },
+//     "shape": {
+//       "shapes": { <shapeId>: { shapeId, descriptor: { isUserDefined, baseShapeId, parameters,
+//                                fieldOrdering, name, bindings }, isRemoved } },
+//       "fields": { <fieldId>: { fieldId, descriptor: { shapeId, shapeDescriptor, name, bindings },
+//                                isRemoved } }
+//     }
+//   }
+//
+// where the bodies of a request or response are keyed by their content type:
+//
+//   "bodies": { <httpContentType>: { httpContentType, shapeId, isRemoved } }
+//
+// Entities are keyed by their id and listed in the order their events were applied. Enums like
+// descriptors are externally tagged by their variant name, matching the events they came from.
+// Unless removed entities are included, entities with `isRemoved` are left out, as are removed
+// bodies and the ids of removed fields in the `fieldOrdering` of shapes.
+pub fn state_to_json(state: &OpticState, include_removed: bool) -> serde_json::Result<Value> {
+  let mut json = serde_json::to_value(state)?;
+  if include_removed {
+    return Ok(json);
+  }
+
+  for (aggregate, entities) in &[
+    ("requests", "pathComponents"),
+    ("requests", "requestParameters"),
+    ("requests", "requests"),
+    ("requests", "responses"),
+    ("shape", "shapes"),
+    ("shape", "fields"),
+  ] {
+    if let Some(Value::Object(entities)) = json[*aggregate].get_mut(*entities) {
+      *entities = std::mem::take(entities)
+        .into_iter()
+        .filter(|(_, entity)| !is_removed(entity))
+        .collect();
+    }
+  }
+
+  for (entities, descriptor) in &[
+    ("requests", "requestDescriptor"),
+    ("responses", "responseDescriptor"),
+  ] {
+    if let Some(Value::Object(entities)) = json["requests"].get_mut(*entities) {
+      for entity in entities.values_mut() {
+        if let Some(Value::Object(bodies)) = entity[*descriptor].get_mut("bodies") {
+          *bodies = std::mem::take(bodies)
+            .into_iter()
+            .filter(|(_, body)| !is_removed(body))
+            .collect();
+        }
+      }
+    }
+  }
+
+  let live_fields: HashSet<String> = json["shape"]["fields"]
+    .as_object()
+    .map(|fields| fields.keys().cloned().collect())
+    .unwrap_or_default();
+  if let Some(Value::Object(shapes)) = json["shape"].get_mut("shapes") {
+    for shape in shapes.values_mut() {
+      if let Some(Value::Array(ordering)) = shape["descriptor"].get_mut("fieldOrdering") {
+        ordering.retain(|field_id| {
+          field_id
+            .as_str()
+            .is_some_and(|field_id| live_fields.contains(field_id))
+        });
+      }
+    }
+  }
+
+  Ok(json)
+}
+
+fn is_removed(entity: &Value) -> bool {
+  entity["isRemoved"].as_bool().unwrap_or(false)
+}
+
+#[test]
+fn removed_entities_are_left_out_unless_included() {
+  use crate::aggregate::{Aggregate, OpticAggregate};
+  use crate::events::OpticEvent;
+
+  let mut aggregate =
+    OpticAggregate::fold(crate::events_from_file("test-fixtures/uncompacted-spec.json").unwrap());
+  let removals: Vec<OpticEvent> = serde_json::from_str(
+    r#"[
+      {"FieldRemoved":{"fieldId":"EQSZqM_12","eventContext":null}},
+      {"ResponseRemoved":{"responseId":"response_WkmtI23TF7","eventContext":null}}
+    ]"#,
+  )
+  .unwrap();
+  for event in removals {
+    aggregate.apply(event);
+  }
+  let state = aggregate.get_state();
+
+  let json = state_to_json(&state, false).unwrap();
+  assert!(json["shape"]["fields"].get("EQSZqM_12").is_none());
+  assert!(json["requests"]["responses"]
+    .get("response_WkmtI23TF7")
+    .is_none());
+  let ordering = &json["shape"]["shapes"]["EQSZqM_11"]["descriptor"]["fieldOrdering"];
+  assert!(!ordering
+    .as_array()
+    .unwrap()
+    .contains(&Value::from("EQSZqM_12")));
+
+  let json = state_to_json(&state, true).unwrap();
+  assert_eq!(json["shape"]["fields"]["EQSZqM_12"]["isRemoved"], true);
+  assert_eq!(
+    json["requests"]["responses"]["response_WkmtI23TF7"]["isRemoved"],
+    true
+  );
+}
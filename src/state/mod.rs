@@ -1,2 +1,6 @@
+pub mod http;
+pub mod ids;
+pub mod json;
+pub mod provenance;
 pub mod requests;
 pub mod shape;
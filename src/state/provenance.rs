@@ -0,0 +1,136 @@
+use indexmap::IndexMap;
+use std::fmt;
+
+use super::requests::{PathComponentId, RequestId, RequestParameterId, ResponseId};
+use super::shape::{FieldId, ShapeId, ShapeParameterId};
+use crate::events::EventContext;
+
+// Who created every entity of the spec, and who last changed it, from the contexts of the events
+// that did. Entities that were changed by events without a context (like compacted ones) are
+// attributed to no one.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProvenanceState {
+  #[serde(with = "indexmap::serde_seq")]
+  entities: IndexMap<EntityId, Provenance>,
+  // the batch commit events are applied in, as blame attributes them
+  current_batch: Option<Batch>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum EntityId {
+  PathComponent(PathComponentId),
+  RequestParameter(RequestParameterId),
+  Request(RequestId),
+  Response(ResponseId),
+  Shape(ShapeId),
+  ShapeParameter(ShapeParameterId),
+  Field(FieldId),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Provenance {
+  // unknown for entities that exist without being added, like the root path
+  pub created: Option<Change>,
+  pub last_modified: Change,
+  pub changes: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Change {
+  pub event_type: String,
+  pub event_context: Option<EventContext>,
+  // the batch commit the event was committed in, if any
+  pub batch: Option<Batch>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Batch {
+  pub batch_id: String,
+  pub commit_message: String,
+}
+
+impl ProvenanceState {
+  pub fn with_batch_started(&mut self, batch_id: &str, commit_message: &str) {
+    self.current_batch = Some(Batch {
+      batch_id: String::from(batch_id),
+      commit_message: String::from(commit_message.trim()),
+    });
+  }
+
+  pub fn with_batch_ended(&mut self) {
+    self.current_batch = None;
+  }
+
+  pub fn current_batch(&self) -> Option<&Batch> {
+    self.current_batch.as_ref()
+  }
+
+  pub fn with_change(&mut self, entity_id: EntityId, change: Change, is_creation: bool) {
+    match self.entities.get_mut(&entity_id) {
+      Some(provenance) => {
+        provenance.last_modified = change;
+        provenance.changes += 1;
+      }
+      None => {
+        self.entities.insert(
+          entity_id,
+          Provenance {
+            created: if is_creation {
+              Some(change.clone())
+            } else {
+              None
+            },
+            last_modified: change,
+            changes: 1,
+          },
+        );
+      }
+    }
+  }
+
+  // who created and last modified an entity, if any event ever changed it
+  pub fn blame(&self, entity_id: EntityId) -> Option<&Provenance> {
+    self.entities.get(&entity_id)
+  }
+
+  pub fn all_entities(&self) -> impl Iterator<Item = (&EntityId, &Provenance)> {
+    self.entities.iter()
+  }
+}
+
+impl fmt::Display for EntityId {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      EntityId::PathComponent(id) => write!(f, "path {}", id),
+      EntityId::RequestParameter(id) => write!(f, "request parameter {}", id),
+      EntityId::Request(id) => write!(f, "request {}", id),
+      EntityId::Response(id) => write!(f, "response {}", id),
+      EntityId::Shape(id) => write!(f, "shape {}", id),
+      EntityId::ShapeParameter(id) => write!(f, "shape parameter {}", id),
+      EntityId::Field(id) => write!(f, "field {}", id),
+    }
+  }
+}
+
+impl fmt::Display for Change {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match &self.event_context {
+      Some(context) => write!(
+        f,
+        "{} by {} at {}",
+        self.event_type,
+        context.client_id(),
+        context.created_at()
+      )?,
+      None => write!(f, "{} by (unknown)", self.event_type)?,
+    }
+    match &self.batch {
+      Some(batch) => write!(f, " (batch {})", batch.batch_id),
+      None => write!(f, " (outside of batches)"),
+    }
+  }
+}
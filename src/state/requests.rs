@@ -1,104 +1,268 @@
-use super::shape::ShapeId;
-use std::collections::hash_map::HashMap;
-// use std::collections::HashMap;
+use super::http::{HttpMethod, StatusCode};
+pub use super::ids::{PathComponentId, RequestId, RequestParameterId, ResponseId};
+use super::shape::{ShapeId, UNKNOWN_SHAPE_ID};
+use indexmap::IndexMap;
+use std::fmt;
 
-#[derive(Default, Debug)]
+pub const ROOT_PATH_ID: &str = "root";
+
+// entities are kept in the order their events were applied, so every projection is deterministic
+#[derive(Default, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct RequestsState {
-  path_components: HashMap<PathComponentId, PathComponent>,
-  parent_path: HashMap<PathComponentId, PathComponentId>,
-  request_parameters: HashMap<RequestParameterId, HttpRequestParameter>,
-  requests: HashMap<RequestId, HttpRequest>,
-  responses: HashMap<ResponseId, HttpResponse>,
+  path_components: IndexMap<PathComponentId, PathComponent>,
+  request_parameters: IndexMap<RequestParameterId, HttpRequestParameter>,
+  requests: IndexMap<RequestId, HttpRequest>,
+  responses: IndexMap<ResponseId, HttpResponse>,
 }
 
-pub type PathComponentId = String;
-pub type RequestId = String;
-pub type RequestParameterId = String;
-pub type ResponseId = String;
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PathComponent {
+  pub path_id: PathComponentId,
+  pub descriptor: PathComponentDescriptor,
+  pub is_removed: bool,
+}
 
-#[derive(Debug)]
-struct PathComponent {
-  path_id: PathComponentId,
-  descriptor: PathComponentDescriptor,
-  is_removed: bool,
+#[derive(Debug, Serialize, Deserialize)]
+pub enum PathComponentDescriptor {
+  Basic(BasicPathComponentDescriptor),
+  Parameterized(ParameterizedPathComponentDescriptor),
 }
 
-#[derive(Debug)]
-struct PathComponentDescriptor {
-  parent_path_id: PathComponentId,
-  name: String,
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BasicPathComponentDescriptor {
+  pub parent_path_id: PathComponentId,
+  pub name: String,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParameterizedPathComponentDescriptor {
+  pub parent_path_id: PathComponentId,
+  pub name: String,
+  pub shape_descriptor: RequestParameterShapeDescriptor,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct HttpRequest {
   pub request_id: RequestId,
   pub request_descriptor: RequestDescriptor,
   pub is_removed: bool,
 }
 
-#[derive(Debug)]
-struct HttpRequestParameter {
-  parameter_id: RequestParameterId,
-  request_parameter_descriptor: RequestParameterDescriptor,
-  is_removed: bool,
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HttpRequestParameter {
+  pub parameter_id: RequestParameterId,
+  pub request_parameter_descriptor: RequestParameterDescriptor,
+  pub is_removed: bool,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct HttpResponse {
   pub response_id: ResponseId,
   pub response_descriptor: ResponseDescriptor,
   pub is_removed: bool,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct RequestDescriptor {
   pub path_component_id: PathComponentId,
-  pub http_method: String,
-  pub body_descriptor: BodyDescriptor,
+  pub http_method: HttpMethod,
+  pub bodies: Bodies,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ResponseDescriptor {
   pub path_id: PathComponentId,
-  pub http_method: String,
-  pub http_status_code: u16,
-  pub body_descriptor: BodyDescriptor,
+  pub http_method: HttpMethod,
+  pub http_status_code: StatusCode,
+  pub bodies: Bodies,
 }
 
-#[derive(Debug)]
-pub enum BodyDescriptor {
-  Unset,
-  Shaped(ShapedBodyDescriptor),
-}
+// the bodies of a request or response by their content type, in the order they were set
+pub type Bodies = IndexMap<String, ShapedBodyDescriptor>;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ShapedBodyDescriptor {
-  http_content_type: String,
-  shape_id: ShapeId,
-  is_removed: bool,
+  pub http_content_type: String,
+  pub shape_id: ShapeId,
+  pub is_removed: bool,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct RequestParameterDescriptor {
-  path_id: RequestId,
-  http_method: String,
-  location: String,
-  name: String,
-  shape_descriptor: RequestParameterShapeDescriptor, // bodyDescriptor: BodyDescriptor
+  pub path_id: PathComponentId,
+  pub http_method: HttpMethod,
+  pub location: ParameterLocation,
+  pub name: String,
+  pub shape_descriptor: RequestParameterShapeDescriptor, // bodyDescriptor: BodyDescriptor
 }
 
-#[derive(Debug)]
-enum RequestParameterShapeDescriptor {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ParameterLocation {
+  Query,
+  Header,
+  Cookie,
+  Path,
+}
+
+impl ParameterLocation {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      ParameterLocation::Query => "query",
+      ParameterLocation::Header => "header",
+      ParameterLocation::Cookie => "cookie",
+      ParameterLocation::Path => "path",
+    }
+  }
+
+  // header names are case insensitive, all other parameter names aren't
+  pub fn names_match(&self, name: &str, other: &str) -> bool {
+    match self {
+      ParameterLocation::Header => name.eq_ignore_ascii_case(other),
+      _ => name == other,
+    }
+  }
+}
+
+impl fmt::Display for ParameterLocation {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str(self.as_str())
+  }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RequestParameterError {
+  Duplicate {
+    name: String,
+    location: ParameterLocation,
+  },
+}
+
+impl fmt::Display for RequestParameterError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      RequestParameterError::Duplicate { name, location } => write!(
+        f,
+        "the endpoint already has a {} parameter named '{}'",
+        location, name
+      ),
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum RequestParameterShapeDescriptor {
   Unset,
   Shaped(ShapedRequestParameterShapeDescriptor),
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ShapedRequestParameterShapeDescriptor {
-  shape_id: ShapeId,
-  is_removed: bool,
+  pub shape_id: ShapeId,
+  pub is_removed: bool,
+}
+
+impl PathComponentDescriptor {
+  pub fn parent_path_id(&self) -> PathComponentId {
+    match self {
+      PathComponentDescriptor::Basic(descriptor) => descriptor.parent_path_id,
+      PathComponentDescriptor::Parameterized(descriptor) => descriptor.parent_path_id,
+    }
+  }
+
+  pub fn name(&self) -> &str {
+    match self {
+      PathComponentDescriptor::Basic(descriptor) => &descriptor.name,
+      PathComponentDescriptor::Parameterized(descriptor) => &descriptor.name,
+    }
+  }
+
+  pub fn is_parameter(&self) -> bool {
+    match self {
+      PathComponentDescriptor::Basic(_) => false,
+      PathComponentDescriptor::Parameterized(_) => true,
+    }
+  }
+}
+
+impl RequestParameterShapeDescriptor {
+  pub fn shaped(&self) -> Option<&ShapedRequestParameterShapeDescriptor> {
+    match self {
+      RequestParameterShapeDescriptor::Unset => None,
+      RequestParameterShapeDescriptor::Shaped(descriptor) => Some(descriptor),
+    }
+  }
+}
+
+// the bodies that aren't removed, in the order they were set
+pub fn live_bodies(bodies: &Bodies) -> impl Iterator<Item = &ShapedBodyDescriptor> {
+  bodies.values().filter(|body| !body.is_removed)
+}
+
+fn with_body(bodies: &mut Bodies, body_descriptor: ShapedBodyDescriptor) {
+  bodies.insert(body_descriptor.http_content_type.clone(), body_descriptor);
+}
+
+fn without_body(bodies: &mut Bodies, http_content_type: Option<String>) {
+  match http_content_type {
+    Some(http_content_type) => {
+      bodies.shift_remove(&http_content_type);
+    }
+    None => bodies.clear(),
+  }
+}
+
+// Changes the content type of the body, keeping its shape and its place. A content type set before
+// any shape describes a body of unknown shape.
+fn with_content_type(bodies: &mut Bodies, http_content_type: String) {
+  let current = live_bodies(bodies)
+    .next()
+    .map(|body| body.http_content_type.clone());
+  match current {
+    // the body of the content type is already there
+    Some(current)
+      if current == http_content_type
+        || bodies
+          .get(&http_content_type)
+          .is_some_and(|body| !body.is_removed) => {}
+    Some(current) => {
+      // a removed body of the new content type is replaced
+      bodies.shift_remove(&http_content_type);
+      *bodies = std::mem::take(bodies)
+        .into_iter()
+        .map(|(key, mut body)| {
+          if key == current {
+            body.http_content_type = http_content_type.clone();
+            (http_content_type.clone(), body)
+          } else {
+            (key, body)
+          }
+        })
+        .collect();
+    }
+    None => {
+      bodies.insert(
+        http_content_type.clone(),
+        ShapedBodyDescriptor {
+          http_content_type,
+          shape_id: ShapeId::from(UNKNOWN_SHAPE_ID),
+          is_removed: false,
+        },
+      );
+    }
+  }
 }
 
 impl RequestsState {
@@ -110,6 +274,53 @@ impl RequestsState {
     self.responses.values()
   }
 
+  pub fn all_path_components(&self) -> impl Iterator<Item = &PathComponent> {
+    self.path_components.values()
+  }
+
+  pub fn all_request_parameters(&self) -> impl Iterator<Item = &HttpRequestParameter> {
+    self.request_parameters.values()
+  }
+
+  pub fn path_component(&self, path_id: PathComponentId) -> Option<&PathComponent> {
+    self.path_components.get(&path_id)
+  }
+
+  pub fn request_parameter(
+    &self,
+    parameter_id: RequestParameterId,
+  ) -> Option<&HttpRequestParameter> {
+    self.request_parameters.get(&parameter_id)
+  }
+
+  pub fn request(&self, request_id: RequestId) -> Option<&HttpRequest> {
+    self.requests.get(&request_id)
+  }
+
+  pub fn response(&self, response_id: ResponseId) -> Option<&HttpResponse> {
+    self.responses.get(&response_id)
+  }
+
+  // The absolute path a path component describes, with path parameters as `{name}`
+  pub fn absolute_path(&self, path_id: PathComponentId) -> String {
+    let mut names = vec![];
+    let mut current = self.path_components.get(&path_id);
+    while let Some(component) = current {
+      let name = component.descriptor.name();
+      if component.descriptor.is_parameter() {
+        names.push(format!("{{{}}}", name));
+      } else {
+        names.push(String::from(name));
+      }
+      current = self
+        .path_components
+        .get(&component.descriptor.parent_path_id());
+    }
+    names.reverse();
+
+    format!("/{}", names.join("/"))
+  }
+
   // Path components
   // ---------------
   pub fn with_path_component(
@@ -119,55 +330,194 @@ impl RequestsState {
     name: String,
   ) {
     self.path_components.insert(
-      path_id.clone(),
+      path_id,
       PathComponent {
-        path_id: path_id.clone(),
-        descriptor: PathComponentDescriptor {
+        path_id,
+        descriptor: PathComponentDescriptor::Basic(BasicPathComponentDescriptor {
           parent_path_id,
           name,
-        },
+        }),
+        is_removed: false,
+      },
+    );
+  }
+
+  pub fn with_path_parameter(
+    &mut self,
+    path_id: PathComponentId,
+    parent_path_id: PathComponentId,
+    name: String,
+  ) {
+    self.path_components.insert(
+      path_id,
+      PathComponent {
+        path_id,
+        descriptor: PathComponentDescriptor::Parameterized(ParameterizedPathComponentDescriptor {
+          parent_path_id,
+          name,
+          shape_descriptor: RequestParameterShapeDescriptor::Unset,
+        }),
         is_removed: false,
       },
     );
   }
 
+  pub fn with_path_parameter_shape(
+    &mut self,
+    path_id: PathComponentId,
+    shape_descriptor: ShapedRequestParameterShapeDescriptor,
+  ) {
+    let component = self
+      .path_components
+      .get_mut(&path_id)
+      .expect("path parameter must exist to set its shape");
+    match &mut component.descriptor {
+      PathComponentDescriptor::Parameterized(descriptor) => {
+        descriptor.shape_descriptor = RequestParameterShapeDescriptor::Shaped(shape_descriptor)
+      }
+      PathComponentDescriptor::Basic(_) => {
+        panic!("path component must be a path parameter to set its shape")
+      }
+    }
+  }
+
+  pub fn with_path_component_name(&mut self, path_id: PathComponentId, name: String) {
+    let component = self
+      .path_components
+      .get_mut(&path_id)
+      .expect("path component must exist to rename it");
+    match &mut component.descriptor {
+      PathComponentDescriptor::Basic(descriptor) => descriptor.name = name,
+      PathComponentDescriptor::Parameterized(descriptor) => descriptor.name = name,
+    }
+  }
+
+  pub fn without_path_component(&mut self, path_id: PathComponentId) {
+    let component = self
+      .path_components
+      .get_mut(&path_id)
+      .expect("path component must exist to remove it");
+    component.is_removed = true;
+  }
+
+  // Reverting the addition of an entity forgets it, as if it was never added
+  pub fn forget_path_component(&mut self, path_id: PathComponentId) {
+    self.path_components.shift_remove(&path_id);
+  }
+
   // Requests
   // --------
   pub fn with_request(
     &mut self,
     request_id: RequestId,
     path_id: PathComponentId,
-    http_method: String,
+    http_method: HttpMethod,
   ) {
     self.requests.insert(
-      request_id.clone(),
+      request_id,
       HttpRequest {
-        request_id: request_id.clone(),
+        request_id,
         request_descriptor: RequestDescriptor {
           path_component_id: path_id,
           http_method,
-          body_descriptor: BodyDescriptor::Unset,
+          bodies: Bodies::new(),
         },
         is_removed: false,
       },
     );
   }
 
+  pub fn with_request_body(
+    &mut self,
+    request_id: RequestId,
+    body_descriptor: ShapedBodyDescriptor,
+  ) {
+    let request = self
+      .requests
+      .get_mut(&request_id)
+      .expect("request must exist to set body for it");
+    with_body(&mut request.request_descriptor.bodies, body_descriptor);
+  }
+
+  pub fn without_request_body(&mut self, request_id: RequestId, http_content_type: Option<String>) {
+    let request = self
+      .requests
+      .get_mut(&request_id)
+      .expect("request must exist to unset body for it");
+    without_body(&mut request.request_descriptor.bodies, http_content_type);
+  }
+
+  pub fn with_request_content_type(&mut self, request_id: RequestId, http_content_type: String) {
+    let request = self
+      .requests
+      .get_mut(&request_id)
+      .expect("request must exist to set content type for it");
+    with_content_type(&mut request.request_descriptor.bodies, http_content_type);
+  }
+
+  pub fn without_request(&mut self, request_id: RequestId) {
+    let request = self
+      .requests
+      .get_mut(&request_id)
+      .expect("request must exist to remove it");
+    request.is_removed = true;
+  }
+
+  pub fn forget_request(&mut self, request_id: RequestId) {
+    self.requests.shift_remove(&request_id);
+  }
+
   // Request parameters
   // ------------------
 
+  // The live parameters of an endpoint in one location, in the order they were added
+  pub fn request_parameters_at<'a>(
+    &'a self,
+    path_id: PathComponentId,
+    http_method: &'a HttpMethod,
+    location: ParameterLocation,
+  ) -> impl Iterator<Item = &'a HttpRequestParameter> {
+    self.request_parameters.values().filter(move |parameter| {
+      let descriptor = &parameter.request_parameter_descriptor;
+      !parameter.is_removed
+        && descriptor.path_id == path_id
+        && &descriptor.http_method == http_method
+        && descriptor.location == location
+    })
+  }
+
+  // An endpoint can't have two parameters of the same name in the same location
+  pub fn validate_request_parameter(
+    &self,
+    path_id: PathComponentId,
+    http_method: &HttpMethod,
+    location: ParameterLocation,
+    name: &str,
+  ) -> Result<(), RequestParameterError> {
+    let mut existing = self.request_parameters_at(path_id, http_method, location);
+    if existing
+      .any(|parameter| location.names_match(&parameter.request_parameter_descriptor.name, name))
+    {
+      return Err(RequestParameterError::Duplicate {
+        name: String::from(name),
+        location,
+      });
+    }
+    Ok(())
+  }
+
   pub fn with_request_parameter_by_path_and_method(
     &mut self,
     parameter_id: RequestParameterId,
     path_id: PathComponentId,
-    http_method: String,
-    parameter_location: String,
+    http_method: HttpMethod,
+    parameter_location: ParameterLocation,
     name: String,
   ) {
     self.request_parameters.insert(
-      parameter_id.clone(),
+      parameter_id,
       HttpRequestParameter {
-        parameter_id: parameter_id.clone(),
+        parameter_id,
         request_parameter_descriptor: RequestParameterDescriptor {
           path_id,
           http_method,
@@ -194,6 +544,35 @@ impl RequestsState {
       RequestParameterShapeDescriptor::Shaped(parameter_shape_descriptor);
   }
 
+  pub fn without_request_parameter_shape(&mut self, parameter_id: RequestParameterId) {
+    let parameter = self
+      .request_parameters
+      .get_mut(&parameter_id)
+      .expect("request parameter must exist to unset its shape");
+    parameter.request_parameter_descriptor.shape_descriptor =
+      RequestParameterShapeDescriptor::Unset;
+  }
+
+  pub fn with_request_parameter_name(&mut self, parameter_id: RequestParameterId, name: String) {
+    let parameter = self
+      .request_parameters
+      .get_mut(&parameter_id)
+      .expect("request parameter must exist to rename it");
+    parameter.request_parameter_descriptor.name = name;
+  }
+
+  pub fn without_request_parameter(&mut self, parameter_id: RequestParameterId) {
+    let parameter = self
+      .request_parameters
+      .get_mut(&parameter_id)
+      .expect("request parameter must exist to remove it");
+    parameter.is_removed = true;
+  }
+
+  pub fn forget_request_parameter(&mut self, parameter_id: RequestParameterId) {
+    self.request_parameters.shift_remove(&parameter_id);
+  }
+
   // Responses
   // ---------
 
@@ -201,30 +580,77 @@ impl RequestsState {
     &mut self,
     response_id: ResponseId,
     path_id: PathComponentId,
-    http_method: String,
-    http_status_code: u16,
+    http_method: HttpMethod,
+    http_status_code: StatusCode,
   ) {
     self.responses.insert(
-      response_id.clone(),
+      response_id,
       HttpResponse {
-        response_id: response_id.clone(),
+        response_id,
         response_descriptor: ResponseDescriptor {
           path_id,
           http_method,
           http_status_code,
-          body_descriptor: BodyDescriptor::Unset,
+          bodies: Bodies::new(),
         },
         is_removed: false,
       },
     );
   }
 
-  pub fn with_response_body(&mut self, response_id: ResponseId, body_descriptor: BodyDescriptor) {
+  pub fn with_response_status_code(
+    &mut self,
+    response_id: ResponseId,
+    http_status_code: StatusCode,
+  ) {
+    let response = self
+      .responses
+      .get_mut(&response_id)
+      .expect("response must exist to set status code for it");
+    response.response_descriptor.http_status_code = http_status_code;
+  }
+
+  pub fn with_response_body(
+    &mut self,
+    response_id: ResponseId,
+    body_descriptor: ShapedBodyDescriptor,
+  ) {
     let response = self
       .responses
       .get_mut(&response_id)
       .expect("response must exist to set body for it");
-    let response_descriptor = &mut response.response_descriptor;
-    response_descriptor.body_descriptor = body_descriptor;
+    with_body(&mut response.response_descriptor.bodies, body_descriptor);
+  }
+
+  pub fn without_response_body(
+    &mut self,
+    response_id: ResponseId,
+    http_content_type: Option<String>,
+  ) {
+    let response = self
+      .responses
+      .get_mut(&response_id)
+      .expect("response must exist to unset body for it");
+    without_body(&mut response.response_descriptor.bodies, http_content_type);
+  }
+
+  pub fn with_response_content_type(&mut self, response_id: ResponseId, http_content_type: String) {
+    let response = self
+      .responses
+      .get_mut(&response_id)
+      .expect("response must exist to set content type for it");
+    with_content_type(&mut response.response_descriptor.bodies, http_content_type);
+  }
+
+  pub fn without_response(&mut self, response_id: ResponseId) {
+    let response = self
+      .responses
+      .get_mut(&response_id)
+      .expect("response must exist to remove it");
+    response.is_removed = true;
+  }
+
+  pub fn forget_response(&mut self, response_id: ResponseId) {
+    self.responses.shift_remove(&response_id);
   }
 }
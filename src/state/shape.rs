@@ -1,84 +1,242 @@
-use std::collections::HashMap;
+pub use super::ids::{FieldId, ShapeId, ShapeParameterId};
+use indexmap::IndexMap;
 
-#[derive(Default, Debug)]
+// entities are kept in the order their events were applied, so every projection is deterministic
+#[derive(Default, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ShapeState {
-  shapes: HashMap<ShapeId, ShapeEntity>,
-  fields: HashMap<FieldId, FieldEntity>,
+  shapes: IndexMap<ShapeId, ShapeEntity>,
+  fields: IndexMap<FieldId, FieldEntity>,
 }
 
-pub type ShapeId = String;
-pub type FieldId = String;
-type ShapeParameterId = String;
-
-#[derive(Debug)]
-struct ShapeValue {
-  is_user_defined: bool,
-  base_shape_id: ShapeId,
-  parameters: ShapeParametersDescriptor,
-  field_ordering: Vec<FieldId>,
-  name: String,
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShapeValue {
+  pub is_user_defined: bool,
+  pub base_shape_id: ShapeId,
+  pub parameters: ShapeParametersDescriptor,
+  pub field_ordering: Vec<FieldId>,
+  pub name: String,
+  pub bindings: IndexMap<ShapeParameterId, ProviderDescriptor>,
 }
 
-#[derive(Debug)]
-struct ShapeEntity {
-  shape_id: ShapeId,
-  descriptor: ShapeValue,
-  is_removed: bool,
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShapeEntity {
+  pub shape_id: ShapeId,
+  pub descriptor: ShapeValue,
+  pub is_removed: bool,
 }
 
-#[derive(Debug)]
-struct FieldEntity {
-  field_id: FieldId,
-  descriptor: FieldValue,
-  is_removed: bool,
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldEntity {
+  pub field_id: FieldId,
+  pub descriptor: FieldValue,
+  pub is_removed: bool,
 }
 
-#[derive(Debug)]
-struct FieldValue {
-  shape_id: ShapeId,
-  shape_descriptor: FieldShapeDescriptor,
-  name: String,
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldValue {
+  pub shape_id: ShapeId,
+  pub shape_descriptor: FieldShapeDescriptor,
+  pub name: String,
+  pub bindings: IndexMap<ShapeParameterId, ProviderDescriptor>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[allow(clippy::enum_variant_names)] // variant names are part of the serialized format
 pub enum ShapeParametersDescriptor {
   NoParameterList,
   StaticParameterList(StaticShapeParametersDescriptor),
   DynamicParameterList(DynamicShapeParametersDescriptor),
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct StaticShapeParametersDescriptor {
-  shape_parameter_ids: Vec<ShapeParameterId>,
+  pub shape_parameter_ids: Vec<ShapeParameterId>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DynamicShapeParametersDescriptor {
-  shape_parameter_ids: Vec<ShapeParameterId>,
+  pub shape_parameter_ids: Vec<ShapeParameterId>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum FieldShapeDescriptor {
   FieldShapeFromShape(FieldShapeFromShape),
   FieldShapeFromParameter(FieldShapeFromParameter),
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct FieldShapeFromShape {
-  field_id: FieldId,
-  shape_id: ShapeId,
+  pub field_id: FieldId,
+  pub shape_id: ShapeId,
 }
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct FieldShapeFromParameter {
-  field_id: FieldId,
-  shape_parameter_id: ShapeParameterId,
+  pub field_id: FieldId,
+  pub shape_parameter_id: ShapeParameterId,
+}
+
+// Shape parameter bindings
+// ------------------------
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ParameterShapeDescriptor {
+  ProviderInShape(ProviderInShape),
+  ProviderInField(ProviderInField),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderInShape {
+  pub shape_id: ShapeId,
+  pub provider_descriptor: ProviderDescriptor,
+  pub consuming_parameter_id: ShapeParameterId,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderInField {
+  pub field_id: FieldId,
+  pub provider_descriptor: ProviderDescriptor,
+  pub consuming_parameter_id: ShapeParameterId,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[allow(clippy::enum_variant_names)] // variant names are part of the serialized format
+pub enum ProviderDescriptor {
+  ShapeProvider(ShapeProvider),
+  ParameterProvider(ParameterProvider),
+  NoProvider(NoProvider),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShapeProvider {
+  pub shape_id: ShapeId,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParameterProvider {
+  pub shape_parameter_id: ShapeParameterId,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoProvider {}
+
+impl FieldShapeDescriptor {
+  pub fn field_id(&self) -> FieldId {
+    match self {
+      FieldShapeDescriptor::FieldShapeFromShape(descriptor) => descriptor.field_id,
+      FieldShapeDescriptor::FieldShapeFromParameter(descriptor) => descriptor.field_id,
+    }
+  }
+
+  pub fn shape_id(&self) -> Option<ShapeId> {
+    match self {
+      FieldShapeDescriptor::FieldShapeFromShape(descriptor) => Some(descriptor.shape_id),
+      FieldShapeDescriptor::FieldShapeFromParameter(_) => None,
+    }
+  }
+}
+
+impl ProviderDescriptor {
+  pub fn shape_id(&self) -> Option<ShapeId> {
+    match self {
+      ProviderDescriptor::ShapeProvider(provider) => Some(provider.shape_id),
+      _ => None,
+    }
+  }
 }
 
 impl ShapeState {
+  pub fn all_shapes(&self) -> impl Iterator<Item = &ShapeEntity> {
+    self.shapes.values()
+  }
+
+  pub fn all_fields(&self) -> impl Iterator<Item = &FieldEntity> {
+    self.fields.values()
+  }
+
+  pub fn shape(&self, shape_id: ShapeId) -> Option<&ShapeEntity> {
+    self.shapes.get(&shape_id)
+  }
+
+  pub fn field(&self, field_id: FieldId) -> Option<&FieldEntity> {
+    self.fields.get(&field_id)
+  }
+
+  // Resolution
+  // ----------
+
+  // Follows the chain of base shapes until it reaches the core shape (`$object`, `$list`, ..) a
+  // shape is ultimately built on. Unknown shapes and cyclic chains resolve to `$unknown`.
+  pub fn core_shape_id(&self, shape_id: ShapeId) -> ShapeId {
+    let mut current = shape_id;
+    for _ in 0..=self.shapes.len() {
+      if is_core_shape(current) {
+        return current;
+      }
+      match self.shapes.get(&current) {
+        Some(shape) => current = shape.descriptor.base_shape_id,
+        None => break,
+      }
+    }
+    ShapeId::from(UNKNOWN_SHAPE_ID)
+  }
+
+  // The shape bound to a shape parameter (like `$listItem`), looking through base shapes
+  pub fn bound_shape_id(&self, shape_id: ShapeId, parameter_id: &str) -> Option<ShapeId> {
+    let parameter_id = ShapeParameterId::from(parameter_id);
+    self
+      .base_shape_chain(shape_id)
+      .into_iter()
+      .find_map(|shape| shape.descriptor.bindings.get(&parameter_id))
+      .and_then(ProviderDescriptor::shape_id)
+  }
+
+  // The fields of a shape including those of its base shapes, base fields first
+  pub fn resolved_fields_of(&self, shape_id: ShapeId) -> Vec<&FieldEntity> {
+    let mut chain = self.base_shape_chain(shape_id);
+    chain.reverse();
+    chain
+      .into_iter()
+      .flat_map(|shape| self.fields_of(shape.shape_id))
+      .collect()
+  }
+
+  fn base_shape_chain(&self, shape_id: ShapeId) -> Vec<&ShapeEntity> {
+    let mut chain: Vec<&ShapeEntity> = vec![];
+    let mut current = self.shapes.get(&shape_id);
+    while let Some(shape) = current {
+      if chain.iter().any(|seen| seen.shape_id == shape.shape_id) {
+        break;
+      }
+      chain.push(shape);
+      current = self.shapes.get(&shape.descriptor.base_shape_id);
+    }
+    chain
+  }
+
+  // The fields of a shape in their defined order, leaving out removed fields
+  pub fn fields_of(&self, shape_id: ShapeId) -> impl Iterator<Item = &FieldEntity> {
+    self
+      .shapes
+      .get(&shape_id)
+      .into_iter()
+      .flat_map(|shape| shape.descriptor.field_ordering.iter())
+      .filter_map(move |field_id| self.fields.get(field_id))
+      .filter(|field| !field.is_removed)
+  }
+
   pub fn with_shape(
     &mut self,
     shape_id: ShapeId,
@@ -87,15 +245,16 @@ impl ShapeState {
     name: String,
   ) {
     self.shapes.insert(
-      shape_id.clone(),
+      shape_id,
       ShapeEntity {
-        shape_id: shape_id.clone(),
+        shape_id,
         descriptor: ShapeValue {
-          is_user_defined: true,
+          is_user_defined: !is_core_shape(shape_id),
           base_shape_id: assigned_shape_id,
           parameters,
           name,
           field_ordering: vec![],
+          bindings: IndexMap::new(),
         },
         is_removed: false,
       },
@@ -114,24 +273,120 @@ impl ShapeState {
       .get_mut(&shape_id)
       .expect("shape must exist to add field for it");
 
-    shape.with_appended_field_id(field_id.clone());
+    shape.with_appended_field_id(field_id);
     self.fields.insert(
-      field_id.clone(),
+      field_id,
       FieldEntity {
-        field_id: field_id.clone(),
+        field_id,
         descriptor: FieldValue {
           shape_id,
           shape_descriptor,
           name,
+          bindings: IndexMap::new(),
         },
         is_removed: false,
       },
     );
   }
+
+  pub fn with_shape_name(&mut self, shape_id: ShapeId, name: String) {
+    let shape = self
+      .shapes
+      .get_mut(&shape_id)
+      .expect("shape must exist to rename it");
+    shape.descriptor.name = name;
+  }
+
+  pub fn with_base_shape(&mut self, shape_id: ShapeId, base_shape_id: ShapeId) {
+    let shape = self
+      .shapes
+      .get_mut(&shape_id)
+      .expect("shape must exist to set its base shape");
+    shape.descriptor.base_shape_id = base_shape_id;
+  }
+
+  pub fn with_field_name(&mut self, field_id: FieldId, name: String) {
+    let field = self
+      .fields
+      .get_mut(&field_id)
+      .expect("field must exist to rename it");
+    field.descriptor.name = name;
+  }
+
+  pub fn with_field_shape(&mut self, shape_descriptor: FieldShapeDescriptor) {
+    let field = self
+      .fields
+      .get_mut(&shape_descriptor.field_id())
+      .expect("field must exist to set its shape");
+    field.descriptor.shape_descriptor = shape_descriptor;
+  }
+
+  pub fn without_shape(&mut self, shape_id: ShapeId) {
+    let shape = self
+      .shapes
+      .get_mut(&shape_id)
+      .expect("shape must exist to remove it");
+    shape.is_removed = true;
+  }
+
+  pub fn without_field(&mut self, field_id: FieldId) {
+    let field = self
+      .fields
+      .get_mut(&field_id)
+      .expect("field must exist to remove it");
+    field.is_removed = true;
+  }
+
+  // Reverting the addition of a shape or field forgets it, as if it was never added
+  pub fn forget_shape(&mut self, shape_id: ShapeId) {
+    self.shapes.shift_remove(&shape_id);
+  }
+
+  pub fn forget_field(&mut self, field_id: FieldId) {
+    if let Some(field) = self.fields.shift_remove(&field_id) {
+      if let Some(shape) = self.shapes.get_mut(&field.descriptor.shape_id) {
+        shape.descriptor.field_ordering.retain(|id| *id != field_id);
+      }
+    }
+  }
+
+  pub fn with_parameter_shape(&mut self, descriptor: ParameterShapeDescriptor) {
+    match descriptor {
+      ParameterShapeDescriptor::ProviderInShape(binding) => {
+        let shape = self
+          .shapes
+          .get_mut(&binding.shape_id)
+          .expect("shape must exist to bind its parameter");
+        shape
+          .descriptor
+          .bindings
+          .insert(binding.consuming_parameter_id, binding.provider_descriptor);
+      }
+      ParameterShapeDescriptor::ProviderInField(binding) => {
+        let field = self
+          .fields
+          .get_mut(&binding.field_id)
+          .expect("field must exist to bind its parameter");
+        field
+          .descriptor
+          .bindings
+          .insert(binding.consuming_parameter_id, binding.provider_descriptor);
+      }
+    }
+  }
+}
+
+// Core shapes are built in and never added by events, their ids are prefixed with `$`
+pub const UNKNOWN_SHAPE_ID: &str = "$unknown";
+
+pub fn is_core_shape(shape_id: ShapeId) -> bool {
+  shape_id.as_str().starts_with('$')
 }
 
 impl ShapeEntity {
   pub fn with_appended_field_id(&mut self, field_id: FieldId) {
-    self.descriptor.field_ordering.push(field_id);
+    if !self.descriptor.field_ordering.contains(&field_id) {
+      self.descriptor.field_ordering.push(field_id);
+    }
   }
 }